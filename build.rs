@@ -3,7 +3,7 @@ use std::process::Command;
 
 fn head_path() -> String {
     let output = Command::new("git")
-        .args(&["rev-parse", "--git-dir"])
+        .args(["rev-parse", "--git-dir"])
         .output()
         .expect("Got $GIT_DIR");
     let git_dir = String::from_utf8_lossy(&output.stdout);
@@ -13,14 +13,14 @@ fn head_path() -> String {
 fn commits_since_release() -> String {
     let id = {
         let out = Command::new("git")
-            .args(&["rev-list", "-1", "HEAD", "--", "CHANGELOG.md"])
+            .args(["rev-list", "-1", "HEAD", "--", "CHANGELOG.md"])
             .output()
             .expect("A committed CHANGELOG.md");
         String::from_utf8_lossy(&out.stdout).to_string()
     };
     let range = format!("{}..HEAD", id.trim());
     let out = Command::new("git")
-        .args(&["rev-list", "--count", &range, "--", "."])
+        .args(["rev-list", "--count", &range, "--", "."])
         .output()
         .expect("git rev-list successful");
     String::from_utf8_lossy(&out.stdout)
@@ -35,7 +35,7 @@ fn main() {
     println!("cargo:rerun-if-changed={}", head_path());
 
     if let Ok(status) = Command::new("git")
-        .args(&["diff-index", "--quiet", "HEAD", "--"])
+        .args(["diff-index", "--quiet", "HEAD", "--"])
         .status()
     {
         let commits_since_release = commits_since_release();
@@ -48,7 +48,7 @@ fn main() {
             (false, false) => format!("{}+dirty", cargo_version),
             (true, clean) => {
                 let id_out = Command::new("git")
-                    .args(&["rev-parse", "--short", "HEAD"])
+                    .args(["rev-parse", "--short", "HEAD"])
                     .output()
                     .expect("Executed git-rev-parse(1)");
                 let id = String::from_utf8_lossy(&id_out.stdout).to_string();
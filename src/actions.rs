@@ -0,0 +1,471 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Git commands which can be run on the selected commit.
+
+use std::fmt::{Display, Formatter};
+use std::process::Command;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{style, Attribute, Stylize};
+use git_wrapper::Repository;
+
+use crate::commit::{Commit, Oid};
+use crate::ui::base::{shorten_line, suspend, Drawable, HandleEvent, StyledArea, StyledLine};
+use crate::ui::input::InputLine;
+
+/// Maximal number of shown output lines
+const MAX_OUTPUT: usize = 10;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Action {
+    Checkout,
+    CheckoutBranch,
+    CherryPick,
+    Revert,
+    Tag,
+    Branch,
+    RebaseInteractive,
+}
+
+impl Action {
+    const ALL: [Self; 7] = [
+        Self::Checkout,
+        Self::CheckoutBranch,
+        Self::CherryPick,
+        Self::Revert,
+        Self::Tag,
+        Self::Branch,
+        Self::RebaseInteractive,
+    ];
+
+    const fn key(self) -> char {
+        match self {
+            Self::Checkout => 'c',
+            Self::CheckoutBranch => 'C',
+            Self::CherryPick => 'p',
+            Self::Revert => 'v',
+            Self::Tag => 't',
+            Self::Branch => 'b',
+            Self::RebaseInteractive => 'r',
+        }
+    }
+
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Checkout => "checkout",
+            Self::CheckoutBranch => "checkout -b",
+            Self::CherryPick => "cherry-pick",
+            Self::Revert => "revert",
+            Self::Tag => "tag",
+            Self::Branch => "branch",
+            Self::RebaseInteractive => "rebase -i",
+        }
+    }
+
+    /// Return the prompt for actions needing a ref name
+    const fn prompt(self) -> Option<&'static str> {
+        match self {
+            Self::CheckoutBranch | Self::Branch => Some("Branch name: "),
+            Self::Tag => Some("Tag name: "),
+            _ => None,
+        }
+    }
+
+    /// Return the command for the commit. Merges are cherry-picked & reverted relative to
+    /// their first parent.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message, when the action needs a ref name & `name` is not a valid one.
+    fn command(self, commit: &Commit, name: &str) -> Result<GitCommand, String> {
+        let oid = commit.id().0.clone();
+        let mainline = || {
+            if commit.is_merge() {
+                vec!["-m".to_owned(), "1".to_owned()]
+            } else {
+                vec![]
+            }
+        };
+        let args = match self {
+            Self::Checkout => vec!["checkout".to_owned(), "--detach".to_owned(), oid],
+            Self::CheckoutBranch => {
+                validate_branch_name(name)?;
+                vec!["checkout".to_owned(), "-b".to_owned(), name.to_owned(), oid]
+            }
+            Self::CherryPick => [vec!["cherry-pick".to_owned()], mainline(), vec![oid]].concat(),
+            Self::Revert => [
+                vec!["revert".to_owned(), "--no-edit".to_owned()],
+                mainline(),
+                vec![oid],
+            ]
+            .concat(),
+            Self::Tag => {
+                validate_tag_name(name)?;
+                vec!["tag".to_owned(), name.to_owned(), oid]
+            }
+            Self::Branch => {
+                validate_branch_name(name)?;
+                vec!["branch".to_owned(), name.to_owned(), oid]
+            }
+            Self::RebaseInteractive => {
+                let upstream = if commit.parents().is_empty() {
+                    "--root".to_owned()
+                } else {
+                    format!("{}^", oid)
+                };
+                vec!["rebase".to_owned(), "-i".to_owned(), upstream]
+            }
+        };
+        Ok(GitCommand {
+            args,
+            interactive: self == Self::RebaseInteractive,
+        })
+    }
+}
+
+/// Check the name with `git check-ref-format --branch`. It refuses names starting with a dash,
+/// which git would parse as an option. Names like `@{-1}` are expanded by git & refused too.
+fn validate_branch_name(name: &str) -> Result<(), String> {
+    let valid = Command::new("git")
+        .args(["check-ref-format", "--branch", name])
+        .output()
+        .is_ok_and(|out| out.status.success() && out.stdout.trim_ascii_end() == name.as_bytes());
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a valid branch name", name))
+    }
+}
+
+/// Check the name with `git check-ref-format`. Names starting with a dash are refused, because
+/// git would parse them as an option.
+fn validate_tag_name(name: &str) -> Result<(), String> {
+    let valid = !name.starts_with('-')
+        && Command::new("git")
+            .args(["check-ref-format", &format!("refs/tags/{}", name)])
+            .status()
+            .is_ok_and(|status| status.success());
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a valid tag name", name))
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct GitCommand {
+    args: Vec<String>,
+    /// Command needs the terminal, i.e. to start an editor
+    interactive: bool,
+}
+
+impl Display for GitCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "git {}", self.args.join(" "))
+    }
+}
+
+impl GitCommand {
+    /// Run the command in the work tree & return its output lines
+    pub fn run(&self, repo: &Repository) -> Vec<String> {
+        let mut cmd = repo.git();
+        cmd.args(&self.args);
+        if self.interactive {
+            return match suspend(|| cmd.status()) {
+                Ok(Ok(status)) => vec![format!("{} exited with {}", self, status)],
+                Ok(Err(err)) | Err(err) => vec![format!("Failed to run {}: {}", self, err)],
+            };
+        }
+        match cmd.output() {
+            Ok(output) => {
+                let mut result: Vec<String> = String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .chain(String::from_utf8_lossy(&output.stderr).lines())
+                    .map(ToOwned::to_owned)
+                    .collect();
+                if !output.status.success() {
+                    result.push(format!("{} failed with {}", self, output.status));
+                } else if result.is_empty() {
+                    result.push(format!("{} succeeded", self));
+                }
+                result
+            }
+            Err(err) => vec![format!("Failed to run {}: {}", self, err)],
+        }
+    }
+}
+
+enum State {
+    Hidden,
+    Menu(Commit),
+    Name(Commit, Action, InputLine),
+    Confirm(GitCommand),
+    Output(Vec<String>),
+}
+
+/// Menu of [`Action`]s for the selected commit, shown in the status area
+#[allow(clippy::module_name_repetitions)]
+pub struct ActionMenu {
+    state: State,
+    /// Command confirmed by the user, see [`ActionMenu::take_confirmed`]
+    confirmed: Option<GitCommand>,
+}
+
+impl Default for ActionMenu {
+    fn default() -> Self {
+        Self {
+            state: State::Hidden,
+            confirmed: None,
+        }
+    }
+}
+
+impl ActionMenu {
+    /// Show the menu for the commit
+    pub fn open(&mut self, commit: Commit) {
+        self.state = State::Menu(commit);
+    }
+
+    /// Return the command confirmed by the user, which should be run now
+    pub fn take_confirmed(&mut self) -> Option<GitCommand> {
+        self.confirmed.take()
+    }
+
+    /// Show the output of a command until the next key press
+    pub fn show_output(&mut self, output: Vec<String>) {
+        self.state = State::Output(output);
+    }
+
+    pub fn render(&self, width: usize) -> StyledArea<String> {
+        let lines = match &self.state {
+            State::Hidden => vec![],
+            State::Menu(commit) => {
+                let mut content = vec![style(format!("{} ", short(commit.id())))];
+                for action in Action::ALL {
+                    content.push(style(action.key().to_string()).attribute(Attribute::Bold));
+                    content.push(style(format!(" {}  ", action.label())));
+                }
+                content.push(style("<Esc> cancel".to_owned()));
+                vec![StyledLine {
+                    content,
+                    links: vec![],
+                }]
+            }
+            State::Name(_, action, input) => {
+                let mut content = vec![style(action.prompt().unwrap_or_default().to_owned())];
+                content.append(&mut input.render_line());
+                vec![StyledLine {
+                    content,
+                    links: vec![],
+                }]
+            }
+            State::Confirm(command) => vec![StyledLine {
+                content: vec![style(format!("Run `{}`? [y/N]", command))],
+                links: vec![],
+            }],
+            State::Output(output) => {
+                let skip = output.len().saturating_sub(MAX_OUTPUT);
+                let mut lines: Vec<StyledLine<String>> = output[skip..]
+                    .iter()
+                    .map(|l| StyledLine {
+                        content: vec![style(l.replace('\t', "    "))],
+                        links: vec![],
+                    })
+                    .collect();
+                lines.push(StyledLine {
+                    content: vec![style("Press any key to continue".to_owned()).italic()],
+                    links: vec![],
+                });
+                lines
+            }
+        };
+        lines.into_iter().map(|l| shorten_line(l, width)).collect()
+    }
+
+    pub fn on_event(&mut self, event: &Event) -> HandleEvent {
+        let key = match event {
+            Event::Key(key) => key,
+            _ => return HandleEvent::Ignored,
+        };
+        let esc = matches!(
+            key,
+            KeyEvent {
+                code: KeyCode::Esc,
+                ..
+            }
+        );
+        match &mut self.state {
+            State::Hidden => return HandleEvent::Ignored,
+            State::Output(_) => self.state = State::Hidden,
+            _ if esc => self.state = State::Hidden,
+            State::Menu(commit) => {
+                if let KeyCode::Char(c) = key.code {
+                    if let Some(action) = Action::ALL.into_iter().find(|a| a.key() == c) {
+                        self.state = if action.prompt().is_some() {
+                            State::Name(commit.clone(), action, InputLine::default())
+                        } else {
+                            confirm(action.command(commit, ""))
+                        };
+                    }
+                }
+            }
+            State::Name(commit, action, input) => {
+                if key.code == KeyCode::Enter && key.modifiers == KeyModifiers::NONE {
+                    if !input.text().is_empty() {
+                        self.state = confirm(action.command(commit, input.text()));
+                    }
+                } else {
+                    input.on_event(event);
+                }
+            }
+            State::Confirm(_) => {
+                let state = std::mem::replace(&mut self.state, State::Hidden);
+                if let (State::Confirm(command), KeyCode::Char('y' | 'Y')) = (state, key.code) {
+                    self.confirmed = Some(command);
+                }
+            }
+        }
+        HandleEvent::Handled
+    }
+}
+
+/// Ask for confirmation of the command, or show why it can not be run
+fn confirm(command: Result<GitCommand, String>) -> State {
+    match command {
+        Ok(command) => State::Confirm(command),
+        Err(msg) => State::Output(vec![msg]),
+    }
+}
+
+fn short(oid: &Oid) -> &str {
+    oid.0.get(..8).unwrap_or(&oid.0)
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
+    use pretty_assertions::assert_eq;
+
+    use super::{Action, ActionMenu};
+    use crate::commit::Commit;
+
+    fn key(c: KeyCode) -> Event {
+        Event::Key(KeyEvent {
+            code: c,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        })
+    }
+
+    fn commit(parents: &str) -> Commit {
+        Commit::new(&format!(
+            "\x1fa17989470af\x1fa179894\x1f{}\x1f\x1fAlice\x1falice@example.com\x1f\x1f\x1fAlice\x1falice@example.com\x1f\x1f\x1fSubject\x1f",
+            parents
+        ))
+    }
+
+    #[test]
+    fn commands() {
+        let commit = commit("b1");
+        let to_string = |action: Action| action.command(&commit, "foo").unwrap().to_string();
+        assert_eq!(
+            to_string(Action::Checkout),
+            "git checkout --detach a17989470af"
+        );
+        assert_eq!(
+            to_string(Action::CheckoutBranch),
+            "git checkout -b foo a17989470af"
+        );
+        assert_eq!(to_string(Action::Tag), "git tag foo a17989470af");
+        assert_eq!(to_string(Action::CherryPick), "git cherry-pick a17989470af");
+        assert_eq!(
+            to_string(Action::RebaseInteractive),
+            "git rebase -i a17989470af^"
+        );
+    }
+
+    #[test]
+    fn merge_and_root_commands() {
+        let merge = commit("b1 c2");
+        let to_string = |action: Action| action.command(&merge, "").unwrap().to_string();
+        assert_eq!(
+            to_string(Action::CherryPick),
+            "git cherry-pick -m 1 a17989470af"
+        );
+        assert_eq!(
+            to_string(Action::Revert),
+            "git revert --no-edit -m 1 a17989470af"
+        );
+        let root = commit("");
+        assert_eq!(
+            Action::RebaseInteractive
+                .command(&root, "")
+                .unwrap()
+                .to_string(),
+            "git rebase -i --root"
+        );
+    }
+
+    #[test]
+    fn invalid_names() {
+        let commit = commit("b1");
+        for name in ["-f", "--delete", "a b", "foo..bar", "@{-1}"] {
+            assert!(Action::Branch.command(&commit, name).is_err(), "{}", name);
+            assert!(Action::Tag.command(&commit, name).is_err(), "{}", name);
+        }
+        assert!(Action::CheckoutBranch.command(&commit, "x/y").is_ok());
+    }
+
+    #[test]
+    fn confirm_named_action() {
+        let mut menu = ActionMenu::default();
+        menu.open(commit("b1"));
+        menu.on_event(&key(KeyCode::Char('t')));
+        menu.on_event(&key(KeyCode::Char('v')));
+        menu.on_event(&key(KeyCode::Char('1')));
+        menu.on_event(&key(KeyCode::Enter));
+        assert_eq!(menu.take_confirmed(), None, "Waits for confirmation");
+        menu.on_event(&key(KeyCode::Char('y')));
+        let command = menu.take_confirmed().expect("Confirmed command");
+        assert_eq!(command.to_string(), "git tag v1 a17989470af");
+        assert!(menu.render(80).is_empty(), "Menu is hidden");
+    }
+
+    #[test]
+    fn decline() {
+        let mut menu = ActionMenu::default();
+        menu.open(commit("b1"));
+        menu.on_event(&key(KeyCode::Char('p')));
+        menu.on_event(&key(KeyCode::Char('n')));
+        assert_eq!(menu.take_confirmed(), None);
+        assert!(menu.render(80).is_empty(), "Menu is hidden");
+    }
+
+    #[test]
+    fn refuse_invalid_name() {
+        let mut menu = ActionMenu::default();
+        menu.open(commit("b1"));
+        menu.on_event(&key(KeyCode::Char('b')));
+        menu.on_event(&key(KeyCode::Char('-')));
+        menu.on_event(&key(KeyCode::Char('f')));
+        menu.on_event(&key(KeyCode::Enter));
+        menu.on_event(&key(KeyCode::Char('y')));
+        assert_eq!(menu.take_confirmed(), None);
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#![allow(clippy::module_name_repetitions)]
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+use git_wrapper::Repository;
+
+use super::ActorThread;
+use crate::events;
+
+/// Count the commits of refs ahead & behind of `HEAD`. A new request replaces the refs of the
+/// previous one, which were not counted yet.
+#[derive(Debug)]
+pub struct AheadBehindRequest {
+    /// Full names of the refs, in the order they are counted
+    pub names: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct AheadBehindResponse {
+    pub name: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+pub struct AheadBehindThread(ActorThread<AheadBehindRequest, AheadBehindResponse>);
+
+impl AheadBehindThread {
+    pub fn new(repo: Repository) -> Self {
+        let (tx_1, receiver): (Sender<AheadBehindResponse>, Receiver<AheadBehindResponse>) =
+            mpsc::channel();
+        let (sender, rx_2): (Sender<AheadBehindRequest>, Receiver<AheadBehindRequest>) =
+            mpsc::channel();
+        let thread = thread::spawn(move || {
+            // Refs are only counted once
+            let mut counted = HashSet::new();
+            let mut pending: Vec<String> = vec![];
+            loop {
+                if pending.is_empty() {
+                    match rx_2.recv() {
+                        Ok(request) => pending = request.names,
+                        Err(_) => break,
+                    }
+                }
+                while let Ok(request) = rx_2.try_recv() {
+                    pending = request.names;
+                }
+                if pending.is_empty() {
+                    continue;
+                }
+                let name = pending.remove(0);
+                if !counted.insert(name.clone()) {
+                    continue;
+                }
+                if let Some((ahead, behind)) = ahead_behind(&repo, &name) {
+                    let response = AheadBehindResponse {
+                        name,
+                        ahead,
+                        behind,
+                    };
+                    if tx_1.send(response).is_err() {
+                        // The picker was closed
+                        break;
+                    }
+                    events::wake();
+                }
+            }
+        });
+        Self(ActorThread::new(thread, receiver, sender))
+    }
+
+    pub fn request(&self, names: Vec<String>) {
+        let request = AheadBehindRequest { names };
+        if let Err(err) = self.0.send(request) {
+            log::error!("Failed to request ahead/behind counts: {}", err);
+        }
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn try_recv(&self) -> Result<AheadBehindResponse, TryRecvError> {
+        self.0.try_recv()
+    }
+}
+
+/// Return the number of commits of the ref ahead & behind of `HEAD`
+fn ahead_behind(repo: &Repository, name: &str) -> Option<(usize, usize)> {
+    let output = repo
+        .git()
+        .args(["rev-list", "--left-right", "--count"])
+        .arg(format!("HEAD...{}", name))
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut counts = text.split_whitespace().map(str::parse::<usize>);
+    match (counts.next(), counts.next()) {
+        (Some(Ok(behind)), Some(Ok(ahead))) => Some((ahead, behind)),
+        _ => None,
+    }
+}
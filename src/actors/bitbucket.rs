@@ -25,6 +25,7 @@ use url::Url;
 
 use crate::cache;
 use crate::commit::Oid;
+use crate::events;
 
 use super::ActorThread;
 
@@ -122,6 +123,7 @@ impl BitbucketThread {
                                     subject: format!("{} (#{})", title, pr_id),
                                 })
                                 .unwrap();
+                                events::wake();
                             } else {
                                 log::warn!("Got invalid JSON for #{}", pr_id);
                                 log::debug!("{}", body);
@@ -147,6 +149,7 @@ impl BitbucketThread {
         Self(ActorThread::new(thread, receiver, sender))
     }
 
+    #[allow(clippy::result_large_err)]
     pub(crate) fn send(&self, req: BitbucketRequest) -> Result<(), SendError<BitbucketRequest>> {
         self.0.send(req)
     }
@@ -0,0 +1,80 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#![allow(clippy::module_name_repetitions)]
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, SendError, Sender, TryRecvError};
+use std::thread;
+
+use git_wrapper::Repository;
+
+use crate::commit::{unfolded_children, Commit, Oid};
+use crate::events;
+
+use super::ActorThread;
+
+pub struct ChildHistoryRequest {
+    pub merge: Commit,
+}
+
+pub struct ChildHistoryResponse {
+    pub oid: Oid,
+    pub children: Vec<Commit>,
+    pub link: Option<Commit>,
+}
+
+/// Precalculates the child commits of merges, so unfolding does not block the ui
+pub struct ChildHistoryThread(ActorThread<ChildHistoryRequest, ChildHistoryResponse>);
+
+impl ChildHistoryThread {
+    pub(crate) fn new(repo: Repository, paths: Vec<PathBuf>) -> Self {
+        let (tx_1, receiver): (Sender<ChildHistoryResponse>, Receiver<ChildHistoryResponse>) =
+            mpsc::channel();
+        let (sender, rx_2): (Sender<ChildHistoryRequest>, Receiver<ChildHistoryRequest>) =
+            mpsc::channel();
+        let thread = thread::spawn(move || {
+            while let Ok(v) = rx_2.recv() {
+                let (children, link) = unfolded_children(&repo, &v.merge, &paths);
+                log::trace!(
+                    "Prefetched #{} children for {:?}",
+                    children.len(),
+                    v.merge.id()
+                );
+                let response = ChildHistoryResponse {
+                    oid: v.merge.id().clone(),
+                    children,
+                    link,
+                };
+                if tx_1.send(response).is_err() {
+                    break;
+                }
+                events::wake();
+            }
+        });
+        Self(ActorThread::new(thread, receiver, sender))
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub fn send(&self, request: ChildHistoryRequest) -> Result<(), SendError<ChildHistoryRequest>> {
+        self.0.send(request)
+    }
+
+    pub fn try_recv(&self) -> Result<ChildHistoryResponse, TryRecvError> {
+        self.0.try_recv()
+    }
+}
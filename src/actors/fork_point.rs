@@ -19,6 +19,7 @@
 use std::sync::mpsc::{Receiver, Sender, TryRecvError};
 
 use crate::commit::{Commit, Oid};
+use crate::events;
 use std::fmt::{Debug, Formatter};
 use std::sync::mpsc;
 use std::thread;
@@ -27,7 +28,7 @@ use git_wrapper::Repository;
 
 use super::ActorThread;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum ForkPointCalculation {
     Done(bool),
     InProgress,
@@ -56,6 +57,7 @@ impl Debug for ForkPointRequest {
 
 pub struct ForkPointResponse {
     pub first: Oid,
+    #[allow(dead_code)]
     pub second: Oid,
     pub value: bool,
 }
@@ -114,6 +116,7 @@ impl ForkPointThread {
                     value,
                 })
                 .expect("Send ForkPointResponse");
+                events::wake();
             }
         });
         Self(ActorThread::new(thread, receiver, sender))
@@ -23,6 +23,7 @@ use curl::easy::Easy;
 
 use crate::cache;
 use crate::commit::Oid;
+use crate::events;
 use std::thread;
 use tinyjson::JsonValue;
 use url::Url;
@@ -141,6 +142,7 @@ impl GitHubThread {
                                     subject: format!("{} (#{})", title, pr_id),
                                 })
                                 .unwrap();
+                                events::wake();
                             } else {
                                 log::warn!("Got invalid JSON for #{}", pr_id);
                                 log::debug!("{}", body);
@@ -163,6 +165,7 @@ impl GitHubThread {
         Self(ActorThread::new(thread, receiver, sender))
     }
 
+    #[allow(clippy::result_large_err)]
     pub(crate) fn send(&self, req: GitHubRequest) -> Result<(), SendError<GitHubRequest>> {
         self.0.send(req)
     }
@@ -20,9 +20,14 @@ use std::{
     thread::JoinHandle,
 };
 
+pub mod ahead_behind;
 pub mod bitbucket;
+pub mod child_history;
 pub mod fork_point;
 pub mod github;
+pub mod pull_requests;
+pub mod refs_watch;
+pub mod search;
 pub mod subtrees;
 
 struct ActorThread<Request, Response> {
@@ -0,0 +1,99 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Pull request titles from the GitHub & Bitbucket actors, shared by all history views.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use url::Url;
+
+use super::bitbucket::{BitbucketRequest, BitbucketThread};
+use super::github::{GitHubRequest, GitHubThread};
+use crate::commit::Oid;
+
+struct Inner {
+    github: GitHubThread,
+    bitbucket: BitbucketThread,
+    /// All titles received so far, in order of arrival
+    received: Vec<(Oid, String)>,
+}
+
+/// Looks up the titles of pull requests. Clones share the actors & the received titles, so a
+/// title requested by one view is also seen by the others.
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct PullRequestTitles(Rc<RefCell<Inner>>);
+
+impl PullRequestTitles {
+    pub fn new() -> Self {
+        Self(Rc::new(RefCell::new(Inner {
+            github: GitHubThread::new(),
+            bitbucket: BitbucketThread::new(),
+            received: vec![],
+        })))
+    }
+
+    /// Return the cached title of the pull request, otherwise request it in the background
+    pub fn request(&self, oid: &Oid, url: Url, pr_id: &str) -> Option<String> {
+        let inner = self.0.borrow();
+        if GitHubThread::can_handle(&url) {
+            if let Some(title) = GitHubThread::from_cache(&url, pr_id) {
+                log::debug!("PR #{} (CACHE) ⇒ «{}»", pr_id, title);
+                return Some(title);
+            }
+            let req = GitHubRequest {
+                oid: oid.clone(),
+                url,
+                pr_id: pr_id.to_owned(),
+            };
+            if let Err(err) = inner.github.send(req) {
+                log::error!("{}", err);
+            }
+        } else if BitbucketThread::can_handle(&url) {
+            if let Some(title) = BitbucketThread::from_cache(&url, pr_id) {
+                log::debug!("PR #{} (CACHE) ⇒ «{}»", pr_id, title);
+                return Some(title);
+            }
+            let req = BitbucketRequest {
+                oid: oid.clone(),
+                url,
+                pr_id: pr_id.to_owned(),
+            };
+            if let Err(err) = inner.bitbucket.send(req) {
+                log::error!("{}", err);
+            }
+        } else {
+            log::info!("Unrecognized url {}", url);
+        }
+        None
+    }
+
+    /// Return the titles received after the first `seen` ones & advance `seen`
+    pub fn received_since(&self, seen: &mut usize) -> Vec<(Oid, String)> {
+        let mut inner = self.0.borrow_mut();
+        while let Ok(v) = inner.github.try_recv() {
+            inner.received.push((v.oid, v.subject));
+        }
+        while let Ok(v) = inner.bitbucket.try_recv() {
+            inner.received.push((v.oid, v.subject));
+        }
+        let result = inner.received.get(*seen..).unwrap_or_default().to_vec();
+        *seen = inner.received.len();
+        result
+    }
+}
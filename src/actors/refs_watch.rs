@@ -0,0 +1,128 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#![allow(clippy::module_name_repetitions)]
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use git_wrapper::Repository;
+
+use crate::events;
+
+/// How often the refs are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Signals that HEAD or a ref changed
+pub struct RefsChanged;
+
+/// Polls HEAD & the refs of the repository & reports when they changed, i.e. after a commit or
+/// a `git fetch` in another terminal. The thread stops, when the watcher is dropped.
+pub struct RefsWatchThread {
+    _thread: JoinHandle<()>,
+    receiver: Receiver<RefsChanged>,
+    /// Disconnects when dropped, which stops the thread
+    _stop: Sender<()>,
+}
+
+impl RefsWatchThread {
+    pub(crate) fn new(repo: Repository) -> Self {
+        let (sender, receiver): (Sender<RefsChanged>, Receiver<RefsChanged>) = mpsc::channel();
+        let (stop, stopped): (Sender<()>, Receiver<()>) = mpsc::channel();
+        let mut last = refs_fingerprint(&repo);
+        let thread = thread::spawn(move || loop {
+            if stopped.recv_timeout(POLL_INTERVAL) != Err(RecvTimeoutError::Timeout) {
+                break;
+            }
+            let current = refs_fingerprint(&repo);
+            if current.is_some() && current != last {
+                log::debug!("Refs changed");
+                last = current;
+                if sender.send(RefsChanged).is_err() {
+                    break;
+                }
+                events::wake();
+            }
+        });
+        Self {
+            _thread: thread,
+            receiver,
+            _stop: stop,
+        }
+    }
+
+    /// Return `true` if the refs changed since the last call
+    pub fn changed(&self) -> bool {
+        self.receiver.try_iter().count() > 0
+    }
+}
+
+/// Return the oids & names of HEAD & all refs, except the ones updated by `git maintenance`
+fn refs_fingerprint(repo: &Repository) -> Option<String> {
+    let output = repo.git().args(["show-ref", "--head"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.contains(" refs/prefetch/"))
+        .collect();
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    use super::{refs_fingerprint, RefsWatchThread};
+    use crate::ui::base::test_helpers::TestRepo;
+
+    #[test]
+    fn fingerprint_changes() {
+        let repo = TestRepo::new("refs");
+        repo.commit("c0");
+        let before = refs_fingerprint(&repo);
+        assert!(before.is_some());
+        repo.git(&["update-ref", "refs/prefetch/remotes/origin/main", "HEAD"]);
+        assert_eq!(refs_fingerprint(&repo), before, "Prefetch refs are ignored");
+        repo.commit("c1");
+        assert_ne!(refs_fingerprint(&repo), before);
+        repo.git(&["tag", "v1"]);
+        let tagged = refs_fingerprint(&repo);
+        repo.git(&["checkout", "-q", "--detach", "HEAD^"]);
+        assert_ne!(refs_fingerprint(&repo), tagged, "HEAD is watched");
+    }
+
+    #[test]
+    fn stops_when_dropped() {
+        let repo = TestRepo::new("refs-stop");
+        let RefsWatchThread {
+            _thread: thread,
+            receiver,
+            _stop: stop,
+        } = RefsWatchThread::new(repo.clone());
+        drop((receiver, stop));
+        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(500);
+        while !thread.is_finished() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(thread.is_finished(), "Stopped before the next poll");
+    }
+}
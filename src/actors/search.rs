@@ -0,0 +1,428 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#![allow(clippy::module_name_repetitions)]
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, SendError, Sender};
+use std::thread;
+use std::thread::JoinHandle;
+
+use git_stree::Subtrees;
+use git_wrapper::Repository;
+
+use crate::commit::{commits_for_range, Commit, Oid};
+use crate::events;
+use crate::index::{CommitIndex, IndexEntry};
+use crate::query::{Field, Query};
+use crate::ui::base::data::SearchProgress;
+use crate::ui::base::search::{Direction, Needle, SearchResult};
+
+/// Parsed needle & data needed for matching commits
+pub struct SearchContext {
+    query: Query,
+    direction: Direction,
+    /// Only set when the query searches for modules
+    subtrees: Option<Subtrees>,
+}
+
+impl SearchContext {
+    pub fn new(needle: &Needle, repo: &Repository, range: &[OsString], paths: &[PathBuf]) -> Self {
+        let mut query = needle.query();
+        if query.uses(Field::Diff) || query.uses(Field::File) {
+            query.load_changes(repo, range, paths);
+        }
+        let subtrees = if query.uses(Field::Module) {
+            Subtrees::from_repo(repo.clone()).ok()
+        } else {
+            None
+        };
+        Self {
+            query,
+            direction: *needle.direction(),
+            subtrees,
+        }
+    }
+
+    pub fn matches(&self, commit: &Commit) -> bool {
+        self.query.matches(commit, &mut |c| {
+            self.subtrees
+                .as_ref()
+                .and_then(|s| s.changed_modules(&c.id().0).ok())
+                .map(|modules| modules.iter().map(|m| m.id().clone()).collect())
+                .unwrap_or_default()
+        })
+    }
+}
+
+pub enum SearchRequest {
+    /// Report matching commits in history order, starting from a first-parent commit
+    Search {
+        needle: Needle,
+        start: usize,
+        progress: Sender<SearchProgress>,
+    },
+    /// Find all matching commits at once
+    Filter {
+        needle: Needle,
+        result: Sender<FilterResult>,
+    },
+}
+
+/// Commits shown while filtering the history
+#[derive(Default)]
+pub struct FilterResult {
+    /// Number of commits matching the needle
+    pub matches: usize,
+    /// Matching commits & merges with matching commits in their child history
+    pub shown: HashSet<Oid>,
+    /// The whole first-parent history, so it does not need to be loaded on the UI thread. The
+    /// commits are loaded like the shown history, not taken from the index.
+    pub first_parent: Vec<Commit>,
+}
+
+enum ScanResult {
+    Finished,
+    /// The receiver of the progress is gone
+    Canceled,
+    /// A new request arrived, while scanning
+    Superseded(SearchRequest),
+}
+
+enum Message {
+    Request(SearchRequest),
+    /// A first-parent commit & its child history were indexed
+    Indexed(Vec<IndexEntry>),
+    /// All commits are indexed
+    Built,
+}
+
+/// The index received so far from the thread building it
+#[derive(Default)]
+struct PartialIndex {
+    index: CommitIndex,
+    complete: bool,
+}
+
+impl PartialIndex {
+    /// Apply an index update & return the message, if it is a request
+    fn apply(&mut self, message: Message) -> Option<SearchRequest> {
+        match message {
+            Message::Request(request) => return Some(request),
+            Message::Indexed(entries) => self.index.extend(entries),
+            Message::Built => {
+                log::debug!("Indexed {} commits", self.index.entries().len());
+                self.complete = true;
+            }
+        }
+        None
+    }
+
+    /// Apply all pending index updates, until a request arrives
+    fn poll(&mut self, messages: &Receiver<Message>) -> Result<(), ScanResult> {
+        while let Ok(message) = messages.try_recv() {
+            if let Some(request) = self.apply(message) {
+                return Err(ScanResult::Superseded(request));
+            }
+        }
+        Ok(())
+    }
+
+    /// Block until `done` is true, or a request arrives
+    fn wait(
+        &mut self,
+        messages: &Receiver<Message>,
+        done: impl Fn(&Self) -> bool,
+    ) -> Result<(), ScanResult> {
+        while !done(self) {
+            match messages.recv() {
+                Ok(message) => {
+                    if let Some(request) = self.apply(message) {
+                        return Err(ScanResult::Superseded(request));
+                    }
+                }
+                Err(_) => return Err(ScanResult::Canceled),
+            }
+        }
+        Ok(())
+    }
+
+    /// Index of the first entry at or after the first-parent commit `start`, once it is known
+    fn split(&self, start: usize) -> Option<usize> {
+        let entries = self.index.entries();
+        let split = entries.partition_point(|e| e.path[0] < start);
+        (split < entries.len() || self.complete).then_some(split)
+    }
+}
+
+/// Answers search requests using a [`CommitIndex`].
+///
+/// The index is built in the background, when the thread starts. Searches run on the commits
+/// indexed so far & continue as more arrive. A new request cancels the running search.
+pub struct SearchThread {
+    _thread: JoinHandle<()>,
+    sender: Sender<Message>,
+}
+
+impl SearchThread {
+    pub(crate) fn new(repo: Repository, range: Vec<OsString>, paths: Vec<PathBuf>) -> Self {
+        let (sender, messages): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let builder = sender.clone();
+        let builder_repo = repo.clone();
+        let builder_range = range.clone();
+        let builder_paths = paths.clone();
+        thread::spawn(move || {
+            // Stop indexing as soon as the search thread is gone
+            let mut on_batch =
+                |entries: &[IndexEntry]| builder.send(Message::Indexed(entries.to_vec())).is_ok();
+            let index = CommitIndex::load_or_build(
+                &builder_repo,
+                &builder_range,
+                &builder_paths,
+                &mut on_batch,
+            );
+            if index.is_none() || builder.send(Message::Built).is_err() {
+                log::debug!("Indexing canceled");
+            }
+        });
+        let thread = thread::spawn(move || {
+            let mut index = PartialIndex::default();
+            let mut next = None;
+            loop {
+                let request = match next.take() {
+                    Some(request) => request,
+                    None => match messages.recv() {
+                        Ok(message) => match index.apply(message) {
+                            Some(request) => request,
+                            None => continue,
+                        },
+                        Err(_) => break,
+                    },
+                };
+                next = match request {
+                    SearchRequest::Search {
+                        needle,
+                        start,
+                        progress,
+                    } => {
+                        let context = SearchContext::new(&needle, &repo, &range, &paths);
+                        match scan(&mut index, &context, start, &progress, &messages) {
+                            ScanResult::Superseded(request) => Some(request),
+                            ScanResult::Canceled => None,
+                            ScanResult::Finished => {
+                                if progress.send(SearchProgress::Finished).is_ok() {
+                                    events::wake();
+                                }
+                                None
+                            }
+                        }
+                    }
+                    SearchRequest::Filter { needle, result } => {
+                        match index.wait(&messages, |i| i.complete) {
+                            Err(ScanResult::Superseded(request)) => Some(request),
+                            Err(_) => None,
+                            Ok(()) => {
+                                let context = SearchContext::new(&needle, &repo, &range, &paths);
+                                let mut filtered = filter(&index.index, &context);
+                                filtered.first_parent =
+                                    commits_for_range(&repo, &range, &paths, None, None);
+                                if result.send(filtered).is_ok() {
+                                    events::wake();
+                                } else {
+                                    log::debug!("Filter for «{}» canceled", needle.text());
+                                }
+                                None
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        Self {
+            _thread: thread,
+            sender,
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub fn send(&self, request: SearchRequest) -> Result<(), SendError<SearchRequest>> {
+        self.sender
+            .send(Message::Request(request))
+            .map_err(|SendError(message)| match message {
+                Message::Request(request) => SendError(request),
+                Message::Indexed(_) | Message::Built => unreachable!("Only requests are sent"),
+            })
+    }
+}
+
+/// Reports the matching entries & the number of searched ones
+struct Scanner<'a> {
+    context: &'a SearchContext,
+    rx: &'a Sender<SearchProgress>,
+    seen: usize,
+}
+
+impl Scanner<'_> {
+    fn visit(
+        &mut self,
+        index: &mut PartialIndex,
+        i: usize,
+        messages: &Receiver<Message>,
+    ) -> Result<(), ScanResult> {
+        let entry = &index.index.entries()[i];
+        #[allow(clippy::arithmetic_side_effects)]
+        {
+            // arithmetic: `seen` can never exceed `usize::MAX`, because `seen <= entries.len()`
+            self.seen += 1;
+        }
+        if self.context.matches(&entry.commit) {
+            if self
+                .rx
+                .send(SearchProgress::Found(SearchResult(entry.path.clone())))
+                .is_err()
+            {
+                return Err(ScanResult::Canceled);
+            }
+            events::wake();
+        }
+        if self.seen.is_multiple_of(100) {
+            index.poll(messages)?;
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Report the entries searched since the last report
+    fn flush(&mut self) -> Result<(), ScanResult> {
+        if 0 < self.seen {
+            if self.rx.send(SearchProgress::Searched(self.seen)).is_err() {
+                return Err(ScanResult::Canceled);
+            }
+            events::wake();
+            self.seen = 0;
+        }
+        Ok(())
+    }
+
+    /// Report progress & block until `done` is true
+    fn wait(
+        &mut self,
+        index: &mut PartialIndex,
+        messages: &Receiver<Message>,
+        done: impl Fn(&PartialIndex) -> bool,
+    ) -> Result<(), ScanResult> {
+        self.flush()?;
+        index.wait(messages, done)
+    }
+}
+
+/// Report the matching entries of the index in the order of the search direction. Entries
+/// before the first-parent commit `start` are searched last. Entries are searched while the
+/// index is still being built.
+fn scan(
+    index: &mut PartialIndex,
+    context: &SearchContext,
+    start: usize,
+    rx: &Sender<SearchProgress>,
+    messages: &Receiver<Message>,
+) -> ScanResult {
+    let mut scanner = Scanner {
+        context,
+        rx,
+        seen: 0,
+    };
+    let result = match context.direction {
+        Direction::Forward => scan_forward(&mut scanner, index, start, messages),
+        Direction::Backward => scan_backward(&mut scanner, index, start, messages),
+    };
+    match result.and_then(|_| scanner.flush()) {
+        Ok(()) => ScanResult::Finished,
+        Err(result) => result,
+    }
+}
+
+fn scan_forward(
+    scanner: &mut Scanner<'_>,
+    index: &mut PartialIndex,
+    start: usize,
+    messages: &Receiver<Message>,
+) -> Result<(), ScanResult> {
+    scanner.wait(index, messages, |i| i.split(start).is_some())?;
+    let split = index.split(start).unwrap_or_default();
+    let mut i = split;
+    loop {
+        while i < index.index.entries().len() {
+            scanner.visit(index, i, messages)?;
+            // arithmetic: `i` is smaller than the number of entries
+            #[allow(clippy::arithmetic_side_effects)]
+            {
+                i += 1;
+            }
+        }
+        if index.complete {
+            break;
+        }
+        let scanned = i;
+        scanner.wait(index, messages, |index| {
+            index.complete || scanned < index.index.entries().len()
+        })?;
+    }
+    for i in 0..split {
+        scanner.visit(index, i, messages)?;
+    }
+    Ok(())
+}
+
+fn scan_backward(
+    scanner: &mut Scanner<'_>,
+    index: &mut PartialIndex,
+    start: usize,
+    messages: &Receiver<Message>,
+) -> Result<(), ScanResult> {
+    scanner.wait(index, messages, |i| i.split(start).is_some())?;
+    let split = index.split(start).unwrap_or_default();
+    for i in (0..split).rev() {
+        scanner.visit(index, i, messages)?;
+    }
+    scanner.wait(index, messages, |i| i.complete)?;
+    for i in (split..index.index.entries().len()).rev() {
+        scanner.visit(index, i, messages)?;
+    }
+    Ok(())
+}
+
+/// Return the matching commits & all merges containing them
+fn filter(index: &CommitIndex, context: &SearchContext) -> FilterResult {
+    let mut result = FilterResult::default();
+    // Entries of the merges containing the current entry
+    let mut ancestors: Vec<&IndexEntry> = vec![];
+    for entry in index.entries() {
+        let depth = entry.path.len().saturating_sub(1);
+        ancestors.truncate(depth);
+        if context.matches(&entry.commit) {
+            result.matches = result.matches.saturating_add(1);
+            result.shown.insert(entry.commit.id().clone());
+            for merge in &ancestors {
+                result.shown.insert(merge.commit.id().clone());
+            }
+        }
+        ancestors.push(entry);
+    }
+    result
+}
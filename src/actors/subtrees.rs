@@ -23,6 +23,7 @@ use std::thread;
 use git_stree::SubtreeConfig;
 
 use crate::commit::Oid;
+use crate::events;
 
 use super::ActorThread;
 
@@ -55,6 +56,7 @@ impl SubtreeThread {
                         subtrees: result,
                     })
                     .expect("Send SubtreeChangesResponse");
+                    events::wake();
                 }
             }
         });
@@ -67,3 +67,43 @@ pub fn fetch_api_response(url: &Url, id: &str) -> Result<Option<String>, Error>
     let path = path_from_url(url)?.join(id);
     fetch(path)
 }
+
+pub fn store_session(id: &str, body: &str) -> Result<(), Error> {
+    store(Path::new("sessions").join(id), body)
+}
+
+pub fn fetch_session(id: &str) -> Result<Option<String>, Error> {
+    fetch(Path::new("sessions").join(id))
+}
+
+/// Store the index of a view & remove the indexes of the view for older tips
+pub fn store_index(view: &str, tips: &str, body: &str) -> Result<(), Error> {
+    let name = format!("{}-{}", view, tips);
+    store(Path::new("index").join(&name), body)?;
+    let proj_dirs = directories::ProjectDirs::from("", "", "glv").ok_or(Error::CacheFailure)?;
+    let prefix = format!("{}-", view);
+    for entry in std::fs::read_dir(proj_dirs.cache_dir().join("index"))? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if file_name.starts_with(&prefix) && file_name != name {
+            log::debug!("Removing stale commit index {}", file_name);
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+pub fn fetch_index(id: &str) -> Result<Option<String>, Error> {
+    fetch(Path::new("index").join(id))
+}
+
+pub fn store_search_history(history: &[String]) -> Result<(), Error> {
+    store(
+        Path::new("search_history").to_path_buf(),
+        &history.join("\n"),
+    )
+}
+
+pub fn fetch_search_history() -> Result<Option<String>, Error> {
+    fetch(Path::new("search_history").to_path_buf())
+}
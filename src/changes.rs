@@ -0,0 +1,177 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The files changed by a diff.
+
+use std::path::PathBuf;
+
+use crossterm::style::{style, Color, Stylize};
+use getset::{CopyGetters, Getters};
+use git_wrapper::Repository;
+
+use crate::ui::base::StyledLine;
+
+/// A file changed by a diff, see `git diff --name-status`
+#[derive(Clone, CopyGetters, Debug, Eq, Getters, PartialEq)]
+pub struct FileChange {
+    /// `A`dded, `M`odified, `R`enamed, `C`opied, `D`eleted, `T`ype changed…
+    #[getset(get_copy = "pub")]
+    status: char,
+    #[getset(get = "pub")]
+    path: String,
+    /// Path before a rename or copy
+    #[getset(get = "pub")]
+    old_path: Option<String>,
+    /// Number of added & deleted lines, `None` for binary files
+    #[getset(get_copy = "pub")]
+    lines: Option<(usize, usize)>,
+}
+
+impl FileChange {
+    /// Return the paths to limit a diff to this file
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut result = vec![PathBuf::from(&self.path)];
+        if let Some(old) = &self.old_path {
+            result.push(PathBuf::from(old));
+        }
+        result
+    }
+
+    pub fn render(&self) -> StyledLine<String> {
+        let color = match self.status {
+            'A' => Color::Green,
+            'D' => Color::Red,
+            'R' | 'C' => Color::Cyan,
+            _ => Color::Yellow,
+        };
+        let path = match &self.old_path {
+            Some(old) => format!("{} → {}", old, self.path),
+            None => self.path.clone(),
+        };
+        let lines = match self.lines {
+            Some((added, deleted)) => format!("+{} -{}", added, deleted),
+            None => "binary".to_owned(),
+        };
+        StyledLine {
+            content: vec![
+                style(format!(" {} ", self.status)).with(color),
+                style(format!("{:>12} ", lines)),
+                style(path),
+            ],
+            links: vec![],
+        }
+    }
+}
+
+/// Parse the output of `git diff --name-status -z`
+fn parse_name_status(text: &str) -> Vec<(char, Option<String>, String)> {
+    let mut fields = text.split('\0');
+    let mut result = vec![];
+    while let Some(status) = fields.next().and_then(|f| f.chars().next()) {
+        let first = match fields.next() {
+            Some(path) => path.to_owned(),
+            None => break,
+        };
+        if status == 'R' || status == 'C' {
+            match fields.next() {
+                Some(second) => result.push((status, Some(first), second.to_owned())),
+                None => break,
+            }
+        } else {
+            result.push((status, None, first));
+        }
+    }
+    result
+}
+
+/// Parse the added & deleted lines from the output of `git diff --numstat -z`
+fn parse_numstat(text: &str) -> Vec<Option<(usize, usize)>> {
+    let mut records = text.split('\0');
+    let mut result = vec![];
+    while let Some(record) = records.next().filter(|r| !r.is_empty()) {
+        let mut parts = record.splitn(3, '\t');
+        let added = parts.next().and_then(|p| p.parse().ok());
+        let deleted = parts.next().and_then(|p| p.parse().ok());
+        // Renames & copies have an empty path, followed by the old & the new path
+        if parts.next().unwrap_or_default().is_empty() {
+            records.next();
+            records.next();
+        }
+        result.push(added.zip(deleted));
+    }
+    result
+}
+
+/// Return the files changed by the git diff command, i.e. `["diff", "A..B"]`
+pub fn file_changes(repo: &Repository, args: &[String], paths: &[PathBuf]) -> Vec<FileChange> {
+    let run = |format: &str| {
+        let mut cmd = repo.git();
+        cmd.args(&args[..1]);
+        cmd.args([format, "-z", "-M"]);
+        cmd.args(&args[1..]);
+        if !paths.is_empty() {
+            cmd.arg("--");
+            cmd.args(paths);
+        }
+        match cmd.output() {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+            Err(err) => {
+                log::warn!("Failed to execute git {:?}: {}", args, err);
+                String::new()
+            }
+        }
+    };
+    let lines = parse_numstat(&run("--numstat"));
+    parse_name_status(&run("--name-status"))
+        .into_iter()
+        .enumerate()
+        .map(|(i, (status, old_path, path))| FileChange {
+            status,
+            path,
+            old_path,
+            lines: lines.get(i).copied().flatten(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{parse_name_status, parse_numstat};
+
+    #[test]
+    fn name_status() {
+        let text = "M\0src/main.rs\0R087\0src/old.rs\0src/new.rs\0A\0README.md\0D\0f\u{f6}\to\0";
+        assert_eq!(
+            parse_name_status(text),
+            vec![
+                ('M', None, "src/main.rs".to_owned()),
+                ('R', Some("src/old.rs".to_owned()), "src/new.rs".to_owned()),
+                ('A', None, "README.md".to_owned()),
+                ('D', None, "f\u{f6}\to".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn numstat() {
+        let text = "3\t1\tsrc/main.rs\0-\t-\timage.png\0\
+                    0\t2\t\0src/old.rs\0src/new.rs\0";
+        assert_eq!(parse_numstat(text), vec![Some((3, 1)), None, Some((0, 2))]);
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Copy commit data to the clipboard.
+//!
+//! The text is written to the terminal via the OSC 52 escape sequence, which works over SSH &
+//! inside tmux. When `$GLV_CLIPBOARD` is set, e.g. to `xclip -selection clipboard`, the text is
+//! piped to that command instead.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use crossterm::style::{style, Attribute, Stylize};
+use git_wrapper::Repository;
+
+use crate::history_entry::HistoryEntry;
+use crate::ui::base::{shorten_line, HandleEvent, StyledArea, StyledLine};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Yank {
+    Id,
+    ShortId,
+    Subject,
+    Reference,
+    Url,
+    Patch,
+}
+
+impl Yank {
+    const ALL: [Self; 6] = [
+        Self::Id,
+        Self::ShortId,
+        Self::Subject,
+        Self::Reference,
+        Self::Url,
+        Self::Patch,
+    ];
+
+    const fn key(self) -> char {
+        match self {
+            Self::Id => 'y',
+            Self::ShortId => 's',
+            Self::Subject => 't',
+            Self::Reference => 'r',
+            Self::Url => 'u',
+            Self::Patch => 'p',
+        }
+    }
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::ShortId => "short id",
+            Self::Subject => "subject",
+            Self::Reference => "reference",
+            Self::Url => "url",
+            Self::Patch => "patch",
+        }
+    }
+
+    /// Return the text to copy for the entry
+    pub fn text(self, repo: &Repository, entry: &HistoryEntry) -> Option<String> {
+        let commit = entry.commit();
+        match self {
+            Self::Id => Some(commit.id().0.clone()),
+            Self::ShortId => Some(commit.short_id().clone()),
+            Self::Subject => Some(commit.subject().clone()),
+            Self::Reference => Some(format!(
+                "{} ({}, {})",
+                commit.short_id(),
+                commit.subject(),
+                commit
+                    .author_date()
+                    .get(..10)
+                    .unwrap_or(commit.author_date())
+            )),
+            Self::Url => entry.web_url().map(String::from),
+            Self::Patch => {
+                let output = repo
+                    .git()
+                    .args(["format-patch", "-1", "--stdout", &commit.id().0])
+                    .output()
+                    .ok()?;
+                output
+                    .status
+                    .success()
+                    .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+            }
+        }
+    }
+}
+
+/// Encode the data as standard base64 with padding
+fn base64(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len().saturating_add(2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or_default(),
+            chunk.get(2).copied().unwrap_or_default(),
+        ];
+        let n = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+        for (i, shift) in [18, 12, 6, 0].into_iter().enumerate() {
+            if i <= chunk.len() {
+                result.push(char::from(BASE64[(n >> shift & 0x3f) as usize]));
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+/// Return the OSC 52 sequence setting the clipboard to the text. Inside tmux the sequence is
+/// wrapped in a DCS passthrough.
+fn osc52(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+/// Copy the text to the clipboard
+///
+/// # Errors
+///
+/// Returns an error if writing to the terminal or running `$GLV_CLIPBOARD` failed
+pub fn copy(text: &str) -> io::Result<()> {
+    if let Ok(command) = std::env::var("GLV_CLIPBOARD") {
+        let mut words = command.split_whitespace();
+        if let Some(program) = words.next() {
+            let mut child = Command::new(program)
+                .args(words)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()?;
+            if let Some(stdin) = child.stdin.as_mut() {
+                stdin.write_all(text.as_bytes())?;
+            }
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!(
+                    "{} exited with {}",
+                    command, status
+                )));
+            }
+            return Ok(());
+        }
+    }
+    let mut stdout = io::stdout();
+    stdout.write_all(osc52(text, std::env::var_os("TMUX").is_some()).as_bytes())?;
+    stdout.flush()
+}
+
+enum State {
+    Hidden,
+    Menu,
+    Message(String),
+}
+
+/// Menu of [`Yank`]s for the selected commit, shown in the status area
+pub struct YankMenu {
+    state: State,
+    /// Chosen yank, see [`YankMenu::take_chosen`]
+    chosen: Option<Yank>,
+}
+
+impl Default for YankMenu {
+    fn default() -> Self {
+        Self {
+            state: State::Hidden,
+            chosen: None,
+        }
+    }
+}
+
+impl YankMenu {
+    pub fn open(&mut self) {
+        self.state = State::Menu;
+    }
+
+    /// Return the yank chosen by the user, which should be copied now
+    pub fn take_chosen(&mut self) -> Option<Yank> {
+        self.chosen.take()
+    }
+
+    /// Show a message until the next key press
+    pub fn show_message(&mut self, message: String) {
+        self.state = State::Message(message);
+    }
+
+    pub fn render(&self, width: usize) -> StyledArea<String> {
+        let line = match &self.state {
+            State::Hidden => return vec![],
+            State::Menu => {
+                let mut content = vec![style("Yank ".to_owned())];
+                for yank in Yank::ALL {
+                    content.push(style(yank.key().to_string()).attribute(Attribute::Bold));
+                    content.push(style(format!(" {}  ", yank.label())));
+                }
+                content.push(style("<Esc> cancel".to_owned()));
+                StyledLine {
+                    content,
+                    links: vec![],
+                }
+            }
+            State::Message(message) => StyledLine {
+                content: vec![style(message.clone()).italic()],
+                links: vec![],
+            },
+        };
+        vec![shorten_line(line, width)]
+    }
+
+    pub fn on_event(&mut self, event: &Event) -> HandleEvent {
+        let key = match event {
+            Event::Key(key) => key,
+            _ => return HandleEvent::Ignored,
+        };
+        match self.state {
+            State::Hidden => HandleEvent::Ignored,
+            State::Message(_) => {
+                // The message does not swallow the key press
+                self.state = State::Hidden;
+                HandleEvent::Ignored
+            }
+            State::Menu => {
+                if let KeyEvent {
+                    code: KeyCode::Char(c),
+                    ..
+                } = key
+                {
+                    self.chosen = Yank::ALL.into_iter().find(|y| y.key() == *c);
+                }
+                self.state = State::Hidden;
+                HandleEvent::Handled
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{base64, osc52};
+
+    #[test]
+    fn encoding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64("ä".as_bytes()), "w6Q=");
+    }
+
+    #[test]
+    fn sequences() {
+        assert_eq!(osc52("foo", false), "\x1b]52;c;Zm9v\x07");
+        assert_eq!(osc52("foo", true), "\x1bPtmux;\x1b\x1b]52;c;Zm9v\x07\x1b\\");
+    }
+}
@@ -15,15 +15,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::ui::base::search::Needle;
 use url::Url;
 
 use getset::Getters;
 use git_wrapper::Repository;
 use posix_errors::PosixError;
-use std::ffi::OsStr;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
 use std::fmt::{Debug, Display, Formatter};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 macro_rules! next_string {
     ($split:expr) => {
@@ -31,7 +32,7 @@ macro_rules! next_string {
     };
 }
 
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Default, Eq, Hash, PartialEq)]
 pub struct Oid(pub String);
 
 #[cfg(not(tarpaulin_include))]
@@ -60,7 +61,7 @@ impl Display for GitRef {
     }
 }
 
-#[derive(Getters)]
+#[derive(Clone, Default, Getters)]
 pub struct Commit {
     #[getset(get = "pub")]
     id: Oid,
@@ -81,6 +82,7 @@ pub struct Commit {
     #[getset(get = "pub")]
     committer_date: String,
     #[getset(get = "pub")]
+    #[allow(dead_code)]
     committer_rel_date: String,
     #[getset(get = "pub")]
     subject: String,
@@ -93,12 +95,12 @@ pub struct Commit {
     is_head: bool,
     #[allow(dead_code)]
     is_merge: bool,
-    #[allow(dead_code)]
-    branches: Vec<GitRef>,
     #[getset(get = "pub")]
     references: Vec<GitRef>,
-    #[allow(dead_code)]
     tags: Vec<GitRef>,
+    /// Status of the signature as printed by `%G?`, empty when signatures are not verified
+    #[getset(get = "pub")]
+    signature: String,
 }
 
 impl Commit {
@@ -106,37 +108,32 @@ impl Commit {
     pub fn is_merge(&self) -> bool {
         self.parents.len() >= 2
     }
-
-    pub fn matches(&self, needle: &Needle) -> bool {
-        let branches = &mut self.branches.iter().map(|v| &v.0).collect::<Vec<_>>();
-        let tags = &mut self.tags.iter().map(|v| &v.0).collect::<Vec<_>>();
-        let mut candidates = vec![
-            self.author_name(),
-            self.short_id(),
-            &self.id().0,
-            self.author_name(),
-            self.author_email(),
-            self.committer_name(),
-            self.committer_email(),
-            &self.subject,
-        ];
-        candidates.append(branches);
-        candidates.append(tags);
-        if *needle.ignore_case() {
-            let needle_lowercase = needle.text();
-            candidates
-                .iter()
-                .map(|x| x.to_lowercase())
-                .any(|x| x.contains(needle_lowercase))
-        } else {
-            candidates.iter().any(|x| x.contains(needle.text()))
-        }
-    }
 }
 
 const REV_FORMAT: &str =
     "--format=%x1f%H%x1f%h%x1f%P%x1f%D%x1f%aN%x1f%aE%x1f%aI%x1f%ad%x1f%cN%x1f%cE%x1f%cI%x1f%cd%x1f%s%x1f%b%x1e";
 
+/// Like [`REV_FORMAT`], but also verifying the signatures
+const REV_FORMAT_SIGNATURES: &str =
+    "--format=%x1f%H%x1f%h%x1f%P%x1f%D%x1f%aN%x1f%aE%x1f%aI%x1f%ad%x1f%cN%x1f%cE%x1f%cI%x1f%cd%x1f%s%x1f%b%x1f%G?%x1e";
+
+/// Set when the signatures of the commits are shown. Verifying them is slow, so it is only done
+/// when needed.
+static VERIFY_SIGNATURES: AtomicBool = AtomicBool::new(false);
+
+/// Verify the signatures of all commits read from now on
+pub fn verify_signatures() {
+    VERIFY_SIGNATURES.store(true, Ordering::SeqCst);
+}
+
+fn rev_format() -> &'static str {
+    if VERIFY_SIGNATURES.load(Ordering::SeqCst) {
+        REV_FORMAT_SIGNATURES
+    } else {
+        REV_FORMAT
+    }
+}
+
 impl Commit {
     #[must_use]
     pub fn new(data: &str) -> Self {
@@ -163,34 +160,7 @@ impl Commit {
         let committer_rel_date = next_string!(split);
         let subject = next_string!(split);
         let body = next_string!(split);
-
-        let mut is_head = false;
-
-        let mut references: Vec<GitRef> = Vec::new();
-        let mut branches: Vec<GitRef> = Vec::new();
-        let mut tags: Vec<GitRef> = Vec::new();
-        for s in references_record.split(", ") {
-            if s == "HEAD" {
-                is_head = true;
-            } else if s.starts_with("HEAD -> ") {
-                is_head = true;
-                let split_2: Vec<&str> = s.splitn(2, " -> ").collect();
-                let branch = split_2[1].to_owned();
-                branches.push(GitRef(branch.clone()));
-                references.push(GitRef(branch));
-            } else if s.starts_with("tag: ") {
-                let split_2: Vec<&str> = s.splitn(2, ": ").collect();
-                let tag = split_2[1].to_owned();
-                tags.push(GitRef(tag.clone()));
-                references.push(GitRef(tag));
-            } else if s.is_empty() {
-                // do nothing
-            } else {
-                let branch = s.to_owned();
-                branches.push(GitRef(branch.clone()));
-                references.push(GitRef(branch));
-            }
-        }
+        let signature = split.next().unwrap_or_default().trim_end().to_owned();
 
         let is_merge = parents_record.len() >= 2;
 
@@ -199,7 +169,7 @@ impl Commit {
             .map(|c| Oid(c.to_owned()))
             .collect();
 
-        Self {
+        let mut result = Self {
             id,
             short_id,
             author_name,
@@ -213,17 +183,77 @@ impl Commit {
             subject,
             body,
             parents,
-            is_head,
             is_merge,
-            branches,
-            references,
-            tags,
+            signature,
+            ..Self::default()
+        };
+        result.set_references(&references_record);
+        result
+    }
+
+    /// Replace the references with the ones from a ref names record as printed by `%D`
+    pub fn set_references(&mut self, references_record: &str) {
+        self.is_head = false;
+        self.references.clear();
+        self.tags.clear();
+        for s in references_record.split(", ") {
+            if s == "HEAD" {
+                self.is_head = true;
+            } else if s.starts_with("HEAD -> ") {
+                self.is_head = true;
+                let split_2: Vec<&str> = s.splitn(2, " -> ").collect();
+                self.references.push(GitRef(split_2[1].to_owned()));
+            } else if s.starts_with("tag: ") {
+                let split_2: Vec<&str> = s.splitn(2, ": ").collect();
+                let tag = split_2[1].to_owned();
+                self.tags.push(GitRef(tag.clone()));
+                self.references.push(GitRef(tag));
+            } else if s.is_empty() {
+                // do nothing
+            } else {
+                self.references.push(GitRef(s.to_owned()));
+            }
         }
     }
 
     pub fn from_repo(repo: &Repository, oid: &Oid) -> Option<Self> {
         to_commit(repo, oid)
     }
+
+    /// Serialize the fields, which do not change over time, in the format parsed by
+    /// [`Commit::new`]. References, relative dates & the signature status are left empty.
+    #[must_use]
+    pub fn to_record(&self) -> String {
+        let parents: Vec<&str> = self.parents.iter().map(|p| p.0.as_str()).collect();
+        [
+            self.id.0.as_str(),
+            &self.short_id,
+            &parents.join(" "),
+            "",
+            &self.author_name,
+            &self.author_email,
+            &self.author_date,
+            "",
+            &self.committer_name,
+            &self.committer_email,
+            &self.committer_date,
+            "",
+            &self.subject,
+            &self.body,
+        ]
+        .iter()
+        .fold(String::new(), |acc, field| acc + "\x1f" + field)
+    }
+
+    /// A commit without any data, used for entries which are still loading
+    #[must_use]
+    pub fn placeholder(short_id_width: usize, subject: &str) -> Self {
+        Self {
+            short_id: " ".repeat(short_id_width),
+            subject: subject.to_owned(),
+            ..Self::default()
+        }
+    }
 }
 
 /// Return commit count with `--first-parent`
@@ -274,7 +304,7 @@ where
 {
     let mut cmd = repo.git();
     cmd.arg("rev-list")
-        .args(vec!["--date=human", "--first-parent", REV_FORMAT]);
+        .args(vec!["--date=human", "--first-parent", rev_format()]);
 
     let tmp;
     if let Some(val) = skip {
@@ -320,6 +350,103 @@ where
     vec![]
 }
 
+/// Return the ref names of all commits pointed to by a reference, as printed by `%D`
+#[must_use]
+pub fn decorations(repo: &Repository) -> HashMap<Oid, String> {
+    let mut cmd = repo.git();
+    cmd.args(["log", "--no-walk=unsorted", "--all", "--format=%H%x1f%D"]);
+    match cmd.output() {
+        Ok(proc) if proc.status.success() => String::from_utf8_lossy(&proc.stdout)
+            .lines()
+            .filter_map(|l| l.split_once('\x1f'))
+            .map(|(id, names)| (Oid(id.to_owned()), names.to_owned()))
+            .collect(),
+        Ok(proc) => {
+            log::error!(
+                "git-log(1) --no-walk failed: {}",
+                String::from_utf8_lossy(&proc.stderr)
+            );
+            HashMap::new()
+        }
+        Err(err) => {
+            log::error!("Failed to run git-log(1): {}", err);
+            HashMap::new()
+        }
+    }
+}
+
+/// Return the ids of all commits in the range, which diffs match the pickaxe arguments, i.e.
+/// `-S<text>` or `-G<regex>`. Commits from merged branches are included.
+#[must_use]
+pub fn pickaxe(
+    repo: &Repository,
+    rev_range: &[OsString],
+    paths: &[PathBuf],
+    args: &[String],
+) -> HashSet<Oid> {
+    let mut cmd = repo.git();
+    cmd.args(["log", "--format=%H"]).args(args).args(rev_range);
+    if !paths.is_empty() {
+        cmd.arg("--").args(paths);
+    }
+    match cmd.output() {
+        Ok(proc) if proc.status.success() => String::from_utf8_lossy(&proc.stdout)
+            .lines()
+            .map(|l| Oid(l.to_owned()))
+            .collect(),
+        Ok(proc) => {
+            log::error!(
+                "git-log(1) {:?} failed: {}",
+                args,
+                String::from_utf8_lossy(&proc.stderr)
+            );
+            HashSet::new()
+        }
+        Err(err) => {
+            log::error!("Failed to run git-log(1): {}", err);
+            HashSet::new()
+        }
+    }
+}
+
+/// Return the paths of files changed by each commit in the range. Merges are not included.
+#[must_use]
+pub fn changed_files(
+    repo: &Repository,
+    rev_range: &[OsString],
+    paths: &[PathBuf],
+) -> HashMap<Oid, Vec<String>> {
+    let mut cmd = repo.git();
+    cmd.args(["log", "--format=%x1f%H", "--name-only", "-z"])
+        .args(rev_range);
+    if !paths.is_empty() {
+        cmd.arg("--").args(paths);
+    }
+    let mut result = HashMap::new();
+    match cmd.output() {
+        Ok(proc) if proc.status.success() => {
+            let output = String::from_utf8_lossy(&proc.stdout);
+            // With `-z` paths are not quoted & end with a NUL, like the id
+            for record in output.split('\x1f').filter(|r| !r.is_empty()) {
+                let mut fields = record.split('\0');
+                if let Some(id) = fields.next() {
+                    let files = fields
+                        .map(|f| f.trim_start_matches('\n'))
+                        .filter(|f| !f.is_empty())
+                        .map(ToOwned::to_owned);
+                    result.insert(Oid(id.to_owned()), files.collect());
+                }
+            }
+        }
+        Ok(proc) => log::error!(
+            "git-log(1) --name-only failed: {}",
+            String::from_utf8_lossy(&proc.stderr)
+        ),
+        Err(err) => log::error!("Failed to run git-log(1): {}", err),
+    }
+    result
+}
+
 #[must_use]
 pub fn child_history(repo: &Repository, commit: &Commit, paths: &[PathBuf]) -> Vec<Commit> {
     let bellow = commit.parents.first().expect("Expected merge commit");
@@ -341,9 +468,33 @@ pub fn child_history(repo: &Repository, commit: &Commit, paths: &[PathBuf]) -> V
     commits_for_range(repo, &vec![revision], paths, None, None)
 }
 
+/// Return the child commits of a merge and the link commit if the children do not end on the
+/// first parent of the merge.
+#[must_use]
+pub fn unfolded_children(
+    repo: &Repository,
+    merge: &Commit,
+    paths: &[PathBuf],
+) -> (Vec<Commit>, Option<Commit>) {
+    let children = child_history(repo, merge, paths);
+    let link_commit = if let (Some(Some(oid)), Some(bellow_merge)) = (
+        children.last().map(|c| c.parents().first()),
+        merge.parents().first(),
+    ) {
+        if oid == bellow_merge {
+            None
+        } else {
+            Commit::from_repo(repo, oid)
+        }
+    } else {
+        None
+    };
+    (children, link_commit)
+}
+
 fn to_commit(repo: &Repository, oid: &Oid) -> Option<Commit> {
     let mut cmd = repo.git();
-    cmd.args(["rev-list", "--date=human", REV_FORMAT, "-1", &oid.0]);
+    cmd.args(["rev-list", "--date=human", rev_format(), "-1", &oid.0]);
     let proc = cmd.output().expect("Failed to run git-rev-list(1)");
     proc.status.success().then(|| {
         let tmp = String::from_utf8_lossy(&proc.stdout);
@@ -48,6 +48,30 @@ lazy_static::lazy_static! {
         underline_color: None,
         attributes: DEFAULT_STYLE.attributes
     };
+    pub static ref HEAD_STYLE: ContentStyle = ContentStyle {
+        foreground_color: Some(Color::Cyan),
+        background_color: DEFAULT_STYLE.background_color,
+        underline_color: None,
+        attributes: Attributes::from(Attribute::Bold),
+    };
+    pub static ref LOCAL_BRANCH_STYLE: ContentStyle = ContentStyle {
+        foreground_color: Some(Color::Green),
+        background_color: DEFAULT_STYLE.background_color,
+        underline_color: None,
+        attributes: Attributes::from(Attribute::Bold),
+    };
+    pub static ref REMOTE_BRANCH_STYLE: ContentStyle = ContentStyle {
+        foreground_color: Some(Color::DarkRed),
+        background_color: DEFAULT_STYLE.background_color,
+        underline_color: None,
+        attributes: DEFAULT_STYLE.attributes
+    };
+    pub static ref TAG_STYLE: ContentStyle = ContentStyle {
+        foreground_color: Some(Color::Yellow),
+        background_color: DEFAULT_STYLE.background_color,
+        underline_color: None,
+        attributes: Attributes::from(Attribute::Bold),
+    };
 
     pub static ref DEBUG_STYLE: ContentStyle = ContentStyle {
         foreground_color: Some(Color::DarkRed),
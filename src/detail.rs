@@ -15,26 +15,165 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::io::Write;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
+use std::thread;
 
-use crossterm::event::Event;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::style::{style, ContentStyle, StyledContent};
+use getset::{CopyGetters, Getters};
 
 use git_wrapper::Repository;
 
+use crate::changes::{file_changes, FileChange};
 use crate::commit::Commit;
 use crate::commit::Oid;
 use crate::default_styles::{
     DATE_STYLE, DEBUG_STYLE, DEFAULT_STYLE, ID_STYLE, MOD_STYLE, NAME_STYLE, REF_STYLE,
 };
+#[cfg(feature = "syntax-highlighting")]
+use crate::highlight;
 use crate::history_entry::HistoryEntry;
 use crate::raw;
 use crate::ui::base::data::StyledAreaAdapter;
 use crate::ui::base::{Area, Drawable, HandleEvent, ListWidget, StyledArea, StyledLine};
 use crate::ui::layouts::DetailsWidget;
 
-pub struct DiffView(ListWidget<String>, Vec<PathBuf>, Repository);
+/// Commit marked for comparing it with the selected one
+#[derive(Clone, CopyGetters, Getters)]
+pub struct Comparison {
+    #[getset(get = "pub")]
+    from: Commit,
+    /// Compare with `A...B` instead of `A..B`
+    #[getset(get_copy = "pub")]
+    symmetric: bool,
+}
+
+impl Comparison {
+    pub const fn new(from: Commit, symmetric: bool) -> Self {
+        Self { from, symmetric }
+    }
+
+    /// Return the revision range from the marked commit to `to`
+    #[must_use]
+    pub fn rev(&self, to: &Commit) -> String {
+        let dots = if self.symmetric { "..." } else { ".." };
+        format!("{}{}{}", self.from.id().0, dots, to.id().0)
+    }
+}
+
+/// The selected entry shown in the [`DiffView`], optionally compared with a marked commit
+#[derive(Clone, Getters)]
+pub struct Selection {
+    #[getset(get = "pub")]
+    entry: HistoryEntry,
+    #[getset(get = "pub")]
+    comparison: Option<Comparison>,
+}
+
+impl Selection {
+    pub const fn new(entry: HistoryEntry, comparison: Option<Comparison>) -> Self {
+        Self { entry, comparison }
+    }
+}
+
+/// The base a merge commit is diffed against
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum MergeDiff {
+    /// Diff against the parent with the index
+    Parent(usize),
+    /// Combined diff (`--cc`), showing only the files which differ from all parents
+    Combined,
+    /// Diff against a re-merge of the parents, showing the conflict resolutions
+    Remerge,
+}
+
+impl Default for MergeDiff {
+    fn default() -> Self {
+        Self::Parent(0)
+    }
+}
+
+impl MergeDiff {
+    /// Return the next diff mode for a commit with `parents` parents
+    const fn next(self, parents: usize) -> Self {
+        match self {
+            Self::Parent(i) if i.saturating_add(1) < parents => Self::Parent(i.saturating_add(1)),
+            Self::Parent(_) => Self::Combined,
+            Self::Combined => Self::Remerge,
+            Self::Remerge => Self::Parent(0),
+        }
+    }
+
+    fn describe(self, commit: &Commit) -> String {
+        match self {
+            Self::Parent(i) => format!(
+                "parent {} of {} ({})",
+                i.saturating_add(1),
+                commit.parents().len(),
+                commit.parents().get(i).map_or("", |p| p.0.as_str())
+            ),
+            Self::Combined => "combined (--cc)".to_owned(),
+            Self::Remerge => "remerge-diff".to_owned(),
+        }
+    }
+
+    /// Return the git command & arguments showing the diff of the commit
+    fn args(self, commit: &Commit) -> Vec<String> {
+        let empty_tree = Oid("4b825dc642cb6eb9a060e54bf8d69288fbee4904".to_owned());
+        match self {
+            Self::Parent(i) => {
+                let bellow = commit.parents().get(i).unwrap_or(&empty_tree);
+                vec![
+                    "diff".to_owned(),
+                    format!("{}..{}", bellow.0, commit.id().0),
+                ]
+            }
+            Self::Combined => vec![
+                "show".to_owned(),
+                "--format=".to_owned(),
+                "--cc".to_owned(),
+                commit.id().0.clone(),
+            ],
+            Self::Remerge => vec![
+                "show".to_owned(),
+                "--format=".to_owned(),
+                "--remerge-diff".to_owned(),
+                commit.id().0.clone(),
+            ],
+        }
+    }
+}
+
+/// What the [`DiffView`] shows
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum View {
+    /// Commit details & the complete diff
+    Full,
+    /// List of the changed files
+    Files,
+    /// Diff of the changed file with the index
+    File(usize),
+}
+
+pub struct DiffView {
+    list: ListWidget<String>,
+    /// Indices of the file headers in `list`
+    file_lines: Vec<usize>,
+    /// Indices of the hunk headers in `list`
+    hunk_lines: Vec<usize>,
+    /// Changed files, loaded when the file list is shown the first time
+    files: Option<Vec<FileChange>>,
+    file_list: Option<ListWidget<String>>,
+    view: View,
+    paths: Vec<PathBuf>,
+    repo: Repository,
+    selection: Option<Selection>,
+    merge_diff: MergeDiff,
+    /// Wrap long lines, kept when another diff is shown
+    wrap: bool,
+}
 
 impl DiffView {
     pub fn new(repo: Repository, paths: Vec<PathBuf>) -> Self {
@@ -42,23 +181,295 @@ impl DiffView {
             content: vec![],
             thread: None,
         };
-        Self(ListWidget::new(Box::new(adapter)), paths, repo)
+        Self {
+            list: ListWidget::new(Box::new(adapter)),
+            file_lines: vec![],
+            hunk_lines: vec![],
+            files: None,
+            file_list: None,
+            view: View::Full,
+            paths,
+            repo,
+            selection: None,
+            merge_diff: MergeDiff::default(),
+            wrap: false,
+        }
+    }
+
+    /// Show the lines followed by the diff & remember the positions of its file & hunk headers
+    fn set_lines(&mut self, mut data: StyledArea<String>, diff: Diff) {
+        let offset = data.len();
+        self.file_lines = diff
+            .file_lines
+            .iter()
+            .map(|i| i.saturating_add(offset))
+            .collect();
+        self.hunk_lines = diff
+            .hunk_lines
+            .iter()
+            .map(|i| i.saturating_add(offset))
+            .collect();
+        data.extend(diff.lines);
+        let adapter = StyledAreaAdapter {
+            content: data,
+            thread: None,
+        };
+        let offset = self.list.offset();
+        self.list = ListWidget::new(Box::new(adapter));
+        self.list.set_wrap(self.wrap);
+        self.list.set_offset(offset);
+    }
+
+    /// Return the git diff command for the selection, see [`git_diff`]
+    fn diff_args(&self, selection: &Selection) -> Option<Vec<String>> {
+        let content = selection.entry();
+        let commit = content.commit();
+        if content.is_loading() {
+            return None;
+        }
+        match selection.comparison() {
+            Some(comparison) if comparison.from().id() != commit.id() => {
+                Some(vec!["diff".to_owned(), comparison.rev(commit)])
+            }
+            _ => Some(self.merge_diff.args(commit)),
+        }
+    }
+
+    /// Show the list of changed files
+    fn show_files(&mut self) {
+        if self.files.is_none() {
+            let args = self.selection.as_ref().and_then(|s| self.diff_args(s));
+            let files = args
+                .map(|args| file_changes(&self.repo, &args, &self.paths))
+                .unwrap_or_default();
+            let mut content: StyledArea<String> = files.iter().map(FileChange::render).collect();
+            if content.is_empty() {
+                content.push(color_text(" ", "No changed files", *DEFAULT_STYLE));
+            }
+            let adapter = StyledAreaAdapter {
+                content,
+                thread: None,
+            };
+            self.file_list = Some(ListWidget::new(Box::new(adapter)));
+            self.files = Some(files);
+        }
+        self.view = View::Files;
+    }
+
+    /// Show only the diff of the changed file with the index
+    fn show_file(&mut self, i: usize) {
+        let file = match self.files.as_ref().and_then(|files| files.get(i)) {
+            Some(file) => file.clone(),
+            None => return,
+        };
+        let args = match self.selection.as_ref().and_then(|s| self.diff_args(s)) {
+            Some(args) => args,
+            None => return,
+        };
+        let data = vec![
+            file.render(),
+            color_text(
+                " ",
+                "<f> file list  <]>/<[> next/previous file  <}>/<{> next/previous hunk",
+                *DEFAULT_STYLE,
+            ),
+            StyledLine::empty(),
+        ];
+        let diff = git_diff(&self.repo, &args, &file.paths());
+        self.set_lines(data, diff);
+        self.view = View::File(i);
+    }
+
+    /// Select the next or previous of the header lines
+    fn jump(&mut self, headers: &[usize], forward: bool) -> HandleEvent {
+        let current = self.list.selected();
+        let target = if forward {
+            headers.iter().find(|i| **i > current)
+        } else {
+            headers.iter().rev().find(|i| **i < current)
+        };
+        if let Some(i) = target {
+            self.list.select(*i);
+        }
+        HandleEvent::Handled
+    }
+
+    fn on_file_list_event(&mut self, event: &Event) -> HandleEvent {
+        if let Some(list) = &mut self.file_list {
+            if list.on_event(event) == HandleEvent::Handled {
+                return HandleEvent::Handled;
+            }
+        }
+        match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Enter,
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => {
+                let i = self.file_list.as_ref().map_or(0, ListWidget::selected);
+                self.show_file(i);
+                HandleEvent::Handled
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('f'),
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => {
+                if let Some(selection) = self.selection.take() {
+                    self.show(&selection);
+                    self.selection = Some(selection);
+                }
+                HandleEvent::Handled
+            }
+            _ => HandleEvent::Ignored,
+        }
+    }
+
+    /// Show the diff of the selected merge against the next base
+    fn cycle_merge_diff(&mut self) -> HandleEvent {
+        let selection = match self.selection.take() {
+            Some(selection) => selection,
+            None => return HandleEvent::Ignored,
+        };
+        let commit = selection.entry().commit();
+        let result = if commit.is_merge() && selection.comparison().is_none() {
+            self.merge_diff = self.merge_diff.next(commit.parents().len());
+            self.files = None;
+            self.show(&selection);
+            HandleEvent::Handled
+        } else {
+            HandleEvent::Ignored
+        };
+        self.selection = Some(selection);
+        result
+    }
+
+    /// Show the commits & the diff between the marked & the selected commit
+    fn set_comparison(&mut self, comparison: &Comparison, to: &Commit) {
+        let from = comparison.from();
+        let rev = comparison.rev(to);
+        let mut data: StyledArea<String> = vec![
+            color_text("Compare:         ", &rev, *ID_STYLE),
+            color_text(
+                "From:            ",
+                &format!("{} {}", from.short_id(), from.subject()),
+                *DEFAULT_STYLE,
+            ),
+            color_text(
+                "To:              ",
+                &format!("{} {}", to.short_id(), to.subject()),
+                *DEFAULT_STYLE,
+            ),
+            StyledLine::empty(),
+        ];
+        let mut cmd = self.repo.git();
+        cmd.args(["log", "--color=always", "--oneline", "--left-right", &rev]);
+        if !self.paths.is_empty() {
+            cmd.arg("--");
+            cmd.args(&self.paths);
+        }
+        match cmd.output() {
+            Ok(output) => data.append(&mut raw::parse_spans(output.stdout)),
+            Err(err) => log::warn!("Failed to execute git-log(1): {}", err),
+        }
+        data.push(StyledLine::empty());
+        data.push(StyledLine {
+            content: vec![style(
+                "                                 ❦ ❦ ❦ ❦ ".to_owned(),
+            )],
+            links: vec![],
+        });
+        data.push(StyledLine::empty());
+        let args = ["diff".to_owned(), rev];
+        let diff = git_diff(&self.repo, &args, self.paths.as_ref());
+        self.set_lines(data, diff);
     }
 }
 
 impl Drawable for DiffView {
     fn render(&mut self, area: &Area) -> StyledArea<String> {
-        self.0.render(area)
+        match (self.view, &mut self.file_list) {
+            (View::Files, Some(list)) => list.render(area),
+            _ => self.list.render(area),
+        }
     }
 
     fn on_event(&mut self, event: &Event) -> HandleEvent {
-        self.0.on_event(event)
+        if self.view == View::Files {
+            return self.on_file_list_event(event);
+        }
+        if self.list.on_event(event) == HandleEvent::Handled {
+            return HandleEvent::Handled;
+        }
+        let c = match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Char(c),
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                ..
+            }) => *c,
+            _ => return HandleEvent::Ignored,
+        };
+        match (c, self.view) {
+            ('d', View::Full) => self.cycle_merge_diff(),
+            ('w', _) => {
+                self.wrap = !self.wrap;
+                self.list.set_wrap(self.wrap);
+                HandleEvent::Handled
+            }
+            ('f', _) => {
+                self.show_files();
+                HandleEvent::Handled
+            }
+            (']', View::File(i)) => {
+                self.show_file(i.saturating_add(1));
+                HandleEvent::Handled
+            }
+            ('[', View::File(i)) => {
+                if let Some(i) = i.checked_sub(1) {
+                    self.show_file(i);
+                }
+                HandleEvent::Handled
+            }
+            (']' | '[', _) => {
+                let headers = self.file_lines.clone();
+                self.jump(&headers, c == ']')
+            }
+            ('}' | '{', _) => {
+                let headers = self.hunk_lines.clone();
+                self.jump(&headers, c == '}')
+            }
+            _ => HandleEvent::Ignored,
+        }
+    }
+}
+
+impl DetailsWidget<Selection> for DiffView {
+    fn set_content(&mut self, selection: &Selection) {
+        self.merge_diff = MergeDiff::default();
+        self.files = None;
+        self.show(selection);
+        self.selection = Some(selection.clone());
     }
 }
 
-impl DetailsWidget<HistoryEntry> for DiffView {
-    fn set_content(&mut self, content: &HistoryEntry) {
+impl DiffView {
+    fn show(&mut self, selection: &Selection) {
+        self.view = View::Full;
+        let content = selection.entry();
         let commit = content.commit();
+        if let Some(comparison) = selection.comparison() {
+            if !content.is_loading() && comparison.from().id() != commit.id() {
+                self.set_comparison(comparison, commit);
+                return;
+            }
+        }
+        if content.is_loading() {
+            self.set_lines(
+                vec![color_text(" ", commit.subject(), *DEFAULT_STYLE)],
+                Diff::default(),
+            );
+            return;
+        }
         let mut data: StyledArea<String> = vec![
             color_text("Commit:          ", &commit.id().0, *ID_STYLE),
             color_text(
@@ -115,6 +526,13 @@ impl DetailsWidget<HistoryEntry> for DiffView {
                 *REF_STYLE,
             ));
         }
+        if commit.is_merge() {
+            data.push(color_text(
+                "Diff:            ",
+                &format!("{}, <d> to cycle", self.merge_diff.describe(commit)),
+                *DEFAULT_STYLE,
+            ));
+        }
         if *content.debug() {
             add_debug_content(&mut data, content);
         }
@@ -132,22 +550,22 @@ impl DetailsWidget<HistoryEntry> for DiffView {
             content: vec![style(
                 "                                 ❦ ❦ ❦ ❦ ".to_owned(),
             )],
+            links: vec![],
         });
         data.push(StyledLine::empty());
-        for line in git_diff(&self.2, commit, self.1.as_ref()) {
-            data.push(line);
-        }
-        let adapter = StyledAreaAdapter {
-            content: data,
-            thread: None,
-        };
-        self.0 = ListWidget::new(Box::new(adapter));
+        let diff = git_diff(
+            &self.repo,
+            &self.merge_diff.args(commit),
+            self.paths.as_ref(),
+        );
+        self.set_lines(data, diff);
     }
 }
 
 fn add_debug_content(data: &mut Vec<StyledLine<String>>, content: &HistoryEntry) {
     data.push(StyledLine {
         content: vec![style("                                 DEBUG".to_owned())],
+        links: vec![],
     });
     data.push(color_text(
         "fork_point:      ",
@@ -187,49 +605,339 @@ fn add_debug_content(data: &mut Vec<StyledLine<String>>, content: &HistoryEntry)
         content: vec![style(
             "                                 ❦ ❦ ❦ ❦ ".to_owned(),
         )],
+        links: vec![],
     });
 }
 
-fn git_diff(repo: &Repository, commit: &Commit, paths: &[PathBuf]) -> Vec<StyledLine<String>> {
-    let empty_tree = Oid("4b825dc642cb6eb9a060e54bf8d69288fbee4904".to_owned());
-    let bellow = commit.parents().first().unwrap_or(&empty_tree);
-    let rev = format!("{}..{}", bellow.0, commit.id().0);
+/// Colored output of git-diff(1) & the indices of its file & hunk headers
+#[derive(Default)]
+struct Diff {
+    lines: StyledArea<String>,
+    file_lines: Vec<usize>,
+    hunk_lines: Vec<usize>,
+}
+
+/// Run the git diff command, i.e. `["diff", "A..B"]`, & return the colored output
+fn git_diff(repo: &Repository, args: &[String], paths: &[PathBuf]) -> Diff {
+    let delta = which::which("delta").is_ok();
     let mut cmd = repo.git();
-    cmd.args(&[
-        "diff",
-        "--color=always",
-        "--stat",
-        "-p",
-        "-M",
-        "--full-index",
-        &rev,
-    ]);
+    cmd.args(&args[..1]);
+    cmd.arg(if delta { "--color=always" } else { DIFF_COLOR });
+    cmd.args(["--stat", "-p", "-M", "--full-index"]);
+    cmd.args(&args[1..]);
     if !paths.is_empty() {
         cmd.arg("--");
         cmd.args(paths);
     }
 
-    if which::which("delta").is_ok() {
-        let proc = cmd.stdout(Stdio::piped()).spawn().unwrap();
-
-        let delta_p = Command::new("delta")
+    let output = cmd.output().expect("Failed to execute git-diff(1)").stdout;
+    // The headers are found in the diff without colors
+    let plain: Vec<String> = raw::parse_spans(output.clone())
+        .iter()
+        .map(line_text)
+        .collect();
+    if delta {
+        let mut delta_p = Command::new("delta")
             .arg("--paging=never")
-            .stdin(Stdio::from(proc.stdout.unwrap()))
-            .output()
-            .unwrap();
-        raw::parse_spans(delta_p.stdout)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to execute delta(1)");
+        let mut stdin = delta_p.stdin.take().expect("Piped stdin");
+        let writer = thread::spawn(move || stdin.write_all(&output));
+        let rendered = delta_p
+            .wait_with_output()
+            .expect("Failed to wait for delta(1)");
+        if let Ok(Err(err)) = writer.join() {
+            log::warn!("Failed to write the diff to delta(1): {}", err);
+        }
+        let lines = raw::parse_spans(rendered.stdout);
+        let texts: Vec<String> = lines.iter().map(line_text).collect();
+        let (file_lines, hunk_lines) = delta_headers(&plain, &texts);
+        Diff {
+            lines,
+            file_lines,
+            hunk_lines,
+        }
     } else {
-        let proc = cmd
-            .args(paths)
-            .output()
-            .expect("Failed to execute git-diff(1)");
-        raw::parse_spans(proc.stdout)
+        let (file_lines, hunk_lines) = diff_headers(&plain);
+        Diff {
+            lines: parse_diff(output),
+            file_lines,
+            hunk_lines,
+        }
+    }
+}
+
+fn line_text(line: &StyledLine<String>) -> String {
+    line.content.iter().map(|c| c.content().as_str()).collect()
+}
+
+/// Return the indices of the file & hunk headers in the plain output of git-diff(1)
+fn diff_headers(plain: &[String]) -> (Vec<usize>, Vec<usize>) {
+    let mut files = vec![];
+    let mut hunks = vec![];
+    for (i, line) in plain.iter().enumerate() {
+        if line.starts_with("diff --") {
+            files.push(i);
+        } else if line.starts_with("@@") {
+            hunks.push(i);
+        }
+    }
+    (files, hunks)
+}
+
+/// Return the text of a line without whitespace, so tabs expanded by `delta(1)` still match
+fn squeeze(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Return the indices of the file & hunk headers of the plain diff in the output of `delta(1)`.
+///
+/// Delta renders headers with a varying number of decoration lines, but keeps one line per
+/// content line. So each hunk is found via its first non-empty content line. The headers before
+/// it are the last lines with any text in the gap since the previous hunk.
+fn delta_headers(plain: &[String], rendered: &[String]) -> (Vec<usize>, Vec<usize>) {
+    let mut files = vec![];
+    let mut hunks = vec![];
+    // Headers in the current gap, `true` for hunks
+    let mut pending: Vec<bool> = vec![];
+    let mut start = 0;
+    let mut i = 0;
+    while let Some(line) = plain.get(i) {
+        i = i.saturating_add(1);
+        if line.starts_with("diff --") {
+            pending.push(false);
+            continue;
+        }
+        if !line.starts_with("@@") {
+            continue;
+        }
+        pending.push(true);
+        // Combined diffs have a column per parent & one more `@` per parent
+        let columns = line
+            .chars()
+            .take_while(|c| *c == '@')
+            .count()
+            .saturating_sub(1);
+        let content: Vec<&String> = plain[i..]
+            .iter()
+            .take_while(|l| l.is_empty() || l.starts_with([' ', '+', '-', '\\']))
+            .collect();
+        i = i.saturating_add(content.len());
+        let anchor = content.iter().enumerate().find_map(|(n, l)| {
+            let text = squeeze(l.get(columns..).unwrap_or_default());
+            (!text.is_empty() && !l.starts_with('\\')).then_some((n, text))
+        });
+        let (n, text) = match anchor {
+            Some(anchor) => anchor,
+            None => continue,
+        };
+        let found = rendered
+            .iter()
+            .enumerate()
+            .skip(start)
+            .find(|(_, r)| squeeze(r).ends_with(&text));
+        let first_content = match found {
+            Some((r, _)) => r.saturating_sub(n),
+            None => {
+                log::debug!("Failed to find the hunk content in the output of delta(1)");
+                break;
+            }
+        };
+        resolve_headers(
+            rendered,
+            start,
+            first_content,
+            &pending,
+            &mut files,
+            &mut hunks,
+        );
+        pending.clear();
+        let shown = content.iter().filter(|l| !l.starts_with('\\')).count();
+        start = first_content.saturating_add(shown).min(rendered.len());
     }
+    resolve_headers(
+        rendered,
+        start,
+        rendered.len(),
+        &pending,
+        &mut files,
+        &mut hunks,
+    );
+    files.sort_unstable();
+    hunks.sort_unstable();
+    (files, hunks)
+}
+
+/// Assign the pending headers to the last lines with text in `rendered[start..end]`
+fn resolve_headers(
+    rendered: &[String],
+    start: usize,
+    end: usize,
+    pending: &[bool],
+    files: &mut Vec<usize>,
+    hunks: &mut Vec<usize>,
+) {
+    let mut limit = end;
+    for is_hunk in pending.iter().rev() {
+        let found = (start..limit)
+            .rev()
+            .find(|j| rendered[*j].chars().any(char::is_alphanumeric));
+        match found {
+            Some(j) if *is_hunk => hunks.push(j),
+            Some(j) => files.push(j),
+            None => break,
+        }
+        limit = found.unwrap_or_default();
+    }
+}
+
+/// Without `delta(1)` the diff is highlighted in-process, if built with syntax highlighting
+#[cfg(feature = "syntax-highlighting")]
+const DIFF_COLOR: &str = "--color=never";
+#[cfg(not(feature = "syntax-highlighting"))]
+const DIFF_COLOR: &str = "--color=always";
+
+#[cfg(feature = "syntax-highlighting")]
+fn parse_diff(output: Vec<u8>) -> StyledArea<String> {
+    highlight::highlight_diff(&String::from_utf8_lossy(&output))
+}
+
+#[cfg(not(feature = "syntax-highlighting"))]
+fn parse_diff(output: Vec<u8>) -> StyledArea<String> {
+    raw::parse_spans(output)
 }
 
 fn color_text(key: &str, value: &str, style: ContentStyle) -> StyledLine<String> {
     let content = format!("{}{}", key, value);
     StyledLine {
         content: vec![StyledContent::new(style, content)],
+        links: vec![],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{delta_headers, diff_headers, Comparison, MergeDiff};
+    use crate::commit::Commit;
+
+    fn commit(id: &str) -> Commit {
+        merge(id, "")
+    }
+
+    fn merge(id: &str, parents: &str) -> Commit {
+        Commit::new(&format!(
+            "\x1f{}\x1f{}\x1f{}\x1f\x1fAlice\x1falice@example.com\x1f2024-01-15T10:00:00+01:00\x1f2 days ago\x1fAlice\x1falice@example.com\x1f2024-01-15T10:00:00+01:00\x1f2 days ago\x1fSubject\x1f",
+            id,
+            &id[..7],
+            parents
+        ))
+    }
+
+    #[test]
+    fn comparison_rev() {
+        let from = commit("a17989470af");
+        let to = commit("b2f3a18c9d0");
+        assert_eq!(
+            Comparison::new(from.clone(), false).rev(&to),
+            "a17989470af..b2f3a18c9d0"
+        );
+        assert_eq!(
+            Comparison::new(from, true).rev(&to),
+            "a17989470af...b2f3a18c9d0"
+        );
+    }
+
+    #[test]
+    fn merge_diff_modes() {
+        let commit = merge("a17989470af", "b2f3a18c9d0 c3e4f5a6b7d");
+        let mut mode = MergeDiff::default();
+        let mut args = vec![];
+        for _ in 0..5 {
+            args.push(mode.args(&commit).join(" "));
+            mode = mode.next(commit.parents().len());
+        }
+        assert_eq!(
+            args,
+            [
+                "diff b2f3a18c9d0..a17989470af",
+                "diff c3e4f5a6b7d..a17989470af",
+                "show --format= --cc a17989470af",
+                "show --format= --remerge-diff a17989470af",
+                "diff b2f3a18c9d0..a17989470af",
+            ]
+        );
+        assert_eq!(
+            MergeDiff::Parent(1).describe(&commit),
+            "parent 2 of 2 (c3e4f5a6b7d)"
+        );
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(ToOwned::to_owned).collect()
+    }
+
+    const PLAIN: &str = " a.rs | 3 ++-
+ b.rs | 1 +
+ 2 files changed, 3 insertions(+), 1 deletion(-)
+
+diff --git a/a.rs b/a.rs
+index 1111111111111111111111111111111111111111..2222222222222222222222222222222222222222 100644
+--- a/a.rs
++++ b/a.rs
+@@ -1,3 +1,3 @@ fn main() {
+ 
+-	let x = 1;
++	let x = 2;
+@@ -10,2 +10,3 @@ fn other() {
+     foo();
++    bar();
+diff --git a/b.rs b/b.rs
+index 3333333333333333333333333333333333333333..4444444444444444444444444444444444444444 100644
+--- a/b.rs
++++ b/b.rs
+@@ -1 +1,2 @@
+ use std::io;
++use std::fs;";
+
+    #[test]
+    fn plain_headers() {
+        assert_eq!(diff_headers(&lines(PLAIN)), (vec![4, 15], vec![8, 12, 19]));
+    }
+
+    #[test]
+    fn delta_style_headers() {
+        let rendered = " a.rs | 3 ++-
+ b.rs | 1 +
+ 2 files changed, 3 insertions(+), 1 deletion(-)
+
+Δ a.rs
+────────────────────
+
+───────────────┐
+• a.rs:1: fn main() { │
+───────────────┘
+
+    let x = 1;
+    let x = 2;
+───────────────┐
+• a.rs:10: fn other() { │
+───────────────┘
+    foo();
+    bar();
+
+Δ b.rs
+────────────────────
+
+• b.rs:1:
+use std::io;
+use std::fs;";
+        assert_eq!(
+            delta_headers(&lines(PLAIN), &lines(rendered)),
+            (vec![4, 19], vec![8, 14, 22])
+        );
     }
 }
@@ -0,0 +1,68 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The event channel of the UI loop.
+//!
+//! The UI sleeps until either the user does something or a background thread has new data,
+//! i.e. a fork point or a pull request title. Background threads call [`wake`] after sending
+//! their results, so the next render picks them up.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::OnceLock;
+
+use crossterm::event::Event;
+
+#[derive(Debug)]
+pub enum UiEvent {
+    /// Terminal input from the user
+    Input(Event),
+    /// New data is available
+    Wake,
+}
+
+static SENDER: OnceLock<Sender<UiEvent>> = OnceLock::new();
+
+/// Set while a [`UiEvent::Wake`] is queued, so a burst of results wakes the UI only once
+static PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Create the event channel of the UI loop
+///
+/// # Panics
+///
+/// Panics when called twice
+pub fn channel() -> (Sender<UiEvent>, Receiver<UiEvent>) {
+    let (tx, rx) = mpsc::channel();
+    SENDER
+        .set(tx.clone())
+        .expect("The event channel is created once");
+    (tx, rx)
+}
+
+/// Wake up the UI loop to render new data
+pub fn wake() {
+    if let Some(sender) = SENDER.get() {
+        if !PENDING.swap(true, Ordering::SeqCst) && sender.send(UiEvent::Wake).is_err() {
+            log::debug!("UI loop stopped, ignoring wake up");
+        }
+    }
+}
+
+/// Signal that the UI loop received the [`UiEvent::Wake`] & will render the new data
+pub fn woken() {
+    PENDING.store(false, Ordering::SeqCst);
+}
@@ -0,0 +1,284 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Web URLs of commits, pull requests & files on a forge.
+
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use url::Url;
+
+use crate::commit::Oid;
+
+/// The supported forges & their URL schemes
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+    BitbucketCloud,
+    BitbucketServer,
+    Gitea,
+}
+
+impl Forge {
+    /// Guess the forge from the host of a remote URL. Unknown hosts use the GitHub scheme.
+    #[must_use]
+    pub fn detect(remote: &Url) -> Self {
+        let host = remote.host_str().unwrap_or_default();
+        if host == "bitbucket.org" {
+            Self::BitbucketCloud
+        } else if host.contains("bitbucket") {
+            Self::BitbucketServer
+        } else if host.contains("gitlab") {
+            Self::GitLab
+        } else if host.contains("gitea") || host.contains("codeberg") {
+            Self::Gitea
+        } else {
+            Self::GitHub
+        }
+    }
+}
+
+/// Return the https URL of the repository web page for a remote URL, ending with a slash.
+///
+/// SSH remotes like `git@host:owner/repo` are converted to `https://host/owner/repo/`. The port
+/// is only kept for http(s) remotes, because SSH ports are not the ports of the web server.
+fn web_url(remote: &Url, forge: Forge) -> Option<Url> {
+    let host = remote.host_str()?;
+    let segments: Vec<&str> = remote.path_segments()?.filter(|s| !s.is_empty()).collect();
+    let path = match (forge, segments.as_slice()) {
+        // Bitbucket Server serves repositories under `projects/<project>/repos/<repo>`
+        (Forge::BitbucketServer, [.., project, repo]) => {
+            format!("projects/{}/repos/{}", project, repo)
+        }
+        (Forge::BitbucketServer, _) | (_, []) => return None,
+        (_, segments) => segments.join("/"),
+    };
+    let host = match (remote.scheme(), remote.port()) {
+        ("http" | "https", Some(port)) => format!("{}:{}", host, port),
+        _ => host.to_owned(),
+    };
+    let scheme = if remote.scheme() == "http" {
+        "http"
+    } else {
+        "https"
+    };
+    Url::parse(&format!("{}://{}/{}/", scheme, host, path)).ok()
+}
+
+/// Return the web URL of a commit
+#[must_use]
+pub fn commit_url(remote: &Url, oid: &Oid) -> Option<Url> {
+    let forge = Forge::detect(remote);
+    let suffix = match forge {
+        Forge::GitHub | Forge::Gitea => format!("commit/{}", oid.0),
+        Forge::GitLab => format!("-/commit/{}", oid.0),
+        Forge::BitbucketCloud | Forge::BitbucketServer => format!("commits/{}", oid.0),
+    };
+    web_url(remote, forge)?.join(&suffix).ok()
+}
+
+/// Return the web URL of a pull request
+#[must_use]
+pub fn pull_request_url(remote: &Url, id: &str) -> Option<Url> {
+    let id = id.trim_start_matches('#');
+    let forge = Forge::detect(remote);
+    let suffix = match forge {
+        Forge::GitHub => format!("pull/{}", id),
+        Forge::GitLab => format!("-/merge_requests/{}", id),
+        Forge::BitbucketCloud | Forge::BitbucketServer => format!("pull-requests/{}", id),
+        Forge::Gitea => format!("pulls/{}", id),
+    };
+    web_url(remote, forge)?.join(&suffix).ok()
+}
+
+/// Return the web URL of a file at a revision
+#[must_use]
+pub fn file_url(remote: &Url, oid: &Oid, path: &Path) -> Option<Url> {
+    let forge = Forge::detect(remote);
+    let path = path.to_str()?.trim_start_matches('/');
+    let suffix = match forge {
+        Forge::GitHub => format!("blob/{}/{}", oid.0, path),
+        Forge::GitLab => format!("-/blob/{}/{}", oid.0, path),
+        Forge::BitbucketCloud => format!("src/{}/{}", oid.0, path),
+        Forge::BitbucketServer => format!("browse/{}?at={}", path, oid.0),
+        Forge::Gitea => format!("src/commit/{}/{}", oid.0, path),
+    };
+    web_url(remote, forge)?.join(&suffix).ok()
+}
+
+/// Return the commands listed in `$BROWSER` for the URL. Like `xdg-open`, the value is a `:`
+/// separated list of commands, which arguments are separated by whitespace. A `%s` argument is
+/// replaced by the URL, otherwise the URL is appended.
+fn browser_commands(browser: &str, url: &str) -> Vec<Vec<String>> {
+    browser
+        .split(':')
+        .map(|command| {
+            let mut args: Vec<String> = command.split_whitespace().map(ToOwned::to_owned).collect();
+            if args.iter().any(|arg| arg.contains("%s")) {
+                for arg in &mut args {
+                    *arg = arg.replace("%s", url);
+                }
+            } else if !args.is_empty() {
+                args.push(url.to_owned());
+            }
+            args
+        })
+        .filter(|args| !args.is_empty())
+        .collect()
+}
+
+/// Open the URL with the first available command of `$BROWSER`, `xdg-open` or `open`.
+///
+/// Returns `false` if no opener is available.
+///
+/// # Errors
+///
+/// Returns an error if the opener could not be started
+pub fn open(url: &Url) -> io::Result<bool> {
+    let browser = std::env::var("BROWSER").unwrap_or_default();
+    let fallback = ["xdg-open", "open"].map(|cmd| vec![cmd.to_owned(), url.to_string()]);
+    let opener = browser_commands(&browser, url.as_str())
+        .into_iter()
+        .chain(fallback)
+        .find(|args| which::which(&args[0]).is_ok());
+    match opener {
+        None => Ok(false),
+        Some(args) => {
+            Command::new(&args[0])
+                .args(&args[1..])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()?;
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{browser_commands, commit_url, file_url, pull_request_url, Forge};
+    use crate::commit::{parse_remote_url, Oid};
+
+    fn oid() -> Oid {
+        Oid("a17989470af".to_owned())
+    }
+
+    #[test]
+    fn github() {
+        let remote = parse_remote_url("git@github.com:kalkin/git-log-viewer.git").unwrap();
+        assert_eq!(Forge::detect(&remote), Forge::GitHub);
+        assert_eq!(
+            commit_url(&remote, &oid()).unwrap().as_str(),
+            "https://github.com/kalkin/git-log-viewer/commit/a17989470af"
+        );
+        assert_eq!(
+            pull_request_url(&remote, "#23").unwrap().as_str(),
+            "https://github.com/kalkin/git-log-viewer/pull/23"
+        );
+        assert_eq!(
+            file_url(&remote, &oid(), Path::new("src/main.rs"))
+                .unwrap()
+                .as_str(),
+            "https://github.com/kalkin/git-log-viewer/blob/a17989470af/src/main.rs"
+        );
+    }
+
+    #[test]
+    fn gitlab() {
+        let remote = parse_remote_url("https://gitlab.com/group/sub/project.git").unwrap();
+        assert_eq!(
+            commit_url(&remote, &oid()).unwrap().as_str(),
+            "https://gitlab.com/group/sub/project/-/commit/a17989470af"
+        );
+        assert_eq!(
+            pull_request_url(&remote, "7").unwrap().as_str(),
+            "https://gitlab.com/group/sub/project/-/merge_requests/7"
+        );
+    }
+
+    #[test]
+    fn bitbucket_cloud() {
+        let remote = parse_remote_url("git@bitbucket.org:owner/repo.git").unwrap();
+        assert_eq!(Forge::detect(&remote), Forge::BitbucketCloud);
+        assert_eq!(
+            commit_url(&remote, &oid()).unwrap().as_str(),
+            "https://bitbucket.org/owner/repo/commits/a17989470af"
+        );
+        assert_eq!(
+            pull_request_url(&remote, "5").unwrap().as_str(),
+            "https://bitbucket.org/owner/repo/pull-requests/5"
+        );
+        assert_eq!(
+            file_url(&remote, &oid(), Path::new("src/main.rs"))
+                .unwrap()
+                .as_str(),
+            "https://bitbucket.org/owner/repo/src/a17989470af/src/main.rs"
+        );
+    }
+
+    #[test]
+    fn bitbucket_server() {
+        let remote = parse_remote_url("ssh://git@bitbucket.example.com:7999/foo/bar.git").unwrap();
+        assert_eq!(
+            pull_request_url(&remote, "5").unwrap().as_str(),
+            "https://bitbucket.example.com/projects/foo/repos/bar/pull-requests/5"
+        );
+        let remote = parse_remote_url("https://bitbucket.example.com/scm/foo/bar.git").unwrap();
+        assert_eq!(
+            file_url(&remote, &oid(), Path::new("README.md"))
+                .unwrap()
+                .as_str(),
+            "https://bitbucket.example.com/projects/foo/repos/bar/browse/README.md?at=a17989470af"
+        );
+    }
+
+    #[test]
+    fn gitea() {
+        let remote = parse_remote_url("https://gitea.example.com:3000/owner/repo").unwrap();
+        assert_eq!(
+            pull_request_url(&remote, "2").unwrap().as_str(),
+            "https://gitea.example.com:3000/owner/repo/pulls/2"
+        );
+        assert_eq!(
+            file_url(&remote, &oid(), Path::new("a b.txt"))
+                .unwrap()
+                .as_str(),
+            "https://gitea.example.com:3000/owner/repo/src/commit/a17989470af/a%20b.txt"
+        );
+    }
+
+    #[test]
+    fn browser_list() {
+        let url = "https://example.com/a";
+        assert_eq!(
+            browser_commands("firefox --new-tab:w3m %s::links", url),
+            vec![
+                vec!["firefox", "--new-tab", url],
+                vec!["w3m", url],
+                vec!["links", url],
+            ]
+        );
+        assert!(browser_commands("", url).is_empty());
+    }
+}
@@ -0,0 +1,482 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Built-in syntax highlighting of diffs, used when `delta(1)` is not installed.
+//!
+//! The uncolored output of `git diff` is highlighted by the language of each file, which is
+//! picked by the file extension. Inside modified lines the changed words are emphasized.
+
+use std::ops::Range;
+use std::path::Path;
+
+use crossterm::style::{Attribute, Attributes, Color, ContentStyle, StyledContent};
+use lazy_static::lazy_static;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+use crate::raw::expand_tabs;
+use crate::ui::base::{StyledArea, StyledLine};
+
+lazy_static! {
+    static ref SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEME: Theme = ThemeSet::load_defaults()
+        .themes
+        .remove("base16-ocean.dark")
+        .unwrap_or_default();
+}
+
+const REMOVED_BG: Color = Color::Rgb {
+    r: 0x3f,
+    g: 0x1d,
+    b: 0x1d,
+};
+const REMOVED_EMPH_BG: Color = Color::Rgb {
+    r: 0x7a,
+    g: 0x2a,
+    b: 0x2a,
+};
+const ADDED_BG: Color = Color::Rgb {
+    r: 0x1d,
+    g: 0x3b,
+    b: 0x22,
+};
+const ADDED_EMPH_BG: Color = Color::Rgb {
+    r: 0x2f,
+    g: 0x6b,
+    b: 0x3a,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Side {
+    Context,
+    Removed,
+    Added,
+}
+
+impl Side {
+    const fn background(self, emphasized: bool) -> Option<Color> {
+        match (self, emphasized) {
+            (Self::Context, _) => None,
+            (Self::Removed, false) => Some(REMOVED_BG),
+            (Self::Removed, true) => Some(REMOVED_EMPH_BG),
+            (Self::Added, false) => Some(ADDED_BG),
+            (Self::Added, true) => Some(ADDED_EMPH_BG),
+        }
+    }
+}
+
+/// A changed line of a hunk, split into the diff markers & the code
+struct Change {
+    marker: String,
+    code: String,
+}
+
+/// Return the syntax for the file path, `None` for plain text
+fn syntax_for(path: &str) -> Option<&'static SyntaxReference> {
+    let path = Path::new(path);
+    let by_name = path
+        .file_name()
+        .and_then(|name| SYNTAXES.find_syntax_by_extension(&name.to_string_lossy()));
+    let syntax = by_name.or_else(|| {
+        path.extension()
+            .and_then(|ext| SYNTAXES.find_syntax_by_extension(&ext.to_string_lossy()))
+    })?;
+    (syntax.name != "Plain Text").then_some(syntax)
+}
+
+/// Return the byte offsets of the word boundaries, including `0` & `text.len()`. A word is a run
+/// of alphanumeric characters or a single other character.
+fn word_boundaries(text: &str) -> Vec<usize> {
+    let mut result = vec![0];
+    let mut in_word = false;
+    for (i, c) in text.char_indices() {
+        let is_word = c.is_alphanumeric() || c == '_';
+        if i > 0 && !(in_word && is_word) {
+            result.push(i);
+        }
+        in_word = is_word;
+    }
+    if !text.is_empty() {
+        result.push(text.len());
+    }
+    result
+}
+
+/// Return the changed byte ranges of a removed & an added line.
+///
+/// The words the lines start & end with are unchanged. Returns `None` when the lines do not
+/// share any words, because emphasizing a whole line adds nothing.
+fn changed_words(old: &str, new: &str) -> Option<(Range<usize>, Range<usize>)> {
+    let a = word_boundaries(old);
+    let b = word_boundaries(new);
+    let word_a = |i: usize| &old[a[i]..a[i + 1]];
+    let word_b = |i: usize| &new[b[i]..b[i + 1]];
+    let (len_a, len_b) = (a.len().saturating_sub(1), b.len().saturating_sub(1));
+
+    let mut prefix = 0;
+    while prefix < len_a && prefix < len_b && word_a(prefix) == word_b(prefix) {
+        prefix += 1;
+    }
+    let mut suffix = 0;
+    while suffix < len_a - prefix
+        && suffix < len_b - prefix
+        && word_a(len_a - 1 - suffix) == word_b(len_b - 1 - suffix)
+    {
+        suffix += 1;
+    }
+
+    let old_range = a[prefix]..a[len_a - suffix];
+    let new_range = b[prefix]..b[len_b - suffix];
+    let mut common = old[..old_range.start]
+        .chars()
+        .chain(old[old_range.end..].chars());
+    if !common.any(char::is_alphanumeric) {
+        return None;
+    }
+    Some((old_range, new_range))
+}
+
+/// Highlights the plain output of `git diff --stat -p`
+struct DiffHighlighter {
+    lines: StyledArea<String>,
+    /// Highlighters for the old & new version of the current file
+    old: Option<HighlightLines<'static>>,
+    new: Option<HighlightLines<'static>>,
+    /// `false` while in the `--stat` part before the first file diff
+    in_file: bool,
+    in_hunk: bool,
+    /// The number of marker columns, more than one for combined diffs of merges
+    columns: usize,
+    removed: Vec<Change>,
+    added: Vec<Change>,
+}
+
+impl DiffHighlighter {
+    fn new() -> Self {
+        Self {
+            lines: vec![],
+            old: None,
+            new: None,
+            in_file: false,
+            in_hunk: false,
+            columns: 1,
+            removed: vec![],
+            added: vec![],
+        }
+    }
+
+    fn push(&mut self, text: &str, style: ContentStyle) {
+        self.lines.push(StyledLine {
+            content: vec![StyledContent::new(style, expand_tabs(text, &mut 0))],
+            links: vec![],
+        });
+    }
+
+    fn set_path(&mut self, path: &str) {
+        let syntax = syntax_for(path);
+        self.old = syntax.map(|s| HighlightLines::new(s, &THEME));
+        self.new = syntax.map(|s| HighlightLines::new(s, &THEME));
+    }
+
+    fn header(&mut self, line: &str) {
+        let mut style = ContentStyle::new();
+        if let Some(path) = line
+            .strip_prefix("+++ ")
+            .or_else(|| line.strip_prefix("--- "))
+        {
+            if path != "/dev/null" {
+                let path = path.split_once('/').map_or(path, |(_, path)| path);
+                self.set_path(path);
+            }
+            style.attributes.set(Attribute::Bold);
+        } else if line.starts_with("diff --") {
+            style.foreground_color = Some(Color::Yellow);
+            style.attributes.set(Attribute::Bold);
+        } else {
+            style.attributes.set(Attribute::Dim);
+        }
+        self.push(line, style);
+    }
+
+    /// Colors the `+` & `-` bars of a `--stat` line
+    fn stat(&mut self, line: &str) {
+        let (files, graph) = match line.rfind('|') {
+            Some(i) if line.ends_with(['+', '-']) => line.split_at(i),
+            _ => return self.push(line, ContentStyle::new()),
+        };
+        let plus = graph.trim_end_matches('-').len();
+        let (graph, minus) = graph.split_at(plus);
+        let bars = graph.trim_end_matches('+').len();
+        let (graph, plus) = graph.split_at(bars);
+        let mut content = vec![StyledContent::new(
+            ContentStyle::new(),
+            format!("{}{}", files, graph),
+        )];
+        for (text, color) in [(plus, Color::Green), (minus, Color::Red)] {
+            let mut style = ContentStyle::new();
+            style.foreground_color = Some(color);
+            content.push(StyledContent::new(style, text.to_owned()));
+        }
+        self.lines.push(StyledLine {
+            content,
+            links: vec![],
+        });
+    }
+
+    fn hunk_line(&mut self, line: &str) {
+        if line.starts_with('\\') {
+            let mut style = ContentStyle::new();
+            style.attributes = Attributes::from(Attribute::Dim);
+            return self.push(line, style);
+        }
+        let split = line
+            .char_indices()
+            .nth(self.columns)
+            .map_or(line.len(), |(i, _)| i);
+        let (marker, code) = line.split_at(split);
+        let change = Change {
+            marker: marker.to_owned(),
+            code: code.to_owned(),
+        };
+        if marker.contains('-') {
+            if !self.added.is_empty() {
+                self.flush();
+            }
+            self.removed.push(change);
+        } else if marker.contains('+') {
+            self.added.push(change);
+        } else {
+            self.flush();
+            if let Some(old) = self.old.as_mut() {
+                // Keep the parser state of the old version in sync
+                highlight(Some(old), &change.code);
+            }
+            let line = render(self.new.as_mut(), &change, Side::Context, None);
+            self.lines.push(line);
+        }
+    }
+
+    /// Renders the buffered removed & added lines, pairing them up for the word emphasis
+    fn flush(&mut self) {
+        let removed = std::mem::take(&mut self.removed);
+        let added = std::mem::take(&mut self.added);
+        let pairs: Vec<_> = removed
+            .iter()
+            .zip(added.iter())
+            .map(|(old, new)| changed_words(&old.code, &new.code))
+            .collect();
+        for (i, change) in removed.iter().enumerate() {
+            let emphasis = pairs.get(i).cloned().flatten().map(|(old, _)| old);
+            let line = render(self.old.as_mut(), change, Side::Removed, emphasis);
+            self.lines.push(line);
+        }
+        for (i, change) in added.iter().enumerate() {
+            let emphasis = pairs.get(i).cloned().flatten().map(|(_, new)| new);
+            let line = render(self.new.as_mut(), change, Side::Added, emphasis);
+            self.lines.push(line);
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        if line.starts_with("diff --") {
+            self.flush();
+            self.in_file = true;
+            self.in_hunk = false;
+            self.old = None;
+            self.new = None;
+            self.header(line);
+        } else if line.starts_with("@@") {
+            self.flush();
+            self.in_hunk = true;
+            self.columns = line.chars().take_while(|c| *c == '@').count().max(2) - 1;
+            let mut style = ContentStyle::new();
+            style.foreground_color = Some(Color::Cyan);
+            self.push(line, style);
+        } else if self.in_hunk {
+            self.hunk_line(line);
+        } else if self.in_file {
+            self.header(line);
+        } else {
+            self.stat(line);
+        }
+    }
+}
+
+/// Return the pieces of the code colored by the syntax
+fn highlight(
+    highlighter: Option<&mut HighlightLines<'static>>,
+    code: &str,
+) -> Vec<(Option<Color>, String)> {
+    if let Some(highlighter) = highlighter {
+        match highlighter.highlight_line(&format!("{}\n", code), &SYNTAXES) {
+            Ok(ranges) => {
+                return ranges
+                    .into_iter()
+                    .map(|(style, text)| {
+                        let fg = style.foreground;
+                        let color = Color::Rgb {
+                            r: fg.r,
+                            g: fg.g,
+                            b: fg.b,
+                        };
+                        (Some(color), text.trim_end_matches('\n').to_owned())
+                    })
+                    .filter(|(_, text)| !text.is_empty())
+                    .collect();
+            }
+            Err(err) => log::warn!("Failed to highlight {:?}: {}", code, err),
+        }
+    }
+    vec![(None, code.to_owned())]
+}
+
+fn render(
+    highlighter: Option<&mut HighlightLines<'static>>,
+    change: &Change,
+    side: Side,
+    emphasis: Option<Range<usize>>,
+) -> StyledLine<String> {
+    let mut marker_style = ContentStyle::new();
+    marker_style.background_color = side.background(false);
+    marker_style.foreground_color = match side {
+        Side::Context => None,
+        Side::Removed => Some(Color::Red),
+        Side::Added => Some(Color::Green),
+    };
+    // Display width of the line, needed to expand tabs
+    let mut column = 0;
+    let mut content = vec![StyledContent::new(
+        marker_style,
+        expand_tabs(&change.marker, &mut column),
+    )];
+    let emphasis = emphasis.unwrap_or(0..0);
+    let mut offset = 0;
+    for (color, text) in highlight(highlighter, &change.code) {
+        let end = offset + text.len();
+        // Split the piece at the start & end of the emphasized range
+        let mut cuts = vec![offset];
+        cuts.extend(
+            [emphasis.start, emphasis.end]
+                .into_iter()
+                .filter(|cut| offset < *cut && *cut < end),
+        );
+        cuts.push(end);
+        for window in cuts.windows(2) {
+            let (start, stop) = (window[0], window[1]);
+            let emphasized = emphasis.start <= start && stop <= emphasis.end && start < stop;
+            let mut style = ContentStyle::new();
+            style.foreground_color = color;
+            style.background_color = side.background(emphasized);
+            if emphasized {
+                style.attributes.set(Attribute::Bold);
+            }
+            content.push(StyledContent::new(
+                style,
+                expand_tabs(&text[start - offset..stop - offset], &mut column),
+            ));
+        }
+        offset = end;
+    }
+    StyledLine {
+        content,
+        links: vec![],
+    }
+}
+
+/// Highlight the uncolored output of `git diff --stat -p`
+pub fn highlight_diff(text: &str) -> StyledArea<String> {
+    let mut highlighter = DiffHighlighter::new();
+    for line in text.lines() {
+        highlighter.line(line);
+    }
+    highlighter.flush();
+    highlighter.lines
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::style::Color;
+    use pretty_assertions::assert_eq;
+
+    use super::{changed_words, highlight_diff, syntax_for, ADDED_BG, ADDED_EMPH_BG};
+
+    #[test]
+    fn syntax_detection() {
+        assert_eq!(
+            syntax_for("src/main.rs").map(|s| s.name.as_str()),
+            Some("Rust")
+        );
+        assert_eq!(
+            syntax_for("Makefile").map(|s| s.name.as_str()),
+            Some("Makefile")
+        );
+        assert!(syntax_for("notes.unknown").is_none());
+    }
+
+    #[test]
+    fn word_emphasis() {
+        let old = "    let foo = bar(1);";
+        let new = "    let foo = baz(1, 2);";
+        let (old_range, new_range) = changed_words(old, new).unwrap();
+        assert_eq!(&old[old_range], "bar(1");
+        assert_eq!(&new[new_range], "baz(1, 2");
+        assert_eq!(changed_words("    foo();", "    bar = 1;"), None);
+    }
+
+    #[test]
+    fn diff_lines() {
+        let text = " src/main.rs | 2 +-\n 1 file changed\n\ndiff --git a/src/main.rs b/src/main.rs\nindex 1..2 100644\n--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,2 +1,2 @@\n fn main() {\n-    let foo = bar(1);\n+    let foo = baz(1);\n }\n";
+        let lines = highlight_diff(text);
+        let texts: Vec<String> = lines
+            .iter()
+            .map(|line| line.content.iter().map(|c| c.content().as_str()).collect())
+            .collect();
+        assert_eq!(texts, text.lines().collect::<Vec<_>>());
+
+        let added = &lines[10];
+        assert_eq!(added.content[0].content(), "+");
+        assert!(added
+            .content
+            .iter()
+            .all(|c| c.style().background_color.is_some()));
+        let emphasized: String = added
+            .content
+            .iter()
+            .filter(|c| c.style().background_color == Some(ADDED_EMPH_BG))
+            .map(|c| c.content().as_str())
+            .collect();
+        assert_eq!(emphasized, "baz");
+        assert_eq!(added.content[1].style().background_color, Some(ADDED_BG));
+        assert!(matches!(
+            added.content[1].style().foreground_color,
+            Some(Color::Rgb { .. })
+        ));
+    }
+
+    #[test]
+    fn tabs() {
+        let text = "diff --git a/Makefile b/Makefile\n--- a/Makefile\n+++ b/Makefile\n@@ -1 +1 @@\n-all:\tfoo\n+all:\tbar\n";
+        let lines = highlight_diff(text);
+        let texts: Vec<String> = lines
+            .iter()
+            .map(|line| line.content.iter().map(|c| c.content().as_str()).collect())
+            .collect();
+        assert_eq!(texts[4], "-all:   foo");
+        assert_eq!(texts[5], "+all:   bar");
+    }
+}
@@ -15,47 +15,70 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::Receiver;
 
 use git_stree::{SubtreeConfig, Subtrees};
 use posix_errors::PosixError;
 use subject_classifier::Subject;
+use unicode_width::UnicodeWidthStr;
 use url::Url;
 
-use crate::actors::bitbucket::{BitbucketRequest, BitbucketThread};
+use crate::actors::child_history::{ChildHistoryRequest, ChildHistoryThread};
 use crate::actors::fork_point::ForkPointThread;
-use crate::actors::github::{GitHubRequest, GitHubThread};
+use crate::actors::pull_requests::PullRequestTitles;
+use crate::actors::refs_watch::RefsWatchThread;
+use crate::actors::search::{FilterResult, SearchRequest, SearchThread};
 use crate::actors::subtrees::{SubtreeChangesRequest, SubtreeThread};
-use crate::commit::{child_history, commits_for_range, history_length, Commit};
+use crate::commit::{commits_for_range, history_length, unfolded_children, Commit, Oid};
 use crate::history_entry::{EntryKind, HistoryEntry};
+use crate::hyperlink;
+use crate::refs::RefMap;
+use crate::session::Session;
 use crate::ui::base::data::SearchProgress;
-use crate::ui::base::search::{Direction, Needle, SearchResult};
-use crate::ui::base::StyledLine;
+use crate::ui::base::search::{Needle, SearchResult};
 use crate::utils::find_forge_url;
 use git_wrapper::Remote;
 use git_wrapper::Repository;
 use std::fmt::{Debug, Formatter};
 use std::sync::mpsc;
-use std::thread;
-use std::thread::JoinHandle;
 
 pub struct HistoryAdapter {
     history: Vec<HistoryEntry>,
     length: NonZeroUsize,
+    first_parent_length: usize,
     paths: Vec<PathBuf>,
     remotes: Vec<Remote>,
+    /// Refs of the repository, read again on [`HistoryAdapter::reload`]
+    refs: RefMap,
     range: Vec<OsString>,
     repo: Repository,
     forge_url: Option<Url>,
-    github_thread: GitHubThread,
-    bb_server_thread: BitbucketThread,
+    /// Shared with the adapters created via [`HistoryAdapter::with_view`]
+    pull_requests: PullRequestTitles,
+    /// Number of pull request titles already applied, see [`PullRequestTitles::received_since`]
+    seen_titles: usize,
     fork_point_thread: ForkPointThread,
     subtree_modules: Vec<SubtreeConfig>,
     subtree_thread: SubtreeThread,
-    search_thread: Option<JoinHandle<()>>,
+    search_thread: SearchThread,
+    refs_watch_thread: RefsWatchThread,
+    child_history_thread: ChildHistoryThread,
+    /// Prefetched children of merges
+    children: HashMap<Oid, (Vec<Commit>, Option<Commit>)>,
+    /// Merges for which children were requested from `child_history_thread`
+    requested: HashSet<Oid>,
+    /// Merges showing a loading placeholder
+    pending: HashSet<Oid>,
+    /// Pending merges to unfold recursively up to the level, once their children arrive
+    expanding: HashMap<Oid, u8>,
+    /// First-parent commits added at the top by a [`HistoryAdapter::reload`]
+    new_commits: HashSet<Oid>,
+    /// Incremented whenever entries are added or removed, see [`HistoryAdapter::version`]
+    version: usize,
     debug: bool,
 }
 
@@ -86,7 +109,7 @@ impl Debug for CommitRange {
     }
 }
 
-#[warn(clippy::arithmetic)]
+#[allow(clippy::arithmetic_side_effects)]
 // arithmetic: Debug code which i may rip out in future
 #[cfg(not(tarpaulin_include))]
 impl Debug for HistoryAdapter {
@@ -108,7 +131,7 @@ impl Debug for HistoryAdapter {
                     let range = CommitRange {
                         start: start.clone(),
                         end,
-                        level: level.try_into().expect("usize"),
+                        level: level.into(),
                     };
                     result.push(range);
                     start = RangePart {
@@ -125,7 +148,7 @@ impl Debug for HistoryAdapter {
             let range = CommitRange {
                 start,
                 end,
-                level: level.try_into().expect("usize"),
+                level: level.into(),
             };
             result.push(range);
         }
@@ -149,20 +172,30 @@ impl HistoryAdapter {
         range: Vec<OsString>,
         paths: Vec<PathBuf>,
         debug: bool,
+    ) -> Result<Self, PosixError> {
+        Self::with_titles(repo, range, paths, debug, PullRequestTitles::new())
+    }
+
+    fn with_titles(
+        repo: Repository,
+        range: Vec<OsString>,
+        paths: Vec<PathBuf>,
+        debug: bool,
+        pull_requests: PullRequestTitles,
     ) -> Result<Self, PosixError> {
         let remotes: Vec<Remote>;
         let forge_url: Option<Url>;
         if let Some(hash_map) = repo.remotes() {
             forge_url = find_forge_url(&hash_map);
-            remotes = hash_map
-                .into_iter()
-                .map(|(_k, v)| v)
-                .collect::<Vec<Remote>>();
+            remotes = hash_map.into_values().collect::<Vec<Remote>>();
         } else {
             forge_url = None;
             remotes = vec![];
         }
         log::debug!("Forge url {:?}", forge_url);
+        if let Some(url) = &forge_url {
+            hyperlink::set_forge_url(url.clone());
+        }
 
         let length = history_length(&repo, &range, &paths)?;
         if length == 0 {
@@ -171,27 +204,39 @@ impl HistoryAdapter {
         let subtrees = Subtrees::from_repo(repo.clone()).expect("Read subtree config");
         let subtree_modules = subtrees.all()?;
         let subtree_thread = SubtreeThread::new(subtrees);
-        let bb_server_thread = BitbucketThread::new();
         let fork_point_thread = ForkPointThread::new(repo.clone());
+        let child_history_thread = ChildHistoryThread::new(repo.clone(), paths.clone());
+        let search_thread = SearchThread::new(repo.clone(), range.clone(), paths.clone());
+        let refs_watch_thread = RefsWatchThread::new(repo.clone());
         Ok(Self {
             history: vec![],
             length: NonZeroUsize::new(length).expect("Length should be >=1"),
+            first_parent_length: length,
             paths,
             remotes,
+            refs: RefMap::load(&repo),
             forge_url,
-            bb_server_thread,
             range,
             repo,
-            github_thread: GitHubThread::new(),
+            pull_requests,
+            seen_titles: 0,
             fork_point_thread,
             subtree_modules,
             subtree_thread,
-            search_thread: None,
+            search_thread,
+            refs_watch_thread,
+            child_history_thread,
+            children: HashMap::new(),
+            requested: HashSet::new(),
+            pending: HashSet::new(),
+            expanding: HashMap::new(),
+            new_commits: HashSet::new(),
+            version: 0,
             debug,
         })
     }
 
-    #[warn(clippy::arithmetic)]
+    #[allow(clippy::arithmetic_side_effects)]
     // arithmetic: Level code will be ripped out in future
     pub fn unfold_up_to(&mut self, sr: &SearchResult) -> usize {
         debug_assert!(!sr.0.is_empty(), "Unexpected empty SearchResult vector");
@@ -201,7 +246,7 @@ impl HistoryAdapter {
         for (level, addr) in addresses.iter().enumerate() {
             result = self.addr_to_index(result, level, *addr);
             let entry = self.get_data(result);
-            let entry_level: usize = entry.level().try_into().expect("usize");
+            let entry_level: usize = entry.level().into();
             if last_level - 1 != (entry_level) {
                 if entry_level != level {
                     log::error!("Failed unfold_up_to");
@@ -210,7 +255,7 @@ impl HistoryAdapter {
                 assert_eq!(entry_level, level);
                 result += 1;
                 if entry.is_foldable() {
-                    if entry.is_folded() {
+                    if self.is_collapsed(result - 1) {
                         self.toggle_folding(result - 1);
                     }
                 } else {
@@ -229,19 +274,15 @@ impl HistoryAdapter {
         result
     }
     fn addr_to_index(&mut self, start_index: usize, level: usize, addr: usize) -> usize {
-        let self_level: usize = self
-            .get_data(start_index)
-            .level()
-            .try_into()
-            .expect("usize");
+        let self_level: usize = self.get_data(start_index).level().into();
         assert_eq!(self_level, level);
         let mut result: usize = 0;
         let mut stop: usize = 0;
-        #[allow(clippy::arithmetic)]
+        #[allow(clippy::arithmetic_side_effects)]
         // arithmetic: `stop` is always <= `i` <= `usize::MAX`
         for i in start_index..self.length.get() {
             let entry = self.get_data(i);
-            let cur_level: usize = entry.level().try_into().expect("usize");
+            let cur_level: usize = entry.level().into();
             if cur_level == level {
                 result = i;
                 if stop == addr {
@@ -256,7 +297,7 @@ impl HistoryAdapter {
 
     // TODO return nothing
     fn fill_up(&mut self, max: usize) -> bool {
-        let skip = self.history.len();
+        let skip = self.history.iter().filter(|e| e.level() == 0).count();
         let tmp = commits_for_range(
             &self.repo,
             &self.range,
@@ -267,16 +308,22 @@ impl HistoryAdapter {
         if tmp.is_empty() {
             return false;
         }
+        self.append_first_parent(tmp);
+        true
+    }
+
+    /// Append the next first-parent commits to the history
+    fn append_first_parent(&mut self, commits: Vec<Commit>) {
         let mut above_entry = self.history.last();
-        let mut tmp2 = Vec::with_capacity(tmp.len());
+        let mut tmp2 = Vec::with_capacity(commits.len());
         let level = 0;
-        for commit in tmp {
+        for commit in commits {
             let entry = self.to_entry(commit, above_entry, level, false);
             tmp2.push(entry);
             above_entry = tmp2.last();
         }
         self.history.append(tmp2.as_mut());
-        true
+        self.version = self.version.wrapping_add(1);
     }
 
     fn to_entry(
@@ -309,40 +356,13 @@ impl HistoryAdapter {
             kind,
             self.debug,
         );
+        entry.set_is_new(self.new_commits.contains(entry.id()));
+        entry.set_refs(self.refs.get(entry.id()));
 
         if let Some(url) = entry.url() {
             if let Subject::PullRequest { id, .. } = entry.special() {
-                if GitHubThread::can_handle(&url) {
-                    if let Some(title) = GitHubThread::from_cache(&url, id) {
-                        log::debug!("PR #{} (CACHE) ⇒ «{}»", id, title);
-                        entry.set_subject(&title);
-                    } else {
-                        let req = GitHubRequest {
-                            oid: entry.id().clone(),
-                            url,
-                            pr_id: id.to_string(),
-                        };
-                        if let Err(err) = self.github_thread.send(req) {
-                            log::error!("{}", err);
-                        }
-                    }
-                } else if BitbucketThread::can_handle(&url) {
-                    if let Some(title) = BitbucketThread::from_cache(&url, id) {
-                        log::debug!("PR #{} (CACHE) ⇒ «{}»", id, title);
-                        entry.set_subject(&title);
-                    } else {
-                        let req = BitbucketRequest {
-                            oid: entry.id().clone(),
-                            url,
-                            pr_id: id.to_string(),
-                        };
-
-                        if let Err(err) = self.bb_server_thread.send(req) {
-                            log::error!("{}", err);
-                        }
-                    }
-                } else {
-                    log::info!("Unrecognized url {}", url);
+                if let Some(title) = self.pull_requests.request(entry.id(), url, id) {
+                    entry.set_subject(&title);
                 }
             }
         }
@@ -353,87 +373,404 @@ impl HistoryAdapter {
         i >= self.history.len()
     }
 
+    /// Toggle the folding of the entry at index `i`.
+    ///
+    /// If the children of a merge are not prefetched yet, a loading placeholder is shown until
+    /// they arrive via [`HistoryAdapter::update`].
     pub fn default_action(&mut self, i: usize) {
-        if self.history[i].is_foldable() {
+        let entry = &self.history[i];
+        if !entry.is_foldable() {
+            return;
+        }
+        if !entry.is_folded() {
+            self.fold(i);
+        } else if self.children.contains_key(entry.id()) {
             self.toggle_folding(i);
+        } else {
+            self.show_loading(i);
         }
     }
 
+    /// Toggle the folding of the entry at index `i` and wait for its children
     fn toggle_folding(&mut self, i: usize) {
-        if let Some(pos) = i.checked_add(1) {
-            let selected = &self.history[i];
-            if selected.is_folded() {
-                let children: Vec<Commit> =
-                    child_history(&self.repo, selected.commit(), self.paths.as_ref());
-                log::debug!("Unfolding entry {}, with #{} children", i, children.len());
-
-                // Check if we need to add a Link commit
-                let link_commit = if let (Some(Some(oid)), Some(bellow_selected)) = (
-                    children.last().map(|c| c.parents().first()),
-                    selected.commit().parents().first(),
-                ) {
-                    if oid == bellow_selected {
-                        None
-                    } else {
-                        Commit::from_repo(&self.repo, oid)
-                    }
+        if self.is_collapsed(i) {
+            let entry = &self.history[i];
+            let children = if let Some(data) = self.children.get(entry.id()) {
+                data.clone()
+            } else {
+                unfolded_children(&self.repo, entry.commit(), &self.paths)
+            };
+            self.unfold(i, children);
+        } else {
+            self.fold(i);
+        }
+    }
+
+    /// Return `true` if the children of the entry at index `i` are not visible
+    fn is_collapsed(&self, i: usize) -> bool {
+        let entry = &self.history[i];
+        entry.is_folded() || self.pending.contains(entry.id())
+    }
+
+    /// Insert a loading placeholder bellow the merge at index `i` and request its children
+    fn show_loading(&mut self, i: usize) {
+        self.request_children(i);
+        let entry = &self.history[i];
+        let level = if let Some(level) = entry.level().checked_add(1) {
+            level
+        } else {
+            log::error!("Level > {}", u8::MAX);
+            return;
+        };
+        let placeholder = HistoryEntry::loading(level, entry.short_id().width());
+        self.pending.insert(entry.id().clone());
+        self.history[i].set_visible_children(1);
+        self.history.insert(i.saturating_add(1), placeholder);
+        self.version = self.version.wrapping_add(1);
+        self.length = self.length.saturating_add(1);
+    }
+
+    /// Ask the [`ChildHistoryThread`] for the children of the merge at index `i`
+    fn request_children(&mut self, i: usize) {
+        let entry = &self.history[i];
+        if entry.is_foldable()
+            && !entry.is_loading()
+            && !self.children.contains_key(entry.id())
+            && self.requested.insert(entry.id().clone())
+        {
+            let req = ChildHistoryRequest {
+                merge: entry.commit().clone(),
+            };
+            if let Err(err) = self.child_history_thread.send(req) {
+                log::error!("{}", err);
+            }
+        }
+    }
+
+    /// Prefetch the children of all folded merges in `start..=end`
+    pub fn prefetch(&mut self, start: usize, end: usize) {
+        let last = end.min(self.history.len().saturating_sub(1));
+        for i in start..=last {
+            if self.history[i].is_folded() {
+                self.request_children(i);
+            }
+        }
+    }
+
+    /// Insert the provided children bellow the entry at index `i`
+    fn unfold(&mut self, i: usize, (children, link_commit): (Vec<Commit>, Option<Commit>)) {
+        if self.pending.contains(self.history[i].id()) {
+            self.fold(i);
+        }
+        let pos = if let Some(pos) = i.checked_add(1) {
+            pos
+        } else {
+            return;
+        };
+        let selected = &self.history[i];
+        log::debug!("Unfolding entry {}, with #{} children", i, children.len());
+        let mut tmp: Vec<HistoryEntry> = vec![];
+        if let Some(level) = selected.level().checked_add(1) {
+            let mut above_entry = Some(selected);
+            for t in children {
+                let entry = self.to_entry(t, above_entry, level, false);
+                tmp.push(entry);
+                above_entry = tmp.last();
+            }
+            if let Some(link) = link_commit {
+                tmp.push(self.to_entry(link, above_entry, level, true));
+            }
+        } else {
+            log::error!("Level > {}", u8::MAX);
+            return;
+        }
+
+        let inserted = tmp.len();
+        self.history[i].set_visible_children(inserted);
+        self.history.splice(pos..pos, tmp);
+        self.version = self.version.wrapping_add(1);
+        self.length = self.length.saturating_add(inserted);
+    }
+
+    /// Remove all descendants of the entry at index `i`
+    fn fold(&mut self, i: usize) {
+        let pos = i.saturating_add(1);
+        let end = self.subtree_end(i);
+        log::debug!(
+            "Folding entry {}, with #{} descendants",
+            i,
+            end.saturating_sub(pos)
+        );
+        self.history.drain(pos..end);
+        self.version = self.version.wrapping_add(1);
+        self.history[i].set_visible_children(0);
+        let oid = self.history[i].id().clone();
+        self.pending.remove(&oid);
+        self.expanding.remove(&oid);
+        self.recalculate_length();
+    }
+
+    /// Return the index after the last descendant of the entry at index `i`
+    fn subtree_end(&self, i: usize) -> usize {
+        let level = self.history[i].level();
+        let pos = i.saturating_add(1);
+        self.history[pos..]
+            .iter()
+            .position(|e| e.level() <= level)
+            .map_or(self.history.len(), |n| pos.saturating_add(n))
+    }
+
+    /// Adjust `length` after entries were removed from the loaded history
+    fn recalculate_length(&mut self) {
+        let loaded_first_parent = self.history.iter().filter(|e| e.level() == 0).count();
+        let not_loaded = self.first_parent_length.saturating_sub(loaded_first_parent);
+        let total = self.history.len().saturating_add(not_loaded);
+        self.length = NonZeroUsize::new(total).expect("History len >= 1");
+    }
+
+    /// Fold every loaded entry, so only the first-parent history is visible.
+    ///
+    /// Returns the new index of the entry selected at index `selected`.
+    pub fn fold_all(&mut self, selected: usize) -> usize {
+        let anchors = self.ancestor_ids(selected);
+        self.history.retain(|e| e.level() == 0);
+        self.pending.clear();
+        self.expanding.clear();
+        for e in &mut self.history {
+            e.set_visible_children(0);
+        }
+        self.recalculate_length();
+        self.find_anchor(&anchors)
+    }
+
+    /// Unfold all loaded merges recursively, so entries up to `depth` levels deep are visible.
+    /// Merges on level `depth` and deeper get folded.
+    ///
+    /// Returns the new index of the entry selected at index `selected`.
+    pub fn unfold_to_depth(&mut self, selected: usize, depth: u8) -> usize {
+        let anchors = self.ancestor_ids(selected);
+        for i in (0..self.history.len()).rev() {
+            let entry = &self.history[i];
+            if entry.level() == depth && !entry.is_folded() {
+                self.fold(i);
+            }
+        }
+        let end = self.history.len();
+        self.unfold_recursive(0, end, depth);
+        self.find_anchor(&anchors)
+    }
+
+    /// Unfold the entry at index `i` & all merges bellow it recursively
+    pub fn unfold_subtree(&mut self, i: usize) {
+        if !self.history[i].is_foldable() {
+            return;
+        }
+        let end = self.subtree_end(i);
+        self.unfold_recursive(i, end, u8::MAX);
+    }
+
+    /// Unfold all folded merges in `start..end`, until the entries on `max_level` are visible.
+    ///
+    /// Merges without prefetched children show a loading placeholder, they are unfolded further
+    /// when their children arrive in [`HistoryAdapter::update`].
+    fn unfold_recursive(&mut self, start: usize, mut end: usize, max_level: u8) {
+        let mut i = start;
+        while i < end {
+            let entry = &self.history[i];
+            if entry.level() < max_level && entry.is_foldable() && self.is_collapsed(i) {
+                let oid = entry.id().clone();
+                let before = self.history.len();
+                if let Some(children) = self.children.get(&oid).cloned() {
+                    self.unfold(i, children);
                 } else {
-                    None
-                };
-
-                let mut tmp: Vec<HistoryEntry> = vec![];
-                if let Some(level) = selected.level().checked_add(1) {
-                    let mut above_entry = Some(selected);
-                    for t in children {
-                        let entry = self.to_entry(t, above_entry, level, false);
-                        tmp.push(entry);
-                        above_entry = tmp.last();
+                    if !self.pending.contains(&oid) {
+                        self.show_loading(i);
                     }
-                    if let Some(link) = link_commit {
-                        tmp.push(self.to_entry(link, above_entry, level, true));
-                    }
-                } else {
-                    log::error!("Level > {}", u8::MAX);
-                    return;
+                    self.expanding.insert(oid, max_level);
                 }
+                end = end.saturating_add(self.history.len().saturating_sub(before));
+            }
+            i = i.saturating_add(1);
+        }
+    }
 
-                self.history[i].set_visible_children(tmp.len());
-                for (j, entry) in tmp.into_iter().enumerate() {
-                    log::trace!(
-                        "Inserting index {}, entry {:?}",
-                        j,
-                        self.history[j].commit().subject()
-                    );
-                    if let Some(index) = pos.checked_add(j) {
-                        self.history.insert(index, entry);
-                    } else {
-                        log::error!("History length > {}", usize::MAX);
-                        break;
-                    }
+    /// Return the ids of the entry at index `i` and all its ancestors, the entry itself first
+    fn ancestor_ids(&self, i: usize) -> Vec<Oid> {
+        let mut result = vec![];
+        if let Some(entry) = self.history.get(i) {
+            result.push(entry.id().clone());
+            let mut level = entry.level();
+            for e in self.history[..i].iter().rev() {
+                if level == 0 {
+                    break;
                 }
-
-                self.length = NonZeroUsize::new(self.history.len()).expect("History len >= 1");
-            } else {
-                let f = selected.visible_children();
-                log::debug!("Folding entry {}, with #{} children", i, f);
-                for j in (pos..(pos.saturating_add(*f))).rev() {
-                    log::trace!(
-                        "Removing index {}: {:?}",
-                        j,
-                        self.history[j].commit().subject()
-                    );
-                    if !self.history[j].is_folded() {
-                        self.toggle_folding(j);
-                    }
-                    self.history.remove(j);
+                if e.level() < level {
+                    result.push(e.id().clone());
+                    level = e.level();
                 }
-                self.history[i].set_visible_children(0);
             }
         }
+        result
+    }
+
+    /// Return the index of the first still loaded entry from `anchors`
+    fn find_anchor(&self, anchors: &[Oid]) -> usize {
+        anchors
+            .iter()
+            .find_map(|oid| self.history.iter().position(|e| e.id() == oid))
+            .unwrap_or_default()
     }
 
-    /// Run this function before accessing data, to update data calculated by other threads
-    pub fn update(&mut self) {
+    /// Return the folding state & the id of the entry at index `selected`
+    fn session(&self, selected: usize) -> Session {
+        Session {
+            length: self.first_parent_length,
+            loaded: self.history.iter().filter(|e| e.level() == 0).count(),
+            selected: self.history.get(selected).map(|e| e.id().clone()),
+            unfolded: self
+                .history
+                .iter()
+                .filter(|e| e.is_foldable() && !e.is_folded())
+                .map(|e| e.id().clone())
+                .collect(),
+        }
+    }
+
+    /// Store the folding state & selection in the cache
+    pub fn store_session(&self, selected: usize) {
+        self.session(selected)
+            .store(&self.repo, &self.range, &self.paths);
+    }
+
+    /// Return `true` if HEAD or a ref changed since the last call, see [`HistoryAdapter::reload`]
+    pub fn refs_changed(&self) -> bool {
+        self.refs_watch_thread.changed()
+    }
+
+    /// Reload the history after the repository changed, keeping the folding state & the
+    /// selection. Commits added at the top are marked as new. Returns the new index of the
+    /// selected entry.
+    pub fn reload(&mut self, selected: usize) -> usize {
+        // Changes done until now are part of this reload
+        self.refs_watch_thread = RefsWatchThread::new(self.repo.clone());
+        self.refs = RefMap::load(&self.repo);
+        let length = match history_length(&self.repo, &self.range, &self.paths) {
+            Ok(length) if length > 0 => length,
+            Ok(_) => {
+                log::warn!("No commits found after reload");
+                return selected;
+            }
+            Err(err) => {
+                log::warn!("Failed to reload history: {}", err);
+                return selected;
+            }
+        };
+        let session = self.session(selected);
+        let old_top = self.history.first().map(|e| e.id().clone());
+        self.history.clear();
+        self.version = self.version.wrapping_add(1);
+        self.pending.clear();
+        self.expanding.clear();
+        self.requested.clear();
+        self.first_parent_length = length;
+        self.length = NonZeroUsize::new(length).expect("Length should be >=1");
+        self.search_thread =
+            SearchThread::new(self.repo.clone(), self.range.clone(), self.paths.clone());
+        let result = self.restore(&session);
+        if let Some(old_top) = old_top {
+            self.mark_new_commits(&old_top);
+        }
+        result
+    }
+
+    /// Mark the first-parent commits above the previous top of the history as new
+    fn mark_new_commits(&mut self, old_top: &Oid) {
+        let top_level = || self.history.iter().filter(|e| e.level() == 0);
+        if !top_level().any(|e| e.id() == old_top) {
+            // The history was rewritten, i.e. by a reset
+            return;
+        }
+        let new: Vec<Oid> = top_level()
+            .take_while(|e| e.id() != old_top)
+            .map(|e| e.id().clone())
+            .collect();
+        for entry in &mut self.history {
+            if new.contains(entry.id()) {
+                entry.set_is_new(true);
+            }
+        }
+        self.new_commits.extend(new);
+    }
+
+    /// Restore the folding state of the last session for the same repository, range & paths.
+    ///
+    /// Only the first-parent entries loaded in the last session (plus the new ones) are
+    /// searched, so commits which are not reachable anymore do not cause the whole range to be
+    /// loaded. Returns the index of the previously selected entry.
+    pub fn restore_session(&mut self) -> usize {
+        if let Some(session) = Session::load(&self.repo, &self.range, &self.paths) {
+            self.restore(&session)
+        } else {
+            0
+        }
+    }
+
+    fn restore(&mut self, session: &Session) -> usize {
+        let mut unfolded: HashSet<&Oid> = session.unfolded.iter().collect();
+        let new_commits = self.first_parent_length.saturating_sub(session.length);
+        let max = session
+            .loaded
+            .saturating_add(new_commits)
+            .min(self.first_parent_length);
+        if self.history.is_empty() && max > 0 {
+            self.fill_up(max);
+        }
+
+        let mut selected = None;
+        let mut i = 0;
+        while i < self.history.len() && (!unfolded.is_empty() || selected.is_none()) {
+            let entry = &self.history[i];
+            if selected.is_none() && session.selected.as_ref() == Some(entry.id()) {
+                selected = Some(i);
+            }
+            if unfolded.remove(entry.id()) && entry.is_foldable() && self.is_collapsed(i) {
+                self.toggle_folding(i);
+            }
+            i = i.saturating_add(1);
+        }
+        if !unfolded.is_empty() {
+            log::info!("Skipped restoring #{} unfolded merges", unfolded.len());
+        }
+        selected.unwrap_or_default()
+    }
+
+    /// Run this function before accessing data, to update data calculated by other threads.
+    ///
+    /// Returns the indices of merges, which replaced their loading placeholder, together with
+    /// the number of inserted children.
+    pub fn update(&mut self) -> Vec<(usize, usize)> {
+        let mut unfolded = vec![];
+        while let Ok(v) = self.child_history_thread.try_recv() {
+            let data = (v.children, v.link);
+            if self.pending.contains(&v.oid) {
+                if let Some(i) = self
+                    .history
+                    .iter()
+                    .position(|e| e.id() == &v.oid && !e.is_link())
+                {
+                    // Removed by unfolding, because the placeholder is folded
+                    let expanding = self.expanding.remove(&v.oid);
+                    self.unfold(i, data.clone());
+                    if let Some(max_level) = expanding {
+                        let end = self.subtree_end(i);
+                        self.unfold_recursive(i.saturating_add(1), end, max_level);
+                    }
+                    let inserted = self.subtree_end(i).saturating_sub(i.saturating_add(1));
+                    unfolded.push((i, inserted));
+                }
+            }
+            self.children.insert(v.oid, data);
+        }
         while let Ok(v) = self.fork_point_thread.try_recv() {
             for e in &mut self.history {
                 if e.id() == &v.first {
@@ -450,30 +787,12 @@ impl HistoryAdapter {
                 }
             }
         }
-        while let Ok(v) = self.github_thread.try_recv() {
-            for e in &mut self.history {
-                if e.id() == &v.oid {
-                    e.set_subject(&v.subject);
-                    break;
-                }
-            }
-        }
-
-        while let Ok(v) = self.bb_server_thread.try_recv() {
-            for e in &mut self.history {
-                if e.id() == &v.oid {
-                    e.set_subject(&v.subject);
-                    break;
-                }
+        for (oid, subject) in self.pull_requests.received_since(&mut self.seen_titles) {
+            if let Some(e) = self.history.iter_mut().find(|e| e.id() == &oid) {
+                e.set_subject(&subject);
             }
         }
-    }
-
-    pub fn get_line(&mut self, i: usize, selected: bool) -> StyledLine<String> {
-        if self.is_fill_up_needed(i) {
-            assert!(self.fill_up(i.saturating_add(50)));
-        }
-        self.history[i].render(selected)
+        unfolded
     }
 
     pub fn get_data(&mut self, i: usize) -> &HistoryEntry {
@@ -488,94 +807,99 @@ impl HistoryAdapter {
         self.length
     }
 
-    pub fn search(&mut self, needle: Needle, start: usize) -> Receiver<SearchProgress> {
-        let range = self.range.clone();
-        let paths = self.paths.clone();
-        let repo = self.repo.clone();
+    pub const fn repo(&self) -> &Repository {
+        &self.repo
+    }
 
-        let (rx, tx) = mpsc::channel::<SearchProgress>();
-        let thread = thread::spawn(move || {
-            let commits = commits_for_range(&repo, &range, &paths, None, None);
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
 
-            if !commits.is_empty() {
-                Self::search_recursive(&needle, start, &rx, &commits, &[], &repo, &paths);
-            }
+    pub fn range(&self) -> &[OsString] {
+        &self.range
+    }
 
-            #[allow(unused_must_use)]
-            {
-                rx.send(SearchProgress::Finished);
-            }
-        });
-        self.search_thread = Some(thread);
-        tx
+    /// Create an adapter for another range of the same repository & paths
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the range is invalid or has no commits
+    pub fn with_range(&self, range: Vec<OsString>) -> Result<Self, PosixError> {
+        self.with_view(range, self.paths.clone())
     }
-}
 
-#[derive(Eq, PartialEq)]
-enum KeepGoing {
-    Canceled,
-    Chuckaway,
-}
+    /// Create an adapter for another range & paths of the same repository, which shares the
+    /// pull request lookups with this one
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the range is invalid or has no commits
+    pub fn with_view(&self, range: Vec<OsString>, paths: Vec<PathBuf>) -> Result<Self, PosixError> {
+        Self::with_titles(
+            self.repo.clone(),
+            range,
+            paths,
+            self.debug,
+            self.pull_requests.clone(),
+        )
+    }
 
-impl HistoryAdapter {
-    fn search_recursive(
-        needle: &Needle,
-        start: usize,
-        rx: &Sender<SearchProgress>,
-        commits: &[Commit],
-        search_path: &[usize],
-        repo: &Repository,
-        paths: &[PathBuf],
-    ) -> KeepGoing {
-        let mut seen = 0;
-        let range = {
-            let mut part1 = (start..commits.len()).collect::<Vec<usize>>();
-            let part2 = (0..start).collect::<Vec<usize>>();
-            part1.extend(part2);
-            if *needle.direction() == Direction::Backward {
-                part1 = part1.into_iter().rev().collect::<Vec<_>>();
-            }
-            part1
+    /// Search the history in the background. A running search is canceled.
+    pub fn search(&mut self, needle: Needle, start: usize) -> Receiver<SearchProgress> {
+        let (progress, receiver) = mpsc::channel::<SearchProgress>();
+        let request = SearchRequest::Search {
+            needle,
+            start,
+            progress,
         };
-        for i in range {
-            let c = &commits[i];
-            #[allow(clippy::arithmetic)]
-            {
-                // arithmetic: `seen` can never exceed `usize::MAX`, because `seen <= range.len()`
-                seen += 1;
-            }
-            let mut r = search_path.to_vec();
-            r.push(i);
-            if c.matches(needle)
-                && rx
-                    .send(SearchProgress::Found(SearchResult(r.clone())))
-                    .is_err()
-            {
-                return KeepGoing::Canceled;
-            }
-            if c.is_merge() {
-                let tmp = child_history(repo, c, paths);
-                let result = Self::search_recursive(needle, 0, rx, &tmp, &r, repo, paths);
-                if result == KeepGoing::Canceled {
-                    return result;
-                }
-            }
-            // std::ops::Rem is safe
-            #[allow(clippy::arithmetic)]
-            if seen % 100 == 0 {
-                // This should be fixed in the next clippy version (0.1.59?).
-                // https://github.com/rust-lang/rust-clippy/issues/8269
-                #[allow(clippy::question_mark)]
-                if rx.send(SearchProgress::Searched(seen)).is_err() {
-                    return KeepGoing::Canceled;
-                }
-                seen = 0;
-            }
+        if let Err(err) = self.search_thread.send(request) {
+            log::error!("Failed to send search request: {}", err);
+        }
+        receiver
+    }
+
+    /// Find all commits matching the needle in the background. Merges with matching commits
+    /// in their child history are part of the result too.
+    pub fn filter(&self, needle: Needle) -> Receiver<FilterResult> {
+        let (result, receiver) = mpsc::channel::<FilterResult>();
+        let request = SearchRequest::Filter { needle, result };
+        if let Err(err) = self.search_thread.send(request) {
+            log::error!("Failed to send filter request: {}", err);
         }
-        if 0 < seen && rx.send(SearchProgress::Searched(seen)).is_err() {
-            return KeepGoing::Canceled;
+        receiver
+    }
+
+    /// Return a number, which changes whenever entries are added or removed
+    pub const fn version(&self) -> usize {
+        self.version
+    }
+
+    /// Add the rest of the first-parent history loaded by the filter & return the indices of
+    /// entries shown by the filter. Loading placeholders are always shown.
+    pub fn filter_rows(&mut self, filter: &FilterResult) -> Vec<usize> {
+        let loaded = self.history.iter().filter(|e| e.level() == 0).count();
+        if loaded < self.first_parent_length {
+            let last = self.history.iter().rev().find(|e| e.level() == 0);
+            let same_history = match (last, loaded.checked_sub(1)) {
+                (Some(entry), Some(i)) => filter
+                    .first_parent
+                    .get(i)
+                    .is_some_and(|c| c.id() == entry.id()),
+                _ => true,
+            };
+            if same_history && loaded < filter.first_parent.len() {
+                self.append_first_parent(filter.first_parent[loaded..].to_vec());
+            } else {
+                // The history was reloaded since the filter was applied
+                self.fill_up(self.first_parent_length.saturating_sub(loaded));
+            }
         }
-        KeepGoing::Chuckaway
+        self.history
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.is_loading() || filter.shown.contains(e.id()))
+            .map(|(i, _)| i)
+            .collect()
     }
 }
 
@@ -584,9 +908,273 @@ mod test {
     use std::ffi::OsString;
 
     use crate::history_adapter::HistoryAdapter;
+    use crate::ui::base::data::SearchProgress;
+    use crate::ui::base::search::{Direction, Needle, SearchResult};
+    use crate::ui::base::test_helpers::TestRepo;
     use git_wrapper::Repository;
     use pretty_assertions::assert_eq;
 
+    /// Create a repository with a merge containing another merge:
+    ///
+    /// ```text
+    /// c2 → m_outer → c1 → c0
+    ///      m_outer → m_inner → f2 → f1 → c1
+    ///                m_inner → s1 → f1
+    /// ```
+    fn nested_merges_repo(name: &str) -> TestRepo {
+        let repo = TestRepo::new(name);
+        repo.commit("c0");
+        repo.commit("c1");
+        repo.git(&["checkout", "-q", "-b", "feature"]);
+        repo.commit("f1");
+        repo.git(&["checkout", "-q", "-b", "sub"]);
+        std::fs::write(repo.path().join("sub.txt"), "magic words\n").expect("Wrote sub.txt");
+        std::fs::write(repo.path().join("déjà vu.txt"), "").expect("Wrote déjà vu.txt");
+        repo.git(&["add", "sub.txt", "déjà vu.txt"]);
+        repo.commit("s1");
+        repo.git(&["checkout", "-q", "feature"]);
+        repo.commit("f2");
+        repo.git(&["merge", "-q", "--no-ff", "-m", "m_inner", "sub"]);
+        repo.git(&["checkout", "-q", "main"]);
+        repo.git(&["merge", "-q", "--no-ff", "-m", "m_outer", "feature"]);
+        repo.commit("c2");
+        repo
+    }
+
+    fn wait_for_children(adapter: &mut HistoryAdapter) {
+        while !adapter.pending.is_empty() {
+            adapter.update();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    fn subjects(adapter: &HistoryAdapter) -> Vec<&str> {
+        adapter
+            .history
+            .iter()
+            .map(|e| e.commit().subject().as_str())
+            .collect()
+    }
+
+    fn search_results(adapter: &mut HistoryAdapter, text: &str) -> Vec<Vec<usize>> {
+        let receiver = adapter.search(Needle::smart_case(text, Direction::Forward), 0);
+        let mut result = vec![];
+        while let Ok(progress) = receiver.recv() {
+            match progress {
+                SearchProgress::Found(SearchResult(path)) => result.push(path),
+                SearchProgress::Searched(_) => {}
+                SearchProgress::Finished => break,
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn search_changes() {
+        let repo = nested_merges_repo("search-changes");
+        let range = vec![OsString::from("HEAD")];
+        let mut adapter = HistoryAdapter::new(repo.clone(), range, vec![], false).unwrap();
+        let s1 = vec![vec![1, 0, 0]];
+        assert_eq!(search_results(&mut adapter, "diff:magic"), s1, "git log -S");
+        assert_eq!(
+            search_results(&mut adapter, "diff:/mag.c/"),
+            s1,
+            "git log -G"
+        );
+        assert_eq!(search_results(&mut adapter, "file:sub.txt"), s1);
+        assert_eq!(
+            search_results(&mut adapter, "file:déjà*"),
+            s1,
+            "unquoted path"
+        );
+        assert_eq!(
+            search_results(&mut adapter, "file:*.txt -diff:magic"),
+            vec![] as Vec<Vec<usize>>
+        );
+    }
+
+    #[test]
+    fn new_search_supersedes_running_one() {
+        let repo = nested_merges_repo("search-superseded");
+        let range = vec![OsString::from("HEAD")];
+        let mut adapter = HistoryAdapter::new(repo.clone(), range, vec![], false).unwrap();
+        let _running = adapter.search(Needle::smart_case("subject:c", Direction::Forward), 0);
+        assert_eq!(search_results(&mut adapter, "s1"), vec![vec![1, 0, 0]]);
+        assert_eq!(
+            search_results(&mut adapter, "subject:c0"),
+            vec![vec![3]],
+            "Reuses loaded history"
+        );
+    }
+
+    #[test]
+    fn reload_keeps_folding_and_selection() {
+        let repo = nested_merges_repo("reload");
+        let range = vec![OsString::from("HEAD")];
+        let mut adapter = HistoryAdapter::new(repo.clone(), range, vec![], false).unwrap();
+        adapter.fill_up(50);
+        adapter.unfold_subtree(1);
+        wait_for_children(&mut adapter);
+        assert_eq!(subjects(&adapter)[2], "m_inner");
+        repo.commit("c3");
+        assert_eq!(adapter.reload(2), 3, "Selection moved by the new commit");
+        assert_eq!(
+            subjects(&adapter)[..5],
+            ["c3", "c2", "m_outer", "m_inner", "s1"]
+        );
+        assert!(adapter.get_data(0).is_new(), "c3 is marked as new");
+        assert!(!adapter.get_data(1).is_new());
+    }
+
+    #[test]
+    fn filter_keeps_merges_as_context() {
+        let repo = nested_merges_repo("filter");
+        let range = vec![OsString::from("HEAD")];
+        let mut adapter = HistoryAdapter::new(repo.clone(), range, vec![], false).unwrap();
+        let filter = adapter
+            .filter(Needle::smart_case("s1", Direction::Forward))
+            .recv()
+            .unwrap();
+        assert_eq!(filter.matches, 1);
+        assert_eq!(filter.shown.len(), 3, "s1, m_inner & m_outer");
+        assert_eq!(filter.first_parent.len(), 4, "Loaded in the background");
+        assert_eq!(
+            adapter.filter_rows(&filter),
+            vec![1],
+            "Only m_outer is visible"
+        );
+        adapter.unfold_subtree(1);
+        wait_for_children(&mut adapter);
+        let rows = adapter.filter_rows(&filter);
+        let shown: Vec<_> = rows.iter().map(|i| subjects(&adapter)[*i]).collect();
+        assert_eq!(shown, vec!["m_outer", "m_inner", "s1"]);
+    }
+
+    #[test]
+    fn cached_index_has_current_refs() {
+        let repo = nested_merges_repo("cached-index-refs");
+        let range = vec![OsString::from("HEAD")];
+        let adapter = HistoryAdapter::new(repo.clone(), range.clone(), vec![], false).unwrap();
+        let needle = || Needle::smart_case("ref:v0", Direction::Forward);
+        assert_eq!(adapter.filter(needle()).recv().unwrap().matches, 0);
+        repo.git(&["tag", "v0", "HEAD~2"]);
+        let adapter = HistoryAdapter::new(repo.clone(), range, vec![], false).unwrap();
+        let filter = adapter.filter(needle()).recv().unwrap();
+        assert_eq!(
+            filter.matches, 1,
+            "The tips did not change, but the refs did"
+        );
+        assert!(
+            !filter.first_parent[0].author_rel_date().is_empty(),
+            "Shown commits are not loaded from the index"
+        );
+    }
+
+    #[test]
+    fn unfold_in_background() {
+        let repo = nested_merges_repo("unfold-in-background");
+        let range = vec![OsString::from("HEAD")];
+        let mut adapter = HistoryAdapter::new(repo.clone(), range, vec![], false).unwrap();
+        adapter.fill_up(50);
+        adapter.default_action(1);
+        assert_eq!(
+            subjects(&adapter),
+            vec!["c2", "m_outer", "loading…", "c1", "c0"]
+        );
+        assert!(adapter.history[2].is_loading());
+        assert_eq!(adapter.len().get(), 5);
+
+        wait_for_children(&mut adapter);
+        assert_eq!(
+            subjects(&adapter),
+            vec!["c2", "m_outer", "m_inner", "f2", "f1", "c1", "c0"]
+        );
+        assert_eq!(adapter.len().get(), 7);
+
+        adapter.default_action(1);
+        adapter.default_action(1);
+        assert_eq!(
+            adapter.len().get(),
+            7,
+            "Unfold prefetched children instantly"
+        );
+
+        adapter.prefetch(0, 6);
+        while !adapter.children.contains_key(adapter.history[2].id()) {
+            adapter.update();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        adapter.default_action(2);
+        assert_eq!(adapter.len().get(), 9);
+    }
+
+    #[test]
+    fn restore_session() {
+        let repo = nested_merges_repo("restore-session");
+        let range = vec![OsString::from("HEAD")];
+        let mut adapter = HistoryAdapter::new(repo.clone(), range.clone(), vec![], false).unwrap();
+        adapter.fill_up(50);
+        adapter.unfold_to_depth(0, 2);
+        wait_for_children(&mut adapter);
+        let session = adapter.session(3);
+        assert_eq!(session.loaded, 4);
+        assert_eq!(session.unfolded.len(), 2);
+
+        let mut restored = HistoryAdapter::new(repo.clone(), range, vec![], false).unwrap();
+        let selected = restored.restore(&session);
+        assert_eq!(selected, 3);
+        assert_eq!(subjects(&restored), subjects(&adapter));
+    }
+
+    #[test]
+    fn fold_and_unfold_all() {
+        let repo = nested_merges_repo("fold-and-unfold-all");
+        let range = vec![OsString::from("HEAD")];
+        let mut adapter = HistoryAdapter::new(repo.clone(), range, vec![], false).unwrap();
+        adapter.fill_up(50);
+        assert_eq!(subjects(&adapter), vec!["c2", "m_outer", "c1", "c0"]);
+
+        let selected = adapter.unfold_to_depth(2, 1);
+        assert_eq!(
+            subjects(&adapter),
+            vec!["c2", "m_outer", "loading…", "c1", "c0"],
+            "Children are loaded in the background"
+        );
+        assert_eq!(selected, 3, "Selection follows the selected entry");
+        wait_for_children(&mut adapter);
+        assert_eq!(
+            subjects(&adapter),
+            vec!["c2", "m_outer", "m_inner", "f2", "f1", "c1", "c0"]
+        );
+        assert_eq!(adapter.len().get(), 7);
+
+        adapter.unfold_to_depth(0, 2);
+        wait_for_children(&mut adapter);
+        assert_eq!(
+            subjects(&adapter),
+            vec!["c2", "m_outer", "m_inner", "s1", "f1", "f2", "f1", "c1", "c0"]
+        );
+        assert!(adapter.history[4].is_link(), "s1 forks from f1 not from f2");
+
+        let selected = adapter.unfold_to_depth(3, 1);
+        assert_eq!(adapter.len().get(), 7);
+        assert_eq!(selected, 2, "Select the parent of a folded entry");
+
+        let selected = adapter.fold_all(4);
+        assert_eq!(subjects(&adapter), vec!["c2", "m_outer", "c1", "c0"]);
+        assert_eq!(adapter.len().get(), 4);
+        assert_eq!(selected, 1, "Select the first-parent ancestor");
+
+        adapter.unfold_subtree(1);
+        assert_eq!(
+            adapter.len().get(),
+            9,
+            "Unfold prefetched children instantly"
+        );
+        adapter.default_action(1);
+        assert_eq!(subjects(&adapter), vec!["c2", "m_outer", "c1", "c0"]);
+    }
+
     #[test]
     #[should_panic]
     fn not_loaded_default_action() {
@@ -610,6 +1198,7 @@ mod test {
             adapter.fill_up(50);
             assert_eq!(adapter.history.len(), 9);
             adapter.default_action(8);
+            wait_for_children(&mut adapter);
             assert_eq!(adapter.history.len(), 15);
             adapter.default_action(8);
             assert_eq!(adapter.history.len(), 9);
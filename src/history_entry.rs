@@ -17,14 +17,17 @@
 
 use url::Url;
 
-use crossterm::style::{style, Attribute, ContentStyle, StyledContent};
+use std::str::FromStr;
+
+use crossterm::style::{style, Attribute, Color, ContentStyle, StyledContent, Stylize};
 use getset::{CopyGetters, Getters, Setters};
 use git_stree::SubtreeConfig;
 
 use crate::actors::fork_point::ForkPointCalculation;
 use crate::commit::{parse_remote_url, Commit, GitRef, Oid};
 use crate::default_styles::{DATE_STYLE, ID_STYLE, MOD_STYLE, NAME_STYLE, REF_STYLE};
-use crate::ui::base::StyledLine;
+use crate::forge;
+use crate::refs::{Ref, RefKind};
 use git_wrapper::Remote;
 use lazy_static::lazy_static;
 use subject_classifier::{Subject, SubtreeOperation};
@@ -38,11 +41,57 @@ lazy_static! {
         regex::Regex::new(r#".+{8,} \d\d:\d\d$"#).expect("Valid RegEx");
 }
 
+/// A named column of a [`HistoryEntry`] in the history table
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Column {
+    Id,
+    Date,
+    Author,
+    Committer,
+    Icon,
+    Graph,
+    /// `HEAD`, local & remote branches
+    Refs,
+    /// Tags, notes, stash & other refs
+    Tags,
+    /// Subtree modules changed by the commit
+    Modules,
+    Subject,
+    /// Number of the merged pull request
+    PullRequest,
+    /// Verification status of the signature
+    Signature,
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "id" => Ok(Self::Id),
+            "date" => Ok(Self::Date),
+            "author" => Ok(Self::Author),
+            "committer" => Ok(Self::Committer),
+            "icon" => Ok(Self::Icon),
+            "graph" => Ok(Self::Graph),
+            "refs" => Ok(Self::Refs),
+            "tags" => Ok(Self::Tags),
+            "modules" => Ok(Self::Modules),
+            "subject" => Ok(Self::Subject),
+            "pr" => Ok(Self::PullRequest),
+            "signature" => Ok(Self::Signature),
+            _ => Err(format!("Unknown column “{}”", s)),
+        }
+    }
+}
+
 // Tracks which kind of commit an `HistoryEntry` is.
+#[derive(Clone)]
 pub enum EntryKind {
     IncomingOnly,
     IncomingAndOutgoing,
     Link,
+    Loading,
     Orphan,
     OutgoingOnly,
 }
@@ -66,13 +115,14 @@ impl EntryKind {
             Self::IncomingOnly => '◉',
             Self::IncomingAndOutgoing => '●',
             Self::Link => '⭞',
+            Self::Loading => '┆',
             Self::Orphan => '○',
             Self::OutgoingOnly => '◒',
         }
     }
 }
 
-#[derive(CopyGetters, Getters, Setters)]
+#[derive(Clone, CopyGetters, Getters, Setters)]
 pub struct HistoryEntry {
     #[getset(get = "pub")]
     commit: Commit,
@@ -91,6 +141,12 @@ pub struct HistoryEntry {
     fork_point: ForkPointCalculation,
     #[getset(get = "pub")]
     debug: bool,
+    /// Commit appeared at the top of the history, after the repository changed
+    #[getset(get_copy = "pub", set = "pub")]
+    is_new: bool,
+    /// Refs pointing to the commit, ordered by kind
+    #[getset(get = "pub", set = "pub")]
+    refs: Vec<Ref>,
 }
 
 impl HistoryEntry {
@@ -133,18 +189,42 @@ impl HistoryEntry {
             forge_url,
             fork_point,
             debug,
+            is_new: false,
+            refs: vec![],
         }
     }
 
+    /// A placeholder entry shown while the children of a merge are loading
+    #[must_use]
+    pub fn loading(level: u8, id_width: usize) -> Self {
+        Self::new(
+            Commit::placeholder(id_width, "loading…"),
+            level,
+            None,
+            ForkPointCalculation::Done(false),
+            &[],
+            EntryKind::Loading,
+            false,
+        )
+    }
+
     pub const fn is_link(&self) -> bool {
         matches!(self.kind, EntryKind::Link)
     }
+
+    pub const fn is_loading(&self) -> bool {
+        matches!(self.kind, EntryKind::Loading)
+    }
 }
 // Rendering operations
 impl HistoryEntry {
     fn render_id(&self) -> StyledContent<String> {
         let id = self.commit.short_id();
-        StyledContent::new(*ID_STYLE, id.clone())
+        let mut result = StyledContent::new(*ID_STYLE, id.clone());
+        if self.is_new {
+            result.style_mut().attributes.set(Attribute::Underlined);
+        }
+        result
     }
 
     fn render_date(&self) -> StyledContent<String> {
@@ -152,12 +232,12 @@ impl HistoryEntry {
         let mut end = date.len();
         // Find and remove the timezone
         // arithmetic: if checks for `end` being large enough.
-        #[allow(clippy::arithmetic)]
+        #[allow(clippy::arithmetic_side_effects)]
         if end - 6 > 0 && (&date[end - 6..end - 4] == " +" || &date[end - 6..end - 4] == " -") {
             end -= 6;
         }
 
-        #[allow(clippy::arithmetic)]
+        #[allow(clippy::arithmetic_side_effects)]
         if TIME_SPLIT_REGEX.is_match(date) {
             // arithmetic: regex assure there are 5 chars at the end
             end -= 5;
@@ -223,30 +303,32 @@ impl HistoryEntry {
         }
     }
 
-    fn shorten_references(remotes: &[Remote], references: &[&GitRef]) -> Vec<String> {
+    /// Return the names of the remote branches, multiple branches of the same remote are
+    /// grouped as `remote/{a,b}`
+    fn shorten_remote_branches(remotes: &[Remote], branches: &[&str]) -> Vec<String> {
         let mut result = vec![];
-        if !references.is_empty() {
+        if !branches.is_empty() {
             if remotes.is_empty() {
-                for r in references {
-                    result.push(r.to_string());
+                for r in branches {
+                    result.push((*r).to_owned());
                 }
             } else {
-                let mut mut_refs = references.to_vec();
+                let mut mut_refs = branches.to_vec();
                 let mut tmp_result = vec![];
                 for remote in remotes {
                     let mut remote_branches = vec![];
                     if mut_refs.is_empty() {
                         break;
                     }
-                    for git_ref in references {
-                        if git_ref.to_string().starts_with(&remote.name) {
-                            remote_branches.push(git_ref);
-                            mut_refs.retain(|x| x != git_ref);
+                    for branch in branches {
+                        if branch.starts_with(&remote.name) {
+                            remote_branches.push(branch);
+                            mut_refs.retain(|x| x != branch);
                         }
                     }
                     if !remote_branches.is_empty() {
                         if remote_branches.len() == 1 {
-                            result.push(remote_branches[0].to_string());
+                            result.push((*remote_branches[0]).to_owned());
                         } else {
                             let prefix_len = remote.name.len().saturating_add(1);
                             let mut text = remote.name.clone();
@@ -255,7 +337,7 @@ impl HistoryEntry {
                             text.push_str(
                                 &remote_branches
                                     .iter()
-                                    .map(|r| r.to_string().split_off(prefix_len))
+                                    .map(|r| r.get(prefix_len..).unwrap_or_default())
                                     .collect::<Vec<_>>()
                                     .join(","),
                             );
@@ -264,7 +346,7 @@ impl HistoryEntry {
                         }
                     }
                 }
-                result.extend(mut_refs.iter().map(std::string::ToString::to_string));
+                result.extend(mut_refs.iter().map(|r| (*r).to_owned()));
                 result.extend(tmp_result);
             }
         }
@@ -286,16 +368,34 @@ impl HistoryEntry {
         StyledContent::new(style, text)
     }
 
-    fn render_references(&self) -> Vec<StyledContent<String>> {
+    /// Render `HEAD` & the branches or else the other refs
+    fn render_references(&self, branches: bool) -> Vec<StyledContent<String>> {
+        let mut names = vec![];
+        let mut remotes_done = false;
+        for r in self.refs.iter().filter(|r| r.kind.is_branch() == branches) {
+            if r.kind != RefKind::RemoteBranch {
+                names.push((r.kind, r.name.clone()));
+            } else if !remotes_done {
+                remotes_done = true;
+                let remote_branches: Vec<&str> = self
+                    .refs
+                    .iter()
+                    .filter(|r| r.kind == RefKind::RemoteBranch)
+                    .map(|r| r.name.as_str())
+                    .collect();
+                names.extend(
+                    Self::shorten_remote_branches(&self.remotes, &remote_branches)
+                        .into_iter()
+                        .map(|name| (RefKind::RemoteBranch, name)),
+                );
+            }
+        }
         let mut result = vec![];
-        let references = self.filtered_references();
-        for r in Self::shorten_references(&self.remotes, &references) {
-            let separator = style(" ".to_owned());
-            result.push(separator);
-
-            let text = format!("«{}»", r);
-            let sc = StyledContent::new(*REF_STYLE, text);
-            result.push(sc);
+        for (kind, name) in names {
+            if !result.is_empty() {
+                result.push(style(" ".to_owned()));
+            }
+            result.push(StyledContent::new(kind.style(), format!("«{}»", name)));
         }
         result
     }
@@ -306,13 +406,11 @@ impl HistoryEntry {
         references
             .iter()
             .filter(|r| {
-                for prefix in &ignored_refs() {
-                    if r.0.starts_with(&prefix.0) {
-                        log::info!("Branch {} hidden", r.0);
-                        return false;
-                    }
+                let ignored = is_ignored_ref(&r.0);
+                if ignored {
+                    log::info!("Branch {} hidden", r.0);
                 }
-                true
+                !ignored
             })
             .collect()
     }
@@ -333,7 +431,7 @@ impl HistoryEntry {
                 }
                 if *category == subject_classifier::Type::Deps {
                     if let Some(word) = description.split_whitespace().last() {
-                        #[allow(clippy::arithmetic)]
+                        #[allow(clippy::arithmetic_side_effects)]
                         if word.starts_with('v')
                             || word.starts_with('V')
                             || word.chars().next().unwrap().is_numeric()
@@ -385,38 +483,61 @@ impl HistoryEntry {
         buf
     }
 
-    pub fn render(&self, selected: bool) -> StyledLine<String> {
-        let separator = style(" ".to_owned());
-        let mut result: StyledLine<String> = StyledLine {
-            content: vec![
-                self.render_id(),
-                separator.clone(),
-                self.render_date(),
-                separator.clone(),
-                self.render_name(),
-                separator.clone(),
-                self.render_icon(),
-                self.render_graph(),
-            ],
-        };
-        let references = self.render_references();
-        if !references.is_empty() {
-            result.content.extend(references);
-        }
-        result.content.push(separator.clone());
-        if let Some(modules) = self.render_modules(32) {
-            result.content.push(modules);
-            result.content.push(separator);
+    fn render_committer(&self) -> StyledContent<String> {
+        StyledContent::new(*NAME_STYLE, self.commit.committer_name().clone())
+    }
+
+    fn render_pull_request(&self) -> Option<StyledContent<String>> {
+        match &self.subject {
+            Subject::PullRequest { id, .. } => Some(StyledContent::new(
+                *REF_STYLE,
+                format!("#{}", id.trim_start_matches('#')),
+            )),
+            _ => None,
         }
-        result.content.extend(self.render_subject());
+    }
 
-        if selected {
-            for part in &mut result.content {
-                part.style_mut().attributes.set(Attribute::Reverse);
-            }
+    fn render_signature(&self) -> Option<StyledContent<String>> {
+        let (symbol, color) = match self.commit.signature().as_str() {
+            "G" => ('✓', Color::DarkGreen),
+            "B" => ('✗', Color::DarkRed),
+            "U" | "X" | "Y" | "R" | "E" => ('?', Color::DarkYellow),
+            _ => return None,
         };
-        result
+        Some(symbol.to_string().with(color))
+    }
+
+    /// Return the content of the column, which is empty when the entry has no such data
+    pub fn render_column(&self, column: Column) -> Vec<StyledContent<String>> {
+        if self.is_loading() {
+            let mut loading_style = ContentStyle::default();
+            loading_style.attributes.set(Attribute::Italic);
+            return match column {
+                Column::Id => vec![self.render_id()],
+                Column::Graph => vec![self.render_graph()],
+                Column::Subject => vec![StyledContent::new(
+                    loading_style,
+                    self.commit.subject().clone(),
+                )],
+                _ => vec![],
+            };
+        }
+        match column {
+            Column::Id => vec![self.render_id()],
+            Column::Date => vec![self.render_date()],
+            Column::Author => vec![self.render_name()],
+            Column::Committer => vec![self.render_committer()],
+            Column::Icon => vec![self.render_icon()],
+            Column::Graph => vec![self.render_graph()],
+            Column::Refs => self.render_references(true),
+            Column::Tags => self.render_references(false),
+            Column::Modules => self.render_modules(32).into_iter().collect(),
+            Column::Subject => self.render_subject(),
+            Column::PullRequest => self.render_pull_request().into_iter().collect(),
+            Column::Signature => self.render_signature().into_iter().collect(),
+        }
     }
+
     const fn is_subtree_import(&self) -> bool {
         matches!(
             &self.subject,
@@ -547,9 +668,26 @@ impl HistoryEntry {
         }
         self.forge_url.clone()
     }
+
+    /// Return the forge web URL of the pull request or else of the commit
+    #[must_use]
+    pub fn web_url(&self) -> Option<Url> {
+        let remote = self.url()?;
+        match &self.subject {
+            Subject::PullRequest { id, .. } => forge::pull_request_url(&remote, id),
+            _ => forge::commit_url(&remote, self.id()),
+        }
+    }
 }
 
 fn ignored_refs() -> Vec<IgnoredRefWildcard> {
     // TODO extend this to read ignored refs from ini file
     vec![IgnoredRefWildcard("refs/prefetch/".to_owned())]
 }
+
+/// Return `true` if the ref is hidden, i.e. the refs updated by `git maintenance`
+pub fn is_ignored_ref(name: &str) -> bool {
+    ignored_refs()
+        .iter()
+        .any(|prefix| name.starts_with(&prefix.0))
+}
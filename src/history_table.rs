@@ -15,90 +15,448 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::cmp::Ordering;
-use std::collections::HashMap;
 use std::num::NonZeroUsize;
 
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
-use crossterm::style::StyledContent;
-use unicode_segmentation::UnicodeSegmentation;
-use unicode_width::UnicodeWidthStr;
+use crossterm::style::{style, Attribute, Stylize};
 
+use crate::actions::ActionMenu;
+use crate::actors::search::FilterResult;
+use crate::clipboard::{self, YankMenu};
+use crate::detail::{Comparison, Selection};
+use crate::forge;
 use crate::history_adapter::HistoryAdapter;
-use crate::history_entry::HistoryEntry;
+use crate::history_entry::Column;
+use crate::ref_picker::RefPicker;
 use crate::search::highlight_search_line;
+use crate::table_layout::{Row, TableLayout};
 use crate::ui::base::data::SearchProgress;
 use crate::ui::base::paging::Paging;
+use crate::ui::base::search::{Direction, Needle};
 use crate::ui::base::{
-    shorten_line, Area, Drawable, HandleEvent, Selectable, StyledArea, StyledLine,
+    shorten_line, Area, Drawable, HandleEvent, Selectable, StyledArea, StyledLine, SCROLL_STEP,
 };
-use crate::ui::search::SearchWidget;
+use crate::ui::input::InputLine;
+use crate::ui::search::{search_history, store_search_history, SearchWidget};
+use crate::utils::repo_relative;
+use std::ffi::OsString;
 use std::sync::mpsc::Receiver;
 
-#[derive(Copy, Clone)]
-pub enum ColumnStyle {
-    MaxWidth(usize),
-    None,
+/// Hides the commits not matching a needle
+struct Filter {
+    needle: Needle,
+    receiver: Option<Receiver<FilterResult>>,
+    result: Option<FilterResult>,
+    /// Indices of the shown [`HistoryAdapter`] entries
+    rows: Vec<usize>,
+    /// [`HistoryAdapter::version`] the `rows` were calculated for
+    version: Option<usize>,
 }
 
-#[allow(clippy::module_name_repetitions)]
-#[derive(Default)]
-pub struct TableStyle(Vec<ColumnStyle>);
-
-impl TableStyle {
-    pub fn get(&self, col_number: usize) -> ColumnStyle {
-        self.0
-            .get(col_number)
-            .map_or(ColumnStyle::None, |result| *result)
+impl Filter {
+    fn render(&self, width: usize) -> StyledLine<String> {
+        let status = match &self.result {
+            Some(result) => format!(
+                "\tShown({}) / Matches({})\t<Esc> to clear",
+                self.rows.len(),
+                result.matches
+            ),
+            None => "\tFiltering…".to_owned(),
+        };
+        let line = StyledLine {
+            content: vec![
+                style("&".to_owned()),
+                style(self.needle.text().clone()),
+                style(status),
+            ],
+            links: vec![],
+        };
+        shorten_line(line, width)
     }
 }
 
 #[allow(clippy::module_name_repetitions)]
 pub struct TableWidget {
     adapter: HistoryAdapter,
-    style: TableStyle,
+    layout: TableLayout,
     paging: Paging,
     search_input: SearchWidget,
     search_progress_tx: Option<Receiver<SearchProgress>>,
+    /// Input of the filter needle, while it is captured
+    filter_input: Option<InputLine>,
+    filter: Option<Filter>,
+    actions: ActionMenu,
+    yank: YankMenu,
+    refs: RefPicker,
+    /// Previously shown ranges, the last one is the newest
+    back: Vec<Vec<OsString>>,
+    /// Ranges left by going back, the last one is the next
+    forward: Vec<Vec<OsString>>,
+    /// Commit compared with the selected one in the [`crate::detail::DiffView`]
+    mark: Option<Comparison>,
+    selection: Option<Selection>,
+    /// Display columns the rows are scrolled to the right, except the frozen id & graph
+    offset: usize,
 }
 
 impl TableWidget {
-    pub fn new(adapter: HistoryAdapter) -> Self {
-        let column_styles: Vec<ColumnStyle> = vec![
-            ColumnStyle::None,
-            ColumnStyle::None, // separator
-            ColumnStyle::MaxWidth(20),
-            ColumnStyle::None, // separator
-            ColumnStyle::MaxWidth(12),
-        ];
+    pub fn new(adapter: HistoryAdapter, layout: TableLayout) -> Self {
         let search_input = SearchWidget::default();
         Self {
             adapter,
-            style: TableStyle(column_styles),
+            layout,
             paging: Paging::default(),
             search_input,
             search_progress_tx: None,
+            filter_input: None,
+            filter: None,
+            actions: ActionMenu::default(),
+            yank: YankMenu::default(),
+            refs: RefPicker::default(),
+            back: vec![],
+            forward: vec![],
+            mark: None,
+            selection: None,
+            offset: 0,
         }
     }
+    pub const fn adapter(&self) -> &HistoryAdapter {
+        &self.adapter
+    }
+
     pub fn default_action(&mut self) {
-        self.adapter.default_action(self.paging.selected());
-        self.paging.set_total_length(self.adapter.len());
+        let row = self.selected_row();
+        self.adapter.default_action(row);
+        self.select_row(row);
+    }
+
+    /// Fold everything back to first-parent history for `depth == 0`, otherwise unfold all
+    /// merges, so entries up to `depth` levels deep are visible.
+    fn unfold_to_depth(&mut self, depth: u8) {
+        let selected = if depth == 0 {
+            self.adapter.fold_all(self.selected_row())
+        } else {
+            self.adapter.unfold_to_depth(self.selected_row(), depth)
+        };
+        self.select_row(selected);
+    }
+
+    /// Restore folding state & selection from the last session
+    pub fn restore_session(&mut self) {
+        let selected = self.adapter.restore_session();
+        self.select_row(selected);
+    }
+
+    pub fn store_session(&self) {
+        self.adapter.store_session(self.selected_row());
+    }
+
+    fn unfold_subtree(&mut self) {
+        let row = self.selected_row();
+        self.adapter.unfold_subtree(row);
+        self.select_row(row);
+    }
+
+    /// Return the rows of an applied filter
+    fn filter_rows(&self) -> Option<&Vec<usize>> {
+        self.filter
+            .as_ref()
+            .filter(|f| f.result.is_some())
+            .map(|f| &f.rows)
+    }
+
+    /// Return the [`HistoryAdapter`] index of the visible row `i`
+    fn row(&self, i: usize) -> usize {
+        self.filter_rows()
+            .map_or(i, |rows| rows.get(i).copied().unwrap_or_default())
+    }
+
+    fn selected_row(&self) -> usize {
+        self.row(self.paging.selected())
+    }
+
+    fn len(&self) -> NonZeroUsize {
+        self.filter_rows().map_or(self.adapter.len(), |rows| {
+            NonZeroUsize::new(rows.len()).unwrap_or(NonZeroUsize::MIN)
+        })
+    }
+
+    /// Update the visible rows after the [`HistoryAdapter`] entries changed & select the
+    /// visible row closest to the adapter index `row`
+    fn select_row(&mut self, row: usize) {
+        if let Some(filter) = &mut self.filter {
+            if let Some(result) = &filter.result {
+                if filter.version != Some(self.adapter.version()) {
+                    filter.rows = self.adapter.filter_rows(result);
+                    filter.version = Some(self.adapter.version());
+                }
+            }
+        }
+        let selected = self.filter_rows().map_or(row, |rows| {
+            rows.partition_point(|r| *r <= row).saturating_sub(1)
+        });
+        self.paging.set_total_length(self.len());
+        self.paging.set_selected(selected);
+    }
+
+    fn apply_filter(&mut self, text: &str) {
+        let row = self.selected_row();
+        self.filter = (!text.is_empty()).then(|| {
+            let needle = Needle::smart_case(text, Direction::Forward);
+            Filter {
+                receiver: Some(self.adapter.filter(needle.clone())),
+                needle,
+                result: None,
+                rows: vec![],
+                version: None,
+            }
+        });
+        self.select_row(row);
+    }
+
+    fn filter_input_on_event(&mut self, event: &Event) -> HandleEvent {
+        let input = self.filter_input.as_mut().expect("Capturing filter");
+        if input.on_event(event) == HandleEvent::Handled {
+            return HandleEvent::Handled;
+        }
+        match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Enter,
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => {
+                let text = input.text().clone();
+                input.add_to_history();
+                store_search_history(input.history());
+                self.filter_input = None;
+                self.apply_filter(&text);
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Esc,
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => self.filter_input = None,
+            _ => {}
+        }
+        HandleEvent::Handled
+    }
+
+    /// Run the command confirmed in the action menu & reload the history
+    fn run_action(&mut self) {
+        if let Some(command) = self.actions.take_confirmed() {
+            let output = command.run(self.adapter.repo());
+            self.actions.show_output(output);
+            let selected = self.adapter.reload(self.selected_row());
+            self.select_row(selected);
+        }
+    }
+
+    /// Copy the text chosen in the yank menu to the clipboard
+    fn run_yank(&mut self) {
+        if let Some(yank) = self.yank.take_chosen() {
+            let row = self.selected_row();
+            let repo = self.adapter.repo().clone();
+            let entry = self.adapter.get_data(row);
+            let message = match yank.text(&repo, entry) {
+                None => "Nothing to copy".to_owned(),
+                Some(text) => match clipboard::copy(&text) {
+                    Ok(()) => format!("Copied {} to the clipboard", yank.label()),
+                    Err(err) => format!("Failed to copy: {}", err),
+                },
+            };
+            self.yank.show_message(message);
+        }
+    }
+
+    /// Open the forge web page of the selected commit or pull request, or with `file` of the
+    /// filtered path at the selected commit
+    fn open_in_browser(&mut self, file: bool) {
+        let paths = self.adapter.paths().to_vec();
+        let work_tree = self.adapter.repo().work_tree().unwrap_or_default();
+        let entry = self.adapter.get_data(self.selected_row());
+        if entry.is_loading() {
+            return;
+        }
+        let url = if file {
+            match paths.as_slice() {
+                [path] => match repo_relative(&work_tree, path) {
+                    Some(path) => entry
+                        .url()
+                        .and_then(|remote| forge::file_url(&remote, entry.id(), &path)),
+                    None => {
+                        let message = format!("{} is outside of the work tree", path.display());
+                        self.yank.show_message(message);
+                        return;
+                    }
+                },
+                _ => {
+                    let message = "Opening a file needs exactly one path argument".to_owned();
+                    self.yank.show_message(message);
+                    return;
+                }
+            }
+        } else {
+            entry.web_url()
+        };
+        let message = match url {
+            None => "No forge URL found".to_owned(),
+            Some(url) => match forge::open(&url) {
+                Ok(true) => format!("Opened {}", url),
+                Ok(false) => url.to_string(),
+                Err(err) => format!("Failed to open {}: {}", url, err),
+            },
+        };
+        self.yank.show_message(message);
+    }
+
+    /// Show the history of the ref chosen in the ref picker
+    fn run_ref_picker(&mut self) {
+        if let Some(name) = self.refs.take_chosen() {
+            let current = self.adapter.range().to_vec();
+            if self.switch_range(vec![OsString::from(name)]) {
+                self.back.push(current);
+                self.forward.clear();
+            }
+        }
+    }
+
+    /// Go back to the previously shown range, or forward again when `forward` is `true`
+    fn navigate(&mut self, forward: bool) {
+        let (from, to) = if forward {
+            (&mut self.forward, &mut self.back)
+        } else {
+            (&mut self.back, &mut self.forward)
+        };
+        let Some(range) = from.pop() else {
+            return;
+        };
+        let current = self.adapter.range().to_vec();
+        to.push(current);
+        if !self.switch_range(range.clone()) {
+            let (from, to) = if forward {
+                (&mut self.forward, &mut self.back)
+            } else {
+                (&mut self.back, &mut self.forward)
+            };
+            to.pop();
+            from.push(range);
+        }
+    }
+
+    /// Replace the history with the one of the range, return `false` if it failed
+    fn switch_range(&mut self, range: Vec<OsString>) -> bool {
+        let name = range
+            .iter()
+            .map(|r| r.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ");
+        match self.adapter.with_range(range) {
+            Ok(adapter) => {
+                self.store_session();
+                self.adapter = adapter;
+                self.filter = None;
+                self.mark = None;
+                self.offset = 0;
+                self.paging = Paging::default();
+                self.restore_session();
+                true
+            }
+            Err(err) => {
+                log::warn!("Failed to show {}: {}", name, err);
+                self.refs.show_message(format!("Failed to show {}", name));
+                false
+            }
+        }
+    }
+
+    /// Mark the selected commit for comparing, or clear the mark if it is selected
+    fn toggle_mark(&mut self) {
+        let entry = self.adapter.get_data(self.selected_row());
+        if entry.is_loading() {
+            return;
+        }
+        if self
+            .mark
+            .as_ref()
+            .is_some_and(|m| m.from().id() == entry.id())
+        {
+            self.mark = None;
+        } else {
+            self.mark = Some(Comparison::new(entry.commit().clone(), false));
+        }
+    }
+
+    fn mark_line(&self, width: usize) -> Option<StyledLine<String>> {
+        let mark = self.mark.as_ref()?;
+        let dots = if mark.symmetric() { "..." } else { ".." };
+        let line = StyledLine {
+            content: vec![
+                style("Marked ".to_owned()),
+                style(mark.from().short_id().clone()).attribute(Attribute::Bold),
+                style(format!(
+                    " {}\t<Enter> diff {}{}selected  <M> toggle ../...  <m> clear",
+                    mark.from().subject(),
+                    mark.from().short_id(),
+                    dots
+                )),
+            ],
+            links: vec![],
+        };
+        Some(shorten_line(line, width))
+    }
+
+    /// Return the status lines for the action & yank menu, mark, filter & search
+    fn status_lines(&mut self, width: usize) -> StyledArea<String> {
+        let mut result = self.actions.render(width);
+        result.append(&mut self.yank.render(width));
+        result.append(&mut self.refs.render(width));
+        if let Some(line) = self.mark_line(width) {
+            result.push(line);
+        }
+        if let Some(input) = &self.filter_input {
+            let mut content = vec![style("&".to_owned())];
+            content.append(&mut input.render_line());
+            result.push(shorten_line(
+                StyledLine {
+                    content,
+                    links: vec![],
+                },
+                width,
+            ));
+        } else if let Some(filter) = &self.filter {
+            result.push(filter.render(width));
+        }
+        if self.search_input.is_visible() {
+            result.push(self.search_input.render(width));
+        }
+        result
     }
 }
 
 impl Drawable for TableWidget {
     fn render(&mut self, area: &Area) -> StyledArea<String> {
-        let mut tmp: StyledArea<String> = vec![];
-        #[allow(clippy::arithmetic)]
-        // arithmetic: height is >= 4
-        let page_height = if self.search_input.is_visible() {
-            NonZeroUsize::new(area.height() - 1).unwrap()
-        } else {
-            NonZeroUsize::new(area.height()).unwrap()
-        };
+        if self.adapter.refs_changed() {
+            let selected = self.adapter.reload(self.selected_row());
+            self.select_row(selected);
+        }
+        let received = self
+            .filter
+            .as_mut()
+            .and_then(|f| f.receiver.as_ref().and_then(|r| r.try_recv().ok()));
+        if let Some(result) = received {
+            let row = self.selected_row();
+            if let Some(filter) = &mut self.filter {
+                filter.receiver = None;
+                filter.result = Some(result);
+            }
+            self.select_row(row);
+        }
+        let status_height = self.status_lines(area.width()).len();
+        let page_height = NonZeroUsize::new(area.height().saturating_sub(status_height))
+            .unwrap_or(NonZeroUsize::MIN);
         if let Some(needle) = self.search_input.search_value() {
             if !needle.text().is_empty() {
-                let tx = self.adapter.search(needle, self.paging.selected());
+                let tx = self.adapter.search(needle, self.selected_row());
                 self.search_progress_tx = Some(tx);
             }
         }
@@ -107,81 +465,91 @@ impl Drawable for TableWidget {
                 self.search_input.consume(progress);
             }
         }
-        self.paging.page_height(page_height, self.adapter.len());
+        self.paging.page_height(page_height, self.len());
 
         if let Some(result) = self.search_input.selected().as_ref() {
             let index = self.adapter.unfold_up_to(result);
-            self.paging.set_total_length(self.adapter.len());
-            self.paging.set_selected(index);
-        }
-
-        self.adapter.update();
-        for i in self.paging.top()..=self.paging.bottom() {
-            let line = self.adapter.get_line(i, i == self.paging.selected());
-            tmp.push(line);
-        }
-
-        if tmp.len() < page_height.get() {
-            for _ in tmp.len()..page_height.get() {
-                tmp.push(StyledLine::empty());
-            }
+            self.select_row(index);
         }
 
-        let mut max_column_widths = HashMap::new();
-        {
-            for (_, row) in tmp.iter().enumerate() {
-                for (col_number, cell) in row.content.iter().enumerate() {
-                    let text_len = UnicodeWidthStr::width(cell.content().as_str());
-                    if let Some(max) = max_column_widths.get(&col_number) {
-                        if text_len > *max {
-                            max_column_widths.insert(col_number, text_len);
-                        }
-                    } else {
-                        max_column_widths.insert(col_number, text_len);
-                    }
+        let mut selected = self.selected_row();
+        let unfolded = self.adapter.update();
+        if !unfolded.is_empty() {
+            for (i, inserted) in unfolded {
+                // The loading placeholder was replaced by the children
+                if selected > i.saturating_add(1) {
+                    selected = selected.saturating_add(inserted).saturating_sub(1);
                 }
             }
+            self.select_row(selected);
+            self.paging.page_height(page_height, self.len());
         }
-
-        let mut result = Vec::with_capacity(tmp.len());
-        for row in tmp {
-            let mut new_row = StyledLine {
-                content: Vec::with_capacity(row.content.len()),
-            };
-            for (col_number, cell) in row.content.iter().enumerate() {
-                match self.style.get(col_number) {
-                    ColumnStyle::MaxWidth(style_max) => {
-                        let mut max = *max_column_widths.get(&col_number).expect("max expected");
-                        if max > style_max {
-                            max = style_max;
+        let is_empty = self.filter_rows().is_some_and(Vec::is_empty);
+        let mark_id = self.mark.as_ref().map(|mark| mark.from().id().clone());
+        let mut rows = vec![];
+        for i in self.paging.top()..=self.paging.bottom() {
+            if is_empty {
+                break;
+            }
+            let entry = self.adapter.get_data(self.row(i));
+            let marked = mark_id.as_ref().is_some_and(|id| id == entry.id());
+            let cells = self
+                .layout
+                .columns()
+                .map(|column| {
+                    let mut cell = entry.render_column(column);
+                    if marked && column == Column::Id {
+                        for part in &mut cell {
+                            part.style_mut().attributes.toggle(Attribute::Reverse);
                         }
-                        let adjusted_content = adjust_string(cell.content(), max);
-                        new_row
-                            .content
-                            .push(StyledContent::new(*cell.style(), adjusted_content));
-                    }
-                    ColumnStyle::None => {
-                        new_row.content.push(cell.clone());
                     }
-                }
-            }
+                    cell
+                })
+                .collect();
+            rows.push(Row {
+                cells,
+                selected: i == self.paging.selected(),
+            });
+        }
+        self.adapter
+            .prefetch(self.row(self.paging.top()), self.row(self.paging.bottom()));
 
-            result.push(shorten_line(new_row, area.width()));
+        // Do not scroll further than the end of the longest row
+        let longest = self.layout.natural_width(&rows);
+        self.offset = self.offset.min(longest.saturating_sub(area.width()));
+        let mut result = self.layout.render(&rows, area.width(), self.offset);
+        while result.len() < page_height.get() {
+            result.push(StyledLine::empty());
         }
 
         if self.search_input.is_visible() {
-            let mut new_result = Vec::with_capacity(result.len());
-            for row in &mut result {
-                new_result.push(highlight_search_line(row, &self.search_input.needle()));
-            }
-            new_result.push(self.search_input.render(area.width()));
-            return new_result;
+            let query = self.search_input.needle().query();
+            result = result
+                .iter()
+                .map(|row| highlight_search_line(row, &query))
+                .collect();
         }
+        result.append(&mut self.status_lines(area.width()));
 
         result
     }
 
     fn on_event(&mut self, event: &Event) -> HandleEvent {
+        if self.filter_input.is_some() {
+            return self.filter_input_on_event(event);
+        }
+        if self.actions.on_event(event) == HandleEvent::Handled {
+            self.run_action();
+            return HandleEvent::Handled;
+        }
+        if self.yank.on_event(event) == HandleEvent::Handled {
+            self.run_yank();
+            return HandleEvent::Handled;
+        }
+        if self.refs.on_event(event) == HandleEvent::Handled {
+            self.run_ref_picker();
+            return HandleEvent::Handled;
+        }
         match self.search_input.on_event(event) {
             HandleEvent::Handled => HandleEvent::Handled,
             HandleEvent::Ignored => match self.paging.on_event(event) {
@@ -195,6 +563,128 @@ impl Drawable for TableWidget {
                         self.default_action();
                         HandleEvent::Handled
                     }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char('*'),
+                        modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                        ..
+                    }) => {
+                        self.unfold_subtree();
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char(c @ '0'..='9'),
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    }) => {
+                        let depth = c.to_digit(10).and_then(|d| u8::try_from(d).ok());
+                        self.unfold_to_depth(depth.unwrap_or_default());
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char('a'),
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    }) => {
+                        let row = self.selected_row();
+                        let entry = self.adapter.get_data(row);
+                        if !entry.is_loading() {
+                            self.actions.open(entry.commit().clone());
+                        }
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char('y'),
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    }) => {
+                        if !self.adapter.get_data(self.selected_row()).is_loading() {
+                            self.yank.open();
+                        }
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char('b'),
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    }) => {
+                        self.refs.open(self.adapter.repo());
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char(c @ ('<' | '>')),
+                        modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                        ..
+                    }) => {
+                        self.navigate(*c == '>');
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char('o'),
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    }) => {
+                        self.open_in_browser(false);
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char('O'),
+                        modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                        ..
+                    }) => {
+                        self.open_in_browser(true);
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char('&'),
+                        modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                        ..
+                    }) => {
+                        self.filter_input = Some(InputLine::with_history(search_history()));
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char('m'),
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    }) => {
+                        self.toggle_mark();
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char('M'),
+                        modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                        ..
+                    }) if self.mark.is_some() => {
+                        if let Some(mark) = self.mark.take() {
+                            let symmetric = !mark.symmetric();
+                            self.mark = Some(Comparison::new(mark.from().clone(), symmetric));
+                        }
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Left,
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    }) => {
+                        self.offset = self.offset.saturating_sub(SCROLL_STEP);
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Right,
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    }) => {
+                        self.offset = self.offset.saturating_add(SCROLL_STEP);
+                        HandleEvent::Handled
+                    }
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Esc,
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    }) if self.filter.is_some() => {
+                        self.apply_filter("");
+                        HandleEvent::Handled
+                    }
                     _ => HandleEvent::Ignored,
                 },
             },
@@ -202,64 +692,11 @@ impl Drawable for TableWidget {
     }
 }
 
-impl Selectable<HistoryEntry> for TableWidget {
-    fn selected_item(&mut self) -> &HistoryEntry {
-        let tmp: &HistoryEntry = self.adapter.get_data(self.paging.selected());
-        tmp
-    }
-}
-
-// I'm not proud of this code. Ohh Omnissiah be merciful on my soul‼
-// arithmetic: This code is not reviewed, but it will be removed soon
-#[warn(clippy::arithmetic)]
-fn adjust_string(text: &str, expected: usize) -> String {
-    debug_assert!(expected > 0, "Minimal length should be 1");
-    let length = unicode_width::UnicodeWidthStr::width(text);
-    let mut result = String::from(text);
-    match length.cmp(&expected) {
-        Ordering::Less => {
-            let actual = expected - length;
-            for _ in 0..actual {
-                result.push(' ');
-            }
-        }
-        Ordering::Equal => {}
-        Ordering::Greater => {
-            result = "".to_owned();
-            for w in text.unicode_words().collect::<Vec<&str>>() {
-                let actual = UnicodeWidthStr::width(result.as_str()) + UnicodeWidthStr::width(w);
-                if actual > expected {
-                    break;
-                }
-                result.push_str(w);
-                result.push(' ');
-            }
-
-            if result.is_empty() {
-                let words = text.unicode_words().collect::<Vec<&str>>();
-                result.push_str(words[0]);
-            }
-
-            let actual = UnicodeWidthStr::width(result.as_str());
-            if actual > expected {
-                let mut tmp = String::new();
-                let mut i = 0;
-                for g in result.as_str().graphemes(true) {
-                    tmp.push_str(g);
-                    i += 1;
-                    if i == expected - 1 {
-                        break;
-                    }
-                }
-                result = tmp;
-                result.push('…');
-            } else {
-                let end = expected - actual;
-                for _ in 0..end {
-                    result.push(' ');
-                }
-            }
-        }
+impl Selectable<Selection> for TableWidget {
+    fn selected_item(&mut self) -> &Selection {
+        let row = self.selected_row();
+        let entry = self.adapter.get_data(row).clone();
+        self.selection
+            .insert(Selection::new(entry, self.mark.clone()))
     }
-    result
 }
@@ -0,0 +1,163 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Hyperlinks of URLs, pull request numbers & commit ids in the rendered text.
+//!
+//! Pull request numbers (`#123`) & commit ids link to the forge of the repository. The links are
+//! emitted as OSC 8 sequences, so they can be opened with a click in modern terminals. Set
+//! `$GLV_HYPERLINKS` to `0` to disable them, i.e. for terminals printing the sequences.
+
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+use url::Url;
+
+use crate::commit::Oid;
+use crate::forge;
+use crate::ui::base::{Hyperlink, StyledLine};
+
+lazy_static! {
+    static ref ENABLED: bool = std::env::var_os("GLV_HYPERLINKS").is_none_or(|v| v != "0");
+    static ref LINKABLE: Regex =
+        Regex::new(r#"(?P<url>https?://[^\s<>"'`]+)|(?P<pr>\B#\d+\b)|(?P<id>\b[0-9a-f]{7,40}\b)"#)
+            .expect("Valid regex");
+}
+
+/// Web URL of the forge the pull request numbers & commit ids link to
+static FORGE_URL: OnceLock<Url> = OnceLock::new();
+
+/// Set the remote URL of the forge, only the first call has an effect
+pub fn set_forge_url(url: Url) {
+    // Ignoring the error, the forge does not change while running
+    let _ = FORGE_URL.set(url);
+}
+
+/// Strip trailing punctuation & unbalanced closing parentheses, which are most likely not part
+/// of the URL
+fn trim_url(mut url: &str) -> &str {
+    loop {
+        url = url.trim_end_matches(['.', ',', ':', ';', '!', '?', ']', '>']);
+        let unbalanced = url.matches(')').count() > url.matches('(').count();
+        match url.strip_suffix(')') {
+            Some(trimmed) if unbalanced => url = trimmed,
+            _ => return url,
+        }
+    }
+}
+
+/// Return the byte ranges & targets of the links in the text
+fn detect(text: &str, forge_url: Option<&Url>) -> Vec<(Range<usize>, Url)> {
+    let mut result = vec![];
+    // The `index <blob>..<blob>` header of a diff contains blob ids, not commit ids
+    let blob_ids = text.starts_with("index ");
+    for captures in LINKABLE.captures_iter(text) {
+        if let Some(m) = captures.name("url") {
+            let url = trim_url(m.as_str());
+            if let Ok(target) = Url::parse(url) {
+                result.push((m.start()..m.start().saturating_add(url.len()), target));
+            }
+        } else if let Some(m) = captures.name("pr") {
+            if let Some(target) = forge_url.and_then(|f| forge::pull_request_url(f, m.as_str())) {
+                result.push((m.range(), target));
+            }
+        } else if let Some(m) = captures.name("id") {
+            // Words like `defaced` are no commit ids
+            let id = m.as_str();
+            let has_digit = id.chars().any(|c| c.is_ascii_digit());
+            let has_letter = id.chars().any(|c| c.is_ascii_alphabetic());
+            if has_digit && has_letter && !blob_ids {
+                if let Some(target) =
+                    forge_url.and_then(|f| forge::commit_url(f, &Oid(id.to_owned())))
+                {
+                    result.push((m.range(), target));
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Return the hyperlinks of the line & the ones detected in its text, ordered by column
+pub fn links(line: &StyledLine<String>) -> Vec<Hyperlink> {
+    if !*ENABLED {
+        return vec![];
+    }
+    let mut result = line.links.clone();
+    let text: String = line.content.iter().map(|c| c.content().as_str()).collect();
+    for (range, url) in detect(&text, FORGE_URL.get()) {
+        let start = text[..range.start].width();
+        let columns = start..start.saturating_add(text[range].width());
+        let overlaps = line
+            .links
+            .iter()
+            .any(|link| link.columns.start < columns.end && columns.start < link.columns.end);
+        if !overlaps {
+            result.push(Hyperlink {
+                columns,
+                url: url.into(),
+            });
+        }
+    }
+    result.sort_by_key(|link| link.columns.start);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::detect;
+    use crate::commit::parse_remote_url;
+
+    #[test]
+    fn detection() {
+        let forge = parse_remote_url("git@github.com:kalkin/git-log-viewer.git");
+        let text = "Fix #12 (see https://example.com/a_(b).), reverts a17989470af & defaced";
+        let links: Vec<(&str, String)> = detect(text, forge.as_ref())
+            .into_iter()
+            .map(|(range, url)| (&text[range], url.into()))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                (
+                    "#12",
+                    "https://github.com/kalkin/git-log-viewer/pull/12".to_owned()
+                ),
+                (
+                    "https://example.com/a_(b)",
+                    "https://example.com/a_(b)".to_owned()
+                ),
+                (
+                    "a17989470af",
+                    "https://github.com/kalkin/git-log-viewer/commit/a17989470af".to_owned()
+                ),
+            ]
+        );
+        let text = "Merge pull request #3, see http://example.com.";
+        let links: Vec<String> = detect(text, None)
+            .into_iter()
+            .map(|(range, _)| text[range].to_owned())
+            .collect();
+        assert_eq!(links, vec!["http://example.com"]);
+        let text = "index 3b18e512dba79e4c8300dd08aeb37f8e728b8dad..0a1b2c3d4e5f60718293a4b5c6d7e8f901234567 100644";
+        assert!(detect(text, forge.as_ref()).is_empty());
+    }
+}
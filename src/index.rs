@@ -0,0 +1,231 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! In-memory index of all commits reachable in a range, including the child history of merges.
+
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+use git_wrapper::Repository;
+
+use crate::cache;
+use crate::commit::{child_history, commits_for_range, decorations, Commit};
+use crate::utils::stable_hash;
+
+/// A commit & its address in the history, see [`crate::ui::base::search::SearchResult`]
+#[derive(Clone)]
+pub struct IndexEntry {
+    pub path: Vec<usize>,
+    pub commit: Commit,
+}
+
+/// All commits of a range in the order they appear in the fully unfolded history
+#[derive(Default)]
+pub struct CommitIndex {
+    entries: Vec<IndexEntry>,
+}
+
+impl Display for CommitIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            let path: Vec<String> = entry.path.iter().map(ToString::to_string).collect();
+            write!(f, "{}{}\x1e", path.join(","), entry.commit.to_record())?;
+        }
+        Ok(())
+    }
+}
+
+impl CommitIndex {
+    /// Walk the range & the child history of all merges. Each first-parent commit is passed
+    /// together with its child history to `on_batch`, as soon as it is indexed.
+    ///
+    /// Returns `None`, when `on_batch` returns `false` to stop building the index.
+    #[must_use]
+    pub fn build(
+        repo: &Repository,
+        range: &[OsString],
+        paths: &[PathBuf],
+        on_batch: &mut impl FnMut(&[IndexEntry]) -> bool,
+    ) -> Option<Self> {
+        let mut result = Self::default();
+        let commits = commits_for_range(repo, &range.to_vec(), paths, None, None);
+        for (i, commit) in commits.into_iter().enumerate() {
+            let first = result.entries.len();
+            result.add(repo, paths, commit, vec![i]);
+            if !on_batch(&result.entries[first..]) {
+                return None;
+            }
+        }
+        Some(result)
+    }
+
+    fn add(&mut self, repo: &Repository, paths: &[PathBuf], commit: Commit, path: Vec<usize>) {
+        let children = commit
+            .is_merge()
+            .then(|| child_history(repo, &commit, paths));
+        self.entries.push(IndexEntry {
+            path: path.clone(),
+            commit,
+        });
+        for (i, child) in children.into_iter().flatten().enumerate() {
+            let mut child_path = path.clone();
+            child_path.push(i);
+            self.add(repo, paths, child, child_path);
+        }
+    }
+
+    /// Parse an index stored via [`CommitIndex::to_string`]. The commits have no references,
+    /// relative dates or signature status, see [`Commit::to_record`].
+    #[must_use]
+    pub fn parse(text: &str) -> Self {
+        let entries = text
+            .split('\x1e')
+            .filter(|record| !record.is_empty())
+            .filter_map(|record| {
+                let (path, _) = record.split_once('\x1f')?;
+                let path = path.split(',').map(str::parse).collect::<Result<_, _>>();
+                match path {
+                    Ok(path) => Some(IndexEntry {
+                        path,
+                        commit: Commit::new(record),
+                    }),
+                    Err(err) => {
+                        log::warn!("Invalid index entry path: {}", err);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// Load the index from the cache, or build & store it, when the tips of the range changed.
+    /// The references of cached commits are read from the repository, because they change
+    /// independently of the range. The entries are passed to `on_batch` while loading, see
+    /// [`CommitIndex::build`].
+    ///
+    /// Returns `None`, when `on_batch` returns `false`. An incomplete index is not stored.
+    #[must_use]
+    pub fn load_or_build(
+        repo: &Repository,
+        range: &[OsString],
+        paths: &[PathBuf],
+        on_batch: &mut impl FnMut(&[IndexEntry]) -> bool,
+    ) -> Option<Self> {
+        let id = index_id(repo, range, paths);
+        if let Some((view, tips)) = &id {
+            match cache::fetch_index(&format!("{}-{}", view, tips)) {
+                Ok(Some(text)) => {
+                    log::debug!("Loaded commit index {}-{}", view, tips);
+                    let mut result = Self::parse(&text);
+                    let decorations = decorations(repo);
+                    for entry in &mut result.entries {
+                        if let Some(names) = decorations.get(entry.commit.id()) {
+                            entry.commit.set_references(names);
+                        }
+                    }
+                    return on_batch(result.entries()).then_some(result);
+                }
+                Ok(None) => {}
+                Err(err) => log::warn!("Failed to load commit index: {}", err),
+            }
+        }
+        let result = Self::build(repo, range, paths, on_batch)?;
+        if let Some((view, tips)) = &id {
+            if let Err(err) = cache::store_index(view, tips, &result.to_string()) {
+                log::warn!("Failed to store commit index: {}", err);
+            }
+        }
+        Some(result)
+    }
+
+    pub fn extend(&mut self, entries: Vec<IndexEntry>) {
+        self.entries.extend(entries);
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+}
+
+/// Return an id for the work tree & paths and one for the tip oids of the range
+fn index_id(repo: &Repository, range: &[OsString], paths: &[PathBuf]) -> Option<(String, String)> {
+    let proc = repo.git().arg("rev-parse").args(range).output().ok()?;
+    if !proc.status.success() {
+        return None;
+    }
+    let work_tree = repo.work_tree().unwrap_or_default();
+    let view = std::iter::once(work_tree.into_os_string())
+        .chain(range.iter().cloned())
+        .chain(std::iter::once(OsString::from("--")))
+        .chain(paths.iter().map(|p| p.clone().into_os_string()));
+    Some((
+        stable_hash(view.map(|p| p.to_string_lossy().into_owned())),
+        stable_hash([proc.stdout]),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use std::ffi::OsString;
+
+    use super::{CommitIndex, IndexEntry};
+    use crate::commit::Commit;
+    use crate::ui::base::test_helpers::TestRepo;
+
+    #[test]
+    fn roundtrip() {
+        let data = "\x1fa17989470af6ab0f9c9ab2d1b2a0d3f4a8e9c123\x1fa179894\x1fb1 c2\x1fHEAD -> main, tag: v1.0, origin/main\x1fAlice\x1falice@example.com\x1f2024-01-15T10:00:00+01:00\x1f2 days ago\x1fBob\x1fbob@example.com\x1f2024-01-16T10:00:00+01:00\x1f1 day ago\x1fMerge feature\x1fSome\nbody\x1fG";
+        let index = CommitIndex {
+            entries: vec![IndexEntry {
+                path: vec![3, 0, 1],
+                commit: Commit::new(data),
+            }],
+        };
+        let parsed = CommitIndex::parse(&index.to_string());
+        assert_eq!(parsed.entries().len(), 1);
+        assert_eq!(parsed.entries()[0].path, vec![3, 0, 1]);
+        let commit = &parsed.entries()[0].commit;
+        assert_eq!(commit.to_record(), Commit::new(data).to_record());
+        assert_eq!(commit.author_email(), "alice@example.com");
+        assert_eq!(commit.committer_date(), "2024-01-16T10:00:00+01:00");
+        assert_eq!(commit.body(), "Some\nbody");
+        assert!(commit.references().is_empty(), "References are not stored");
+        assert_eq!(commit.author_rel_date(), "");
+        assert_eq!(commit.signature(), "");
+    }
+
+    #[test]
+    fn build_stops() {
+        let repo = TestRepo::new("index-stops");
+        repo.commit("c0");
+        repo.commit("c1");
+        let range = vec![OsString::from("HEAD")];
+        let mut batches = 0;
+        let index = CommitIndex::build(&repo, &range, &[], &mut |_| {
+            batches += 1;
+            false
+        });
+        assert!(index.is_none(), "Incomplete index");
+        assert_eq!(batches, 1, "Stopped after the first batch");
+        let index = CommitIndex::build(&repo, &range, &[], &mut |_| true);
+        assert_eq!(index.map(|i| i.entries().len()), Some(2));
+    }
+}
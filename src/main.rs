@@ -21,43 +21,58 @@
 
 use std::ffi::{OsStr, OsString};
 use std::path::PathBuf;
-use std::sync::mpsc;
 use std::thread;
 use std::{env, io};
 
 use clap::{ArgAction, Parser, ValueHint};
-use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 
 use git_wrapper::Repository;
 
 use history_adapter::HistoryAdapter;
-use history_entry::HistoryEntry;
 use memory_logger::blocking::MemoryLogger;
 use ui::base::Drawable;
 
-use crate::detail::DiffView;
-use crate::history_table::TableWidget;
+use crate::events::UiEvent;
+use crate::history_entry::Column;
+use crate::table_layout::TableLayout;
+use crate::tabs::{build_view, Tabs};
 use crate::ui::base::{
-    new_area, render, setup_screen, shutdown_screen, Area, HandleEvent, StyledArea,
+    input_handled, new_area, read_input, setup_screen, shutdown_screen, Area, HandleEvent,
+    Renderer, StyledArea,
 };
-use crate::ui::layouts::SplitLayout;
 use crossterm::ErrorKind;
 use posix_errors::PosixError;
 use std::process::exit;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
+mod actions;
 mod actors;
 #[macro_use]
 mod commit;
 mod cache;
+mod changes;
+mod clipboard;
 mod credentials;
 mod default_styles;
 mod detail;
+mod events;
+mod forge;
+#[cfg(feature = "syntax-highlighting")]
+mod highlight;
 mod history_adapter;
 mod history_entry;
 mod history_table;
+mod hyperlink;
+mod index;
+mod query;
 mod raw;
+mod ref_picker;
+mod refs;
 mod search;
+mod session;
+mod table_layout;
+mod tabs;
 mod ui;
 mod utils;
 
@@ -96,9 +111,13 @@ fn glv(args: Args) -> Result<(), PosixError> {
         parse_rev_paths(&repo, args.revision, &args.paths)?;
     log::info!("Revs  {:?}", revisions);
     log::info!("Paths {:?}", paths);
-    let history_adapter = HistoryAdapter::new(repo.clone(), revisions, paths.clone(), debug)?;
+    let layout = TableLayout::from_repo(&repo);
+    if layout.has(Column::Signature) {
+        commit::verify_signatures();
+    }
+    let history_adapter = HistoryAdapter::new(repo, revisions, paths, debug)?;
 
-    run_ui(history_adapter, repo, paths).map_err(Into::into)
+    run_ui(history_adapter, layout, !args.no_restore).map_err(Into::into)
 }
 
 #[allow(unused_qualifications)]
@@ -153,7 +172,7 @@ where
 
 fn is_valid_rev_spec<S: AsRef<OsStr>>(repo: &Repository, rev: &S) -> bool {
     let mut git = repo.git();
-    git.args(&["rev-parse", "-q"]).arg(rev).arg("--");
+    git.args(["rev-parse", "-q"]).arg(rev).arg("--");
     let proc = git.output().expect("Failed to run rev-parse");
 
     proc.status.success()
@@ -228,21 +247,26 @@ fn main() {
 
 fn run_ui(
     history_adapter: HistoryAdapter,
-    repo: Repository,
-    paths: Vec<PathBuf>,
+    layout: TableLayout,
+    restore: bool,
 ) -> Result<(), ErrorKind> {
-    let root = build_drawable(repo, history_adapter, paths);
-    ui_loop(root)
+    let mut view = build_view(history_adapter, layout.clone());
+    if restore {
+        view.main_mut().restore_session();
+    }
+    let mut root = Tabs::new(view, layout, restore);
+    ui_loop(&mut root)?;
+    root.store_sessions();
+    Ok(())
 }
 
-fn ui_loop(
-    mut drawable: SplitLayout<TableWidget, DiffView, HistoryEntry>,
-) -> Result<(), io::Error> {
-    let (tx, rx) = mpsc::channel::<Event>();
+fn ui_loop(drawable: &mut Tabs) -> Result<(), io::Error> {
+    // Created before the first render, so results arriving after it wake the loop up
+    let (tx, rx) = events::channel();
     {
         thread::spawn(move || {
-            while let Ok(event) = read() {
-                if let Err(err) = tx.send(event) {
+            while let Ok(event) = read_input() {
+                if let Err(err) = tx.send(UiEvent::Input(event)) {
                     log::error!("Error setting up UI event stream:\n{:?}", err);
                 }
             }
@@ -250,46 +274,47 @@ fn ui_loop(
     }
     let mut area = new_area();
     let mut last_rendered = drawable.render(&area);
+    let mut renderer = Renderer::default();
     setup_screen("glv")?;
-    render(&last_rendered, &area)?;
-    // We start with 10ms timeout and bump it up everytime we timeout and rendering doesn't show
-    // any updates. The idea is that every time we render and see no changes we bump the timer up
-    // to 1 second in 100 ms steps.
-    let mut timeout = Duration::from_millis(10);
+    renderer.render(&last_rendered, &area)?;
+    // The loop sleeps until the user does something or a background thread has new data
     loop {
-        match rx.recv_timeout(timeout) {
+        match rx.recv() {
             Ok(event) => {
                 let start = Instant::now();
                 log::debug!(target:"main:ui_loop", "Received Event {:?}", event);
-                if drawable.on_event(&event) == HandleEvent::Ignored {
-                    match event {
-                        Event::Resize(cols, rows) => {
-                            area = Area::new(
-                                cols.try_into().expect("u16 to usize"),
-                                rows.try_into().expect("u16 to usize"),
-                            );
-                        }
-                        Event::Key(KeyEvent {
-                            code: KeyCode::Char('q'),
-                            modifiers: KeyModifiers::NONE,
-                            ..
-                        }) => {
-                            break;
-                        }
-                        _ => {
-                            log::info!(target:"main:ui_loop", "Unexpected event: {:?}", event);
+                match event {
+                    UiEvent::Input(event) => {
+                        let handled = drawable.on_event(&event);
+                        input_handled();
+                        if handled == HandleEvent::Ignored {
+                            match event {
+                                Event::Resize(cols, rows) => {
+                                    area = Area::new(cols.into(), rows.into());
+                                }
+                                Event::Key(KeyEvent {
+                                    code: KeyCode::Char('q'),
+                                    modifiers: KeyModifiers::NONE,
+                                    ..
+                                }) => {
+                                    break;
+                                }
+                                _ => {
+                                    log::info!(target:"main:ui_loop", "Unexpected event: {:?}", event);
+                                }
+                            }
                         }
                     }
+                    // Wake ups during the render trigger another render
+                    UiEvent::Wake => events::woken(),
                 }
                 if area.height() >= 4 && area.width() >= 10 {
                     let new = drawable.render(&area);
-                    if same(&new, &last_rendered) {
+                    if renderer.is_valid() && same(&new, &last_rendered) {
                         log::debug!(target:"main:ui_loop", "Skipping useless rendering calculation");
                     } else {
                         last_rendered = new;
-                        render(&last_rendered, &area)?;
-                        timeout = Duration::from_millis(10);
-                        log::trace!(target:"main:ui_loop", "Set recv timeout to {:?}", timeout);
+                        renderer.render(&last_rendered, &area)?;
                     }
                 } else {
                     log::warn!(target:"main:ui_loop", "target area too small");
@@ -300,29 +325,6 @@ fn ui_loop(
                     log::warn!(target:"main:ui_loop", "Runtime {:?} !", duration);
                 }
             }
-            Err(mpsc::RecvTimeoutError::Timeout) => {
-                let start = Instant::now();
-                let new = drawable.render(&area);
-                #[allow(clippy::else_if_without_else)]
-                if area.height() >= 4 && area.width() >= 10 {
-                    if !same(&new, &last_rendered) {
-                        last_rendered = new;
-                        render(&last_rendered, &area)?;
-                        timeout = Duration::from_millis(10);
-                        log::trace!(target:"main:ui_loop", "Set recv timeout to {:?}", timeout);
-                    } else if Duration::from_millis(1000) > timeout {
-                        timeout = timeout.saturating_add(Duration::from_millis(100));
-                        log::trace!(target:"main:ui_loop","set recv timeout to {:?}", timeout);
-                    }
-                } else {
-                    log::warn!(target:"main:ui_loop","target area too small");
-                }
-
-                let duration = start.elapsed();
-                if duration.as_millis() > 50 {
-                    log::warn!(target:"main:ui_loop", "Runtime {:?} !", duration);
-                }
-            }
             Err(err) => {
                 return Err(io::Error::new(
                     io::ErrorKind::ConnectionAborted,
@@ -355,22 +357,15 @@ struct Args {
     #[clap(last = true, value_hint=ValueHint::AnyPath)]
     paths: Vec<PathBuf>,
 
+    /// Do not restore folding state & selection from the last session
+    #[clap(long)]
+    no_restore: bool,
+
     /// Log level up to -ddd
     #[clap(short, long, action=ArgAction::Count)]
     debug: u8,
 }
 
-fn build_drawable(
-    repo: Repository,
-    history_adapter: HistoryAdapter,
-    paths: Vec<PathBuf>,
-) -> SplitLayout<TableWidget, DiffView, HistoryEntry> {
-    let history_list = { TableWidget::new(history_adapter) };
-    let diff = DiffView::new(repo, paths);
-
-    SplitLayout::new(history_list, diff)
-}
-
 #[cfg(test)]
 mod parse_args {
     use crate::Args;
@@ -378,25 +373,25 @@ mod parse_args {
 
     #[test]
     fn no_arguments() {
-        let _args: Args = Parser::try_parse_from(&["glv"]).expect("No arguments");
+        let _args: Args = Parser::try_parse_from(["glv"]).expect("No arguments");
     }
 
     #[test]
     fn with_ref() {
-        let _args: Args = Parser::try_parse_from(&["glv", "master"]).expect("Ref specified");
+        let _args: Args = Parser::try_parse_from(["glv", "master"]).expect("Ref specified");
     }
 
     #[test]
     fn with_ref_and_path() {
-        let _args1: Args = Parser::try_parse_from(&["glv", "master", "--", "foo/bar"])
+        let _args1: Args = Parser::try_parse_from(["glv", "master", "--", "foo/bar"])
             .expect("Ref and path specified");
-        let _args2: Args = Parser::try_parse_from(&["glv", "master", "--", "foo/bar", "README.md"])
+        let _args2: Args = Parser::try_parse_from(["glv", "master", "--", "foo/bar", "README.md"])
             .expect("Ref and multiple paths specified");
     }
 
     #[test]
     fn no_delim_between_ref_and_path() {
         let _args: Args =
-            Parser::try_parse_from(&["glv", "master", "foo/bar"]).expect("Should accept it");
+            Parser::try_parse_from(["glv", "master", "foo/bar"]).expect("Should accept it");
     }
 }
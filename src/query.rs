@@ -0,0 +1,757 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Search query language.
+//!
+//! A query consists of terms, which are combined with `AND` (also implicit), `OR` (or `|`) and
+//! `NOT` (or a `-`/`!` prefix). Terms can be grouped with parentheses. A term is either a plain
+//! value or a value qualified by a field, i.e. `author:alice`. Values can be:
+//!
+//! * plain text, matching as substring, i.e. `fix`
+//! * quoted text, i.e. `"fix race"`
+//! * regex literals, i.e. `/fix.*race/`
+//! * globs containing `*` or `?`, matching the whole value, i.e. `release-*`
+//!
+//! Values ignore case, unless they contain an upper case character.
+//!
+//! Supported fields are `author`, `committer`, `subject`, `ref`, `sha`, `module`, `type`, `pr`,
+//! `after` & `before`.
+//!
+//! The fields `diff` & `file` search the changes of commits. They need to be resolved via
+//! [`Query::load_changes`] before matching. A `diff:text` term uses `git log -S`, a
+//! `diff:/regex/` term uses `git log -G` and `file:` terms match the paths of changed files.
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::str::Chars;
+
+use git_wrapper::Repository;
+use regex::{Regex, RegexBuilder};
+use subject_classifier::Subject;
+
+use crate::commit::{changed_files, pickaxe, Commit, Oid};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Field {
+    Any,
+    Author,
+    Committer,
+    Subject,
+    Ref,
+    Sha,
+    Module,
+    Type,
+    Pr,
+    After,
+    Before,
+    Diff,
+    File,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "author" => Some(Self::Author),
+            "committer" => Some(Self::Committer),
+            "subject" => Some(Self::Subject),
+            "ref" => Some(Self::Ref),
+            "sha" | "id" => Some(Self::Sha),
+            "module" => Some(Self::Module),
+            "type" => Some(Self::Type),
+            "pr" => Some(Self::Pr),
+            "after" => Some(Self::After),
+            "before" => Some(Self::Before),
+            "diff" => Some(Self::Diff),
+            "file" => Some(Self::File),
+            _ => None,
+        }
+    }
+
+    /// Fields which values are visible as text in the history view
+    const fn is_textual(self) -> bool {
+        !matches!(
+            self,
+            Self::Type | Self::After | Self::Before | Self::Diff | Self::File
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Term {
+    field: Field,
+    value: String,
+    regex: Regex,
+    /// POSIX extended regex of a regex literal or a glob, used by git-log(1) `-G`
+    extended_regex: Option<String>,
+    ignore_case: bool,
+    /// Commits matching a `diff:` or `file:` term
+    changes: HashSet<Oid>,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    All,
+    Term(Term),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+/// A parsed search query
+#[derive(Clone)]
+pub struct Query {
+    source: String,
+    expr: Expr,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl Debug for Query {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Query").field(&self.source).finish()
+    }
+}
+
+impl PartialEq for Query {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for Query {}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<Chars<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+        }
+    }
+
+    /// Read a word until white space or a closing paren. Quoted strings & regex literals may
+    /// contain both.
+    fn word(&mut self) -> String {
+        let mut result = String::new();
+        let mut value_start = true;
+        while let Some(c) = self.chars.peek().copied() {
+            if c.is_whitespace() || c == ')' {
+                break;
+            }
+            self.chars.next();
+            result.push(c);
+            if value_start && (c == '"' || c == '/') {
+                self.delimited(c, &mut result);
+                value_start = false;
+            } else {
+                value_start = c == ':';
+            }
+        }
+        result
+    }
+
+    /// Read until the unescaped `end` character
+    fn delimited(&mut self, end: char, result: &mut String) {
+        let mut escaped = false;
+        for c in self.chars.by_ref() {
+            result.push(c);
+            if c == end && !escaped {
+                break;
+            }
+            escaped = c == '\\' && !escaped;
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let token = match self.chars.peek()? {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '|' => Token::Or,
+            '-' | '!' => Token::Not,
+            _ => {
+                return Some(match self.word().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    word => Token::Word(word.to_owned()),
+                })
+            }
+        };
+        self.chars.next();
+        Some(token)
+    }
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn or(&mut self) -> Expr {
+        let mut parts = vec![self.and()];
+        while self.tokens.next_if_eq(&Token::Or).is_some() {
+            parts.push(self.and());
+        }
+        if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Expr::Or(parts)
+        }
+    }
+
+    fn and(&mut self) -> Expr {
+        let mut parts = vec![];
+        loop {
+            match self.tokens.peek() {
+                None | Some(Token::Or | Token::Close) => break,
+                Some(Token::And) => {
+                    self.tokens.next();
+                }
+                Some(_) => parts.push(self.unary()),
+            }
+        }
+        match parts.len() {
+            0 => Expr::All,
+            1 => parts.remove(0),
+            _ => Expr::And(parts),
+        }
+    }
+
+    fn unary(&mut self) -> Expr {
+        match self.tokens.next() {
+            Some(Token::Not) => Expr::Not(Box::new(self.unary())),
+            Some(Token::Open) => {
+                let result = self.or();
+                self.tokens.next_if_eq(&Token::Close);
+                result
+            }
+            Some(Token::Word(word)) => Expr::Term(self.term(&word)),
+            _ => Expr::All,
+        }
+    }
+
+    fn term(&self, word: &str) -> Term {
+        let (field, value) = match word.split_once(':') {
+            Some((name, value)) => {
+                Field::from_name(name).map_or((Field::Any, word), |f| (f, value))
+            }
+            None => (Field::Any, word),
+        };
+        let mut extended_regex = None;
+        let (pattern, value) = if let Some(literal) = value.strip_prefix('"') {
+            let literal = literal.strip_suffix('"').unwrap_or(literal);
+            (regex::escape(literal), literal.to_owned())
+        } else if let Some(literal) = value.strip_prefix('/').filter(|v| !v.is_empty()) {
+            let literal = literal.strip_suffix('/').unwrap_or(literal);
+            extended_regex = Some(literal.to_owned());
+            (literal.to_owned(), literal.to_owned())
+        } else if value.contains(['*', '?']) {
+            extended_regex = Some(glob_to_extended_regex(value));
+            (glob_to_regex(value), value.to_owned())
+        } else {
+            let pattern = match field {
+                Field::Sha => format!("^{}", regex::escape(value)),
+                Field::Type | Field::Pr => {
+                    format!("^{}$", regex::escape(value.trim_start_matches('#')))
+                }
+                _ => regex::escape(value),
+            };
+            (pattern, value.to_owned())
+        };
+        // Smart case only looks at the value, not at field names or operators
+        let ignore_case = !value.chars().any(char::is_uppercase);
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .unwrap_or_else(|e| {
+                log::info!("Invalid regex «{}», searching literally: {}", pattern, e);
+                RegexBuilder::new(&regex::escape(&value))
+                    .case_insensitive(ignore_case)
+                    .build()
+                    .expect("Escaped regex")
+            });
+        Term {
+            field,
+            value,
+            regex,
+            extended_regex,
+            ignore_case,
+            changes: HashSet::new(),
+        }
+    }
+}
+
+/// Like [`glob_to_regex`], but for the POSIX extended regex syntax of git
+fn glob_to_extended_regex(glob: &str) -> String {
+    let mut result = "^".to_owned();
+    for c in glob.chars() {
+        match c {
+            '*' => result.push_str(".*"),
+            '?' => result.push('.'),
+            '\\' | '.' | '[' | ']' | '(' | ')' | '{' | '}' | '+' | '|' | '^' | '$' => {
+                result.push('\\');
+                result.push(c);
+            }
+            c => result.push(c),
+        }
+    }
+    result.push('$');
+    result
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut result = "^".to_owned();
+    for c in glob.chars() {
+        match c {
+            '*' => result.push_str(".*"),
+            '?' => result.push('.'),
+            c => result.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    result.push('$');
+    result
+}
+
+/// Return the conventional commit type or the kind of subject
+fn subject_type(subject: &Subject) -> String {
+    match subject {
+        Subject::ConventionalCommit { category, .. } => format!("{:?}", category).to_lowercase(),
+        Subject::Fixup(_) => "fixup".to_owned(),
+        Subject::PullRequest { .. } => "pr".to_owned(),
+        Subject::Release { .. } => "release".to_owned(),
+        Subject::Remove(_) => "remove".to_owned(),
+        Subject::Rename(_) => "rename".to_owned(),
+        Subject::Revert(_) => "revert".to_owned(),
+        Subject::SubtreeCommit { .. } => "subtree".to_owned(),
+        Subject::Simple(_) => "simple".to_owned(),
+    }
+}
+
+impl Term {
+    fn matches(&self, commit: &Commit, modules: &mut dyn FnMut(&Commit) -> Vec<String>) -> bool {
+        let is_match = |text: &str| self.regex.is_match(text);
+        match self.field {
+            Field::Any => {
+                [
+                    commit.author_name(),
+                    commit.author_email(),
+                    commit.committer_name(),
+                    commit.committer_email(),
+                    commit.short_id(),
+                    &commit.id().0,
+                    commit.subject(),
+                ]
+                .into_iter()
+                .any(|t| is_match(t))
+                    || commit.references().iter().any(|r| is_match(&r.0))
+            }
+            Field::Author => is_match(commit.author_name()) || is_match(commit.author_email()),
+            Field::Committer => {
+                is_match(commit.committer_name()) || is_match(commit.committer_email())
+            }
+            Field::Subject => is_match(commit.subject()),
+            Field::Ref => commit.references().iter().any(|r| {
+                is_match(&r.0) || r.0.split_once('/').is_some_and(|(_, name)| is_match(name))
+            }),
+            Field::Sha => is_match(&commit.id().0),
+            Field::Module => modules(commit).iter().any(|m| is_match(m)),
+            Field::Type => is_match(&subject_type(&Subject::from(commit.subject().as_str()))),
+            Field::Pr => match Subject::from(commit.subject().as_str()) {
+                Subject::PullRequest { id, .. } => is_match(id.trim_start_matches('#')),
+                _ => false,
+            },
+            Field::After => date_prefix(commit, &self.value) >= self.value.as_str(),
+            Field::Before => date_prefix(commit, &self.value) < self.value.as_str(),
+            Field::Diff | Field::File => self.changes.contains(commit.id()),
+        }
+    }
+
+    /// Check if a line of text matches. Returns `None` for fields which are not visible as
+    /// text, because they can not be checked.
+    fn matches_text(&self, text: &str) -> Option<bool> {
+        self.field.is_textual().then(|| self.regex.is_match(text))
+    }
+
+    /// Return the `git log` pickaxe argument for a `diff:` term. Case is ignored via
+    /// `--regexp-ignore-case`, see [`Query::load_changes`].
+    fn pickaxe_arg(&self) -> String {
+        match &self.extended_regex {
+            Some(pattern) => format!("-G{}", pattern),
+            None => format!("-S{}", self.value),
+        }
+    }
+}
+
+/// Return the prefix of the committer date with the same length as `value`, so `2024` or
+/// `2024-01` compare as expected.
+fn date_prefix<'a>(commit: &'a Commit, value: &str) -> &'a str {
+    let date = commit.committer_date();
+    date.get(..value.len()).unwrap_or(date)
+}
+
+impl Expr {
+    fn matches(&self, commit: &Commit, modules: &mut dyn FnMut(&Commit) -> Vec<String>) -> bool {
+        match self {
+            Self::All => true,
+            Self::Term(term) => term.matches(commit, modules),
+            Self::Not(expr) => !expr.matches(commit, modules),
+            Self::And(parts) => parts.iter().all(|e| e.matches(commit, modules)),
+            Self::Or(parts) => parts.iter().any(|e| e.matches(commit, modules)),
+        }
+    }
+
+    /// Returns `None` if the expression has no textual terms, so it neither includes nor
+    /// excludes the text
+    fn matches_text(&self, text: &str) -> Option<bool> {
+        match self {
+            Self::All => None,
+            Self::Term(term) => term.matches_text(text),
+            Self::Not(expr) => expr.matches_text(text).map(|m| !m),
+            Self::And(parts) => parts
+                .iter()
+                .filter_map(|e| e.matches_text(text))
+                .reduce(|a, b| a && b),
+            Self::Or(parts) => parts
+                .iter()
+                .filter_map(|e| e.matches_text(text))
+                .reduce(|a, b| a || b),
+        }
+    }
+
+    fn ignores_case(&self) -> bool {
+        match self {
+            Self::All => true,
+            Self::Term(term) => term.ignore_case,
+            Self::Not(expr) => expr.ignores_case(),
+            Self::And(parts) | Self::Or(parts) => parts.iter().all(Self::ignores_case),
+        }
+    }
+
+    fn positive_terms<'a>(&'a self, result: &mut Vec<&'a Term>) {
+        match self {
+            Self::All | Self::Not(_) => {}
+            Self::Term(term) => result.push(term),
+            Self::And(parts) | Self::Or(parts) => {
+                for e in parts {
+                    e.positive_terms(result);
+                }
+            }
+        }
+    }
+
+    fn terms_mut(&mut self, f: &mut dyn FnMut(&mut Term)) {
+        match self {
+            Self::All => {}
+            Self::Term(term) => f(term),
+            Self::Not(expr) => expr.terms_mut(f),
+            Self::And(parts) | Self::Or(parts) => {
+                for e in parts {
+                    e.terms_mut(f);
+                }
+            }
+        }
+    }
+
+    fn uses(&self, field: Field) -> bool {
+        match self {
+            Self::All => false,
+            Self::Term(term) => term.field == field,
+            Self::Not(expr) => expr.uses(field),
+            Self::And(parts) | Self::Or(parts) => parts.iter().any(|e| e.uses(field)),
+        }
+    }
+}
+
+impl Query {
+    /// Parse the query. Terms ignore case, unless their value contains an upper case character.
+    #[must_use]
+    pub fn parse(text: &str) -> Self {
+        let mut parser = Parser {
+            tokens: Lexer::new(text).collect::<Vec<_>>().into_iter().peekable(),
+        };
+        let mut parts = vec![parser.or()];
+        // Skip unbalanced closing parens
+        while parser.tokens.next().is_some() {
+            parts.push(parser.or());
+        }
+        let expr = if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Expr::And(parts)
+        };
+        Self {
+            source: text.to_owned(),
+            expr,
+        }
+    }
+
+    /// Return `true` if the query has no terms
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        matches!(self.expr, Expr::All)
+    }
+
+    /// Return `true` if no term of the query is case sensitive
+    #[must_use]
+    pub fn ignores_case(&self) -> bool {
+        self.expr.ignores_case()
+    }
+
+    /// Return `true` if the query contains a term for the field
+    #[must_use]
+    pub fn uses(&self, field: Field) -> bool {
+        self.expr.uses(field)
+    }
+
+    /// Resolve the `diff:` & `file:` terms to the matching commits of the range via git-log(1)
+    pub fn load_changes(&mut self, repo: &Repository, range: &[OsString], paths: &[PathBuf]) {
+        let mut files = None;
+        self.expr.terms_mut(&mut |term| match term.field {
+            Field::Diff => {
+                let mut args = vec![term.pickaxe_arg()];
+                if term.ignore_case {
+                    args.push("--regexp-ignore-case".to_owned());
+                }
+                term.changes = pickaxe(repo, range, paths, &args);
+            }
+            Field::File => {
+                let files = files.get_or_insert_with(|| changed_files(repo, range, paths));
+                term.changes = files
+                    .iter()
+                    .filter(|(_, names)| names.iter().any(|n| term.regex.is_match(n)))
+                    .map(|(oid, _)| oid.clone())
+                    .collect();
+            }
+            _ => {}
+        });
+    }
+
+    /// Check if the commit matches the query. The `modules` function is only called for
+    /// queries containing `module:` terms.
+    pub fn matches(
+        &self,
+        commit: &Commit,
+        modules: &mut dyn FnMut(&Commit) -> Vec<String>,
+    ) -> bool {
+        !self.is_empty() && self.expr.matches(commit, modules)
+    }
+
+    /// Check if a line of text, i.e. of a diff, matches the query. Terms of fields, which are
+    /// not visible as text, are ignored. A query without textual terms matches no line.
+    #[must_use]
+    pub fn matches_text(&self, text: &str) -> bool {
+        self.expr.matches_text(text).unwrap_or(false)
+    }
+
+    /// Return the sorted, non overlapping byte ranges of `text` matched by the not negated
+    /// terms of the query
+    #[must_use]
+    pub fn find_in(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut terms = vec![];
+        self.expr.positive_terms(&mut terms);
+        let mut ranges: Vec<(usize, usize)> = vec![];
+        for term in terms.into_iter().filter(|t| t.field.is_textual()) {
+            ranges.extend(
+                term.regex
+                    .find_iter(text)
+                    .filter(|m| !m.as_str().is_empty())
+                    .map(|m| (m.start(), m.end())),
+            );
+        }
+        ranges.sort_unstable();
+        let mut result: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match result.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => result.push((start, end)),
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{Field, Query};
+    use crate::commit::Commit;
+
+    fn commit(subject: &str, references: &str) -> Commit {
+        let data = format!(
+            "\x1fa17989470af6ab0f9c9ab2d1b2a0d3f4a8e9c123\x1fa179894\x1f\x1f{}\x1fAlice Doe\x1falice@example.com\x1f2024-01-15T10:00:00+01:00\x1f2 days ago\x1fBob\x1fbob@example.com\x1f2024-01-16T10:00:00+01:00\x1f1 day ago\x1f{}\x1f",
+            references, subject
+        );
+        Commit::new(&data)
+    }
+
+    fn no_modules(_: &Commit) -> Vec<String> {
+        vec![]
+    }
+
+    fn matches(query: &str, commit: &Commit) -> bool {
+        Query::parse(query).matches(commit, &mut no_modules)
+    }
+
+    #[test]
+    fn plain_text() {
+        let c = commit("fix: race in watcher", "");
+        assert!(matches("race", &c));
+        assert!(matches("alice", &c));
+        assert!(!matches("bob-the-builder", &c));
+        assert!(!Query::parse("Race").matches(&c, &mut no_modules));
+    }
+
+    #[test]
+    fn fields() {
+        let c = commit("feat(ui): Add foo", "tag: release-1.0, origin/main");
+        assert!(matches("author:alice", &c));
+        assert!(!matches("committer:alice", &c));
+        assert!(matches("subject:/add.*foo/", &c));
+        assert!(matches("ref:release-*", &c));
+        assert!(matches("ref:main", &c));
+        assert!(!matches("ref:nope", &c));
+        assert!(matches("sha:a1798", &c));
+        assert!(!matches("sha:79894", &c));
+        assert!(matches("type:feat", &c));
+        assert!(!matches("type:fix", &c));
+        assert!(matches("after:2024-01-01 before:2024-02", &c));
+        assert!(!matches("after:2024-01-17", &c));
+    }
+
+    #[test]
+    fn pull_requests_and_modules() {
+        let c = commit("Merge pull request #123 from foo/bar", "");
+        assert!(matches("pr:123", &c));
+        assert!(matches("pr:#123", &c));
+        assert!(!matches("pr:12", &c));
+        let query = Query::parse("module:foo");
+        assert!(query.matches(&c, &mut |_| vec!["foo".to_owned()]));
+        assert!(!query.matches(&c, &mut no_modules));
+    }
+
+    #[test]
+    fn boolean_operators() {
+        let c = commit("fix: race in watcher", "");
+        assert!(matches("race watcher", &c));
+        assert!(!matches("race AND nope", &c));
+        assert!(matches("nope OR race", &c));
+        assert!(matches("nope | race", &c));
+        assert!(!matches("NOT race", &c));
+        assert!(matches("-nope", &c));
+        assert!(matches("(nope OR race) !author:bob", &c));
+        assert!(!matches("(nope OR race) author:bob", &c));
+        assert!(matches("subject:\"race in\"", &c));
+    }
+
+    #[test]
+    fn incomplete_queries() {
+        let c = commit("fix: race (in) watcher", "");
+        assert!(matches("subject:/race", &c), "Unterminated regex");
+        assert!(matches("subject:/(in/", &c), "Invalid regex is literal");
+        assert!(matches("(race", &c), "Missing closing paren");
+        assert!(matches("race)", &c), "Stray closing paren");
+        assert!(!matches("", &c), "Empty query matches nothing");
+    }
+
+    #[test]
+    fn pickaxe_arguments() {
+        let query = Query::parse("diff:foo diff:/Fo+/ diff:\"a b\" diff:a.b* file:*.rs");
+        let mut terms = vec![];
+        query.expr.positive_terms(&mut terms);
+        let args: Vec<_> = terms.iter().take(4).map(|t| t.pickaxe_arg()).collect();
+        assert_eq!(args, vec!["-Sfoo", "-GFo+", "-Sa b", "-G^a\\.b.*$"]);
+        assert!(query.uses(Field::File));
+        let c = commit("fix: foo", "");
+        assert!(
+            !matches("diff:foo", &c),
+            "Unresolved diff terms match nothing"
+        );
+    }
+
+    #[test]
+    fn find_in() {
+        let query = Query::parse("foo subject:/b.r/ -baz type:feat");
+        assert_eq!(query.find_in("Foo bar baz"), vec![(0, 3), (4, 7)]);
+        let query = Query::parse("oob foo");
+        assert_eq!(
+            query.find_in("foobar"),
+            vec![(0, 4)],
+            "Merge overlapping ranges"
+        );
+    }
+
+    #[test]
+    fn matches_text() {
+        let query = Query::parse("foo -bar");
+        assert!(query.matches_text("a foo"));
+        assert!(
+            !query.matches_text("foo bar"),
+            "Negated terms exclude lines"
+        );
+        let query = Query::parse("a b");
+        assert!(!query.matches_text("only a"), "All terms have to match");
+        assert!(query.matches_text("a & b"));
+        let query = Query::parse("foo type:feat");
+        assert!(query.matches_text("foo"), "Non-textual terms are ignored");
+        let query = Query::parse("foo -type:feat");
+        assert!(
+            query.matches_text("foo"),
+            "Negated non-textual terms are ignored"
+        );
+        let query = Query::parse("foo NOT author:x");
+        assert!(query.matches_text("a foo"));
+        assert!(!query.matches_text("bar"));
+        assert!(!Query::parse("type:feat").matches_text("foo"));
+        assert!(!Query::parse("").matches_text("foo"));
+    }
+
+    #[test]
+    fn smart_case() {
+        let c = commit("fix: race in watcher", "");
+        let query = Query::parse("race AND watcher");
+        assert!(
+            query.ignores_case(),
+            "Operators do not make the query case sensitive"
+        );
+        assert!(query.matches(&c, &mut no_modules));
+        let query = Query::parse("RACE OR subject:watcher");
+        assert!(!query.ignores_case());
+        assert!(
+            query.matches(&c, &mut no_modules),
+            "Only RACE is case sensitive"
+        );
+    }
+}
@@ -15,21 +15,111 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::ui::base::{StyledArea, StyledLine};
+//! Parses terminal output with SGR escape sequences, i.e. of `git diff --color` or `delta(1)`,
+//! into styled lines.
+
+use crate::ui::base::{Hyperlink, StyledArea, StyledLine};
 use crossterm::style::{Attribute, Color, ContentStyle, StyledContent};
+use unicode_width::UnicodeWidthChar;
+
+const TAB_WIDTH: usize = 8;
+
+/// Replace the tabs in `text` by spaces up to the next tab stop. `column` is the display width
+/// of the line before `text` & is advanced by the width of `text`.
+#[cfg(feature = "syntax-highlighting")]
+pub fn expand_tabs(text: &str, column: &mut usize) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\t' {
+            let spaces = TAB_WIDTH - *column % TAB_WIDTH;
+            result.extend(std::iter::repeat_n(' ', spaces));
+            *column = column.saturating_add(spaces);
+        } else {
+            result.push(c);
+            *column = column.saturating_add(c.width().unwrap_or_default());
+        }
+    }
+    result
+}
+
+const ANSI_COLORS: [Color; 8] = [
+    Color::Black,
+    Color::DarkRed,
+    Color::DarkGreen,
+    Color::DarkYellow,
+    Color::DarkBlue,
+    Color::DarkMagenta,
+    Color::DarkCyan,
+    Color::Grey,
+];
+
+const BRIGHT_COLORS: [Color; 8] = [
+    Color::DarkGrey,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+];
+
+const UNDERLINES: [Attribute; 5] = [
+    Attribute::Underlined,
+    Attribute::DoubleUnderlined,
+    Attribute::Undercurled,
+    Attribute::Underdotted,
+    Attribute::Underdashed,
+];
 
 struct Counter {
     style: ContentStyle,
     buf: String,
+    /// Display width of the current line, needed to expand tabs
+    column: usize,
+    /// Start column & target of the current OSC 8 hyperlink
+    link: Option<(usize, String)>,
     lines: StyledArea<String>,
     cur_line: StyledLine<String>,
 }
 
+/// Parse the extended color of `38`, `48` or `58`.
+///
+/// Supports the semicolon form `38;5;n` & `38;2;r;g;b`, where the arguments are the following
+/// params, as well as the colon form `38:5:n`, `38:2:r:g:b` & `38:2:id:r:g:b`.
+fn extended_color<'a>(param: &[u16], rest: &mut impl Iterator<Item = &'a [u16]>) -> Option<Color> {
+    let to_u8 = |value: Option<&u16>| value.and_then(|v| u8::try_from(*v).ok());
+    if param.len() > 1 {
+        return match (param[1], param.len()) {
+            (5, _) => to_u8(param.get(2)).map(Color::AnsiValue),
+            (2, 5) => Some(Color::from((
+                to_u8(param.get(2))?,
+                to_u8(param.get(3))?,
+                to_u8(param.get(4))?,
+            ))),
+            (2, _) => Some(Color::from((
+                to_u8(param.get(3))?,
+                to_u8(param.get(4))?,
+                to_u8(param.get(5))?,
+            ))),
+            _ => None,
+        };
+    }
+    let mut next = || to_u8(rest.next().and_then(|p| p.first()));
+    match next()? {
+        5 => next().map(Color::AnsiValue),
+        2 => Some(Color::from((next()?, next()?, next()?))),
+        _ => None,
+    }
+}
+
 impl Counter {
     pub fn new() -> Self {
         Self {
             style: ContentStyle::default(),
             buf: String::new(),
+            column: 0,
+            link: None,
             lines: vec![],
             cur_line: StyledLine::empty(),
         }
@@ -43,125 +133,145 @@ impl Counter {
             self.buf = String::new();
         }
     }
+
+    fn new_line(&mut self) {
+        self.save_cur_span();
+        let url = self.close_link();
+        self.lines
+            .push(std::mem::replace(&mut self.cur_line, StyledLine::empty()));
+        self.column = 0;
+        // The hyperlink continues on the next line
+        self.link = url.map(|url| (0, url));
+    }
+
+    /// End the current hyperlink & return its target
+    fn close_link(&mut self) -> Option<String> {
+        let (start, url) = self.link.take()?;
+        if start < self.column {
+            self.cur_line.links.push(Hyperlink {
+                columns: start..self.column,
+                url: url.clone(),
+            });
+        }
+        Some(url)
+    }
+
+    /// Apply the params of a Select Graphic Rendition sequence
+    fn select_graphic_rendition(&mut self, params: &vte::Params) {
+        let style = &mut self.style;
+        let mut iter = params.iter();
+        while let Some(param) = iter.next() {
+            match param[0] {
+                0 => *style = ContentStyle::new(),
+                1 => style.attributes.set(Attribute::Bold),
+                2 => style.attributes.set(Attribute::Dim),
+                3 => style.attributes.set(Attribute::Italic),
+                // `4:0` disables the underline, `4:1`…`4:5` select its style
+                4 => {
+                    for underline in UNDERLINES {
+                        style.attributes.unset(underline);
+                    }
+                    let kind = param.get(1).copied().unwrap_or(1);
+                    if let Some(underline) = usize::from(kind)
+                        .checked_sub(1)
+                        .and_then(|i| UNDERLINES.get(i))
+                    {
+                        style.attributes.set(*underline);
+                    }
+                }
+                5 => style.attributes.set(Attribute::SlowBlink),
+                6 => style.attributes.set(Attribute::RapidBlink),
+                7 => style.attributes.set(Attribute::Reverse),
+                8 => style.attributes.set(Attribute::Hidden),
+                9 => style.attributes.set(Attribute::CrossedOut),
+                21 => style.attributes.set(Attribute::DoubleUnderlined),
+                22 => {
+                    style.attributes.unset(Attribute::Bold);
+                    style.attributes.unset(Attribute::Dim);
+                }
+                23 => style.attributes.unset(Attribute::Italic),
+                24 => {
+                    for underline in UNDERLINES {
+                        style.attributes.unset(underline);
+                    }
+                }
+                25 => {
+                    style.attributes.unset(Attribute::SlowBlink);
+                    style.attributes.unset(Attribute::RapidBlink);
+                }
+                27 => style.attributes.unset(Attribute::Reverse),
+                28 => style.attributes.unset(Attribute::Hidden),
+                29 => style.attributes.unset(Attribute::CrossedOut),
+                n @ 30..=37 => style.foreground_color = Some(ANSI_COLORS[usize::from(n % 10)]),
+                38 => match extended_color(param, &mut iter) {
+                    Some(color) => style.foreground_color = Some(color),
+                    None => log::warn!("Unexpected SGR foreground color {:?}", param),
+                },
+                39 => style.foreground_color = None,
+                n @ 40..=47 => style.background_color = Some(ANSI_COLORS[usize::from(n % 10)]),
+                48 => match extended_color(param, &mut iter) {
+                    Some(color) => style.background_color = Some(color),
+                    None => log::warn!("Unexpected SGR background color {:?}", param),
+                },
+                49 => style.background_color = None,
+                53 => style.attributes.set(Attribute::OverLined),
+                55 => style.attributes.unset(Attribute::OverLined),
+                58 => match extended_color(param, &mut iter) {
+                    Some(color) => style.underline_color = Some(color),
+                    None => log::warn!("Unexpected SGR underline color {:?}", param),
+                },
+                59 => style.underline_color = None,
+                n @ 90..=97 => {
+                    style.foreground_color = Some(BRIGHT_COLORS[usize::from(n % 10)]);
+                }
+                n @ 100..=107 => {
+                    style.background_color = Some(BRIGHT_COLORS[usize::from(n % 10)]);
+                }
+                x => log::warn!("Unexpected SGR value {:?}", x),
+            }
+        }
+    }
 }
 
 impl vte::Perform for Counter {
     fn print(&mut self, c: char) {
         self.buf.push(c);
+        self.column = self.column.saturating_add(c.width().unwrap_or_default());
     }
+
     fn execute(&mut self, byte: u8) {
-        if byte == 10 {
-            self.save_cur_span();
-            self.lines.push(self.cur_line.clone());
-            self.cur_line = StyledLine::empty();
-        } else {
-            self.buf.push(byte.try_into().expect("u8 to char"));
+        match byte {
+            b'\n' => self.new_line(),
+            b'\t' => {
+                let spaces = TAB_WIDTH - self.column % TAB_WIDTH;
+                self.buf.extend(std::iter::repeat_n(' ', spaces));
+                self.column = self.column.saturating_add(spaces);
+            }
+            // Other control characters, i.e. `\r` of CRLF line endings, have no width
+            _ => {}
         }
     }
 
     fn hook(&mut self, _params: &vte::Params, _intermediates: &[u8], _ignore: bool, _c: char) {}
 
-    fn osc_dispatch(&mut self, _params: &[&[u8]], _bell_terminated: bool) {}
-
-    fn csi_dispatch(
-        &mut self,
-        params: &vte::Params,
-        _intermediates: &[u8],
-        _ignore: bool,
-        _c: char,
-    ) {
-        self.save_cur_span();
-        let mut iter = params.iter();
-        while let Some(byte) = iter.next() {
-            // TODO not sure if all colors match
-            match byte[0] {
-                0 => self.style = ContentStyle::new(),
-                1 => self.style.attributes.set(Attribute::Bold),
-                2 => self.style.attributes.set(Attribute::Italic),
-                4 => self.style.attributes.set(Attribute::Underlined),
-                5 => self.style.attributes.set(Attribute::SlowBlink),
-                7 => self.style.attributes.set(Attribute::Reverse),
-
-                30 => self.style.foreground_color = Some(Color::Black),
-                31 => self.style.foreground_color = Some(Color::DarkRed),
-                32 => self.style.foreground_color = Some(Color::DarkGreen),
-                33 => self.style.foreground_color = Some(Color::DarkYellow),
-                34 => self.style.foreground_color = Some(Color::DarkBlue),
-                35 => self.style.foreground_color = Some(Color::DarkMagenta),
-                36 => self.style.foreground_color = Some(Color::DarkCyan),
-                37 => self.style.foreground_color = Some(Color::Grey),
-
-                38 => {
-                    let kind = iter.next().unwrap()[0];
-                    match kind {
-                        5 => {
-                            #[allow(clippy::cast_possible_truncation)]
-                            let color = iter.next().unwrap()[0].try_into().expect("usize to u8");
-                            self.style.foreground_color = Some(Color::AnsiValue(color));
-                        }
-                        2 => {
-                            #[allow(clippy::cast_possible_truncation)]
-                            let r = iter.next().unwrap()[0].try_into().expect("usize to u8");
-                            #[allow(clippy::cast_possible_truncation)]
-                            let g = iter.next().unwrap()[0].try_into().expect("usize to u8");
-                            #[allow(clippy::cast_possible_truncation)]
-                            let b = iter.next().unwrap()[0].try_into().expect("usize to u8");
-                            self.style.foreground_color = Some(Color::from((r, g, b)));
-                        }
-                        x => log::warn!("Unexpected CSI value 38;{:?}", x),
-                    }
-                }
-
-                40 => self.style.background_color = Some(Color::Black),
-                41 => self.style.background_color = Some(Color::DarkRed),
-                42 => self.style.background_color = Some(Color::DarkGreen),
-                43 => self.style.background_color = Some(Color::DarkYellow),
-                44 => self.style.background_color = Some(Color::DarkBlue),
-                45 => self.style.background_color = Some(Color::DarkMagenta),
-                46 => self.style.background_color = Some(Color::DarkCyan),
-                47 => self.style.background_color = Some(Color::Grey),
-
-                48 => {
-                    let kind = iter.next().unwrap()[0];
-                    match kind {
-                        5 => {
-                            #[allow(clippy::cast_possible_truncation)]
-                            let color = iter.next().unwrap()[0].try_into().expect("usize to u8");
-                            self.style.background_color = Some(Color::AnsiValue(color));
-                        }
-                        2 => {
-                            #[allow(clippy::cast_possible_truncation)]
-                            let r = iter.next().unwrap()[0].try_into().expect("usize to u8");
-                            #[allow(clippy::cast_possible_truncation)]
-                            let g = iter.next().unwrap()[0].try_into().expect("usize to u8");
-                            #[allow(clippy::cast_possible_truncation)]
-                            let b = iter.next().unwrap()[0].try_into().expect("usize to u8");
-                            self.style.background_color = Some(Color::from((r, g, b)));
-                        }
-                        x => log::warn!("Unexpected CS value 48;{:?}", x),
-                    }
-                }
+    /// Handles OSC 8 hyperlinks, `ESC ] 8 ; params ; url ST`. An empty url ends the link.
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        if params.first() != Some(&&b"8"[..]) {
+            return;
+        }
+        self.close_link();
+        // The url may contain semicolons
+        let url = params.get(2..).unwrap_or_default().join(&b';');
+        if !url.is_empty() {
+            self.link = Some((self.column, String::from_utf8_lossy(&url).into_owned()));
+        }
+    }
 
-                90 => self.style.foreground_color = Some(Color::DarkGrey),
-                91 => self.style.foreground_color = Some(Color::Red),
-                92 => self.style.foreground_color = Some(Color::Green),
-                93 => self.style.foreground_color = Some(Color::Yellow),
-                94 => self.style.foreground_color = Some(Color::Blue),
-                95 => self.style.foreground_color = Some(Color::Magenta),
-                96 => self.style.foreground_color = Some(Color::Cyan),
-                97 => self.style.foreground_color = Some(Color::White),
-
-                100 => self.style.background_color = Some(Color::DarkGrey),
-                101 => self.style.background_color = Some(Color::Red),
-                102 => self.style.background_color = Some(Color::Green),
-                103 => self.style.background_color = Some(Color::Yellow),
-                104 => self.style.background_color = Some(Color::Blue),
-                105 => self.style.background_color = Some(Color::Magenta),
-                106 => self.style.background_color = Some(Color::Cyan),
-                107 => self.style.background_color = Some(Color::White),
-
-                x => log::warn!("Unexpected CSI value {:?}", x),
-            }
+    fn csi_dispatch(&mut self, params: &vte::Params, intermediates: &[u8], _ignore: bool, c: char) {
+        // Other sequences, i.e. erase in line, do not change the style
+        if c == 'm' && intermediates.is_empty() {
+            self.save_cur_span();
+            self.select_graphic_rendition(params);
         }
     }
 
@@ -174,5 +284,209 @@ pub fn parse_spans(vec: Vec<u8>) -> Vec<StyledLine<String>> {
     for u in vec {
         statemachine.advance(&mut performer, u);
     }
+    if !performer.buf.is_empty() || !performer.cur_line.content.is_empty() {
+        performer.new_line();
+    }
     performer.lines
 }
+
+#[cfg(test)]
+mod test {
+    use crossterm::style::{Attribute, Color, ContentStyle, StyledContent};
+    use pretty_assertions::assert_eq;
+
+    use super::parse_spans;
+    use crate::ui::base::{Hyperlink, StyledLine};
+
+    fn texts(lines: &[StyledLine<String>]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.content.iter().map(|c| c.content().as_str()).collect())
+            .collect()
+    }
+
+    fn span<'a>(line: &'a StyledLine<String>, text: &str) -> &'a StyledContent<String> {
+        line.content
+            .iter()
+            .find(|c| c.content() == text)
+            .unwrap_or_else(|| panic!("No span {:?} in {:?}", text, line))
+    }
+
+    /// Parses the recorded output & compares the text with the uncolored output
+    fn conformance(ansi: &[u8], text: &str) -> Vec<StyledLine<String>> {
+        let lines = parse_spans(ansi.to_vec());
+        assert_eq!(texts(&lines), text.lines().collect::<Vec<_>>());
+        lines
+    }
+
+    #[test]
+    fn git_diff() {
+        let lines = conformance(
+            include_bytes!("../tests/fixtures/git-diff.ansi"),
+            include_str!("../tests/fixtures/git-diff.txt"),
+        );
+        assert!(lines[0].content[0].style().attributes.has(Attribute::Bold));
+        assert_eq!(
+            lines[4].content[0].style().foreground_color,
+            Some(Color::DarkCyan)
+        );
+        assert_eq!(
+            span(&lines[5], "-fn helper() -> u8 {")
+                .style()
+                .foreground_color,
+            Some(Color::DarkRed)
+        );
+        let whitespace_error = span(&lines[13], "  ").style();
+        assert_eq!(whitespace_error.background_color, Some(Color::DarkRed));
+        assert_eq!(whitespace_error.foreground_color, None);
+        assert_eq!(span(&lines[7], "     42 ").style(), &ContentStyle::new());
+    }
+
+    #[test]
+    fn git_word_diff() {
+        let lines = conformance(
+            include_bytes!("../tests/fixtures/git-word-diff.ansi"),
+            include_str!("../tests/fixtures/git-word-diff.txt"),
+        );
+        let meta = lines[0].content[0].style();
+        assert_eq!(meta.foreground_color, Some(Color::DarkYellow));
+        assert!(meta.attributes.has(Attribute::Underlined));
+        assert!(lines[4].content[0]
+            .style()
+            .attributes
+            .has(Attribute::Reverse));
+
+        assert!(span(&lines[5], "fn helper() -> ")
+            .style()
+            .attributes
+            .has(Attribute::Dim));
+        let old = span(&lines[5], "u8").style();
+        assert_eq!(old.foreground_color, Some(Color::Red));
+        assert_eq!(old.background_color, Some(Color::AnsiValue(52)));
+        assert!(old.attributes.has(Attribute::CrossedOut));
+        assert!(!old.attributes.has(Attribute::Dim));
+        let new = span(&lines[5], "u16").style();
+        assert_eq!(
+            new.foreground_color,
+            Some(Color::Rgb { r: 0, g: 255, b: 0 })
+        );
+        assert_eq!(new.background_color, None);
+        assert!(new.attributes.has(Attribute::Bold));
+        assert!(new.attributes.has(Attribute::Italic));
+        assert!(!new.attributes.has(Attribute::CrossedOut));
+    }
+
+    /// Output in the style of `delta(1)`: 256 & true colors in the semicolon & colon forms,
+    /// partial resets & erase in line sequences
+    #[test]
+    fn delta() {
+        let lines = conformance(
+            include_bytes!("../tests/fixtures/delta.ansi"),
+            include_str!("../tests/fixtures/delta.txt"),
+        );
+        assert_eq!(
+            span(&lines[3], "•").style().foreground_color,
+            Some(Color::AnsiValue(4))
+        );
+
+        let old = span(&lines[4], "u8").style();
+        assert_eq!(
+            old.foreground_color,
+            Some(Color::Rgb {
+                r: 102,
+                g: 217,
+                b: 239
+            })
+        );
+        assert_eq!(
+            old.background_color,
+            Some(Color::Rgb {
+                r: 144,
+                g: 16,
+                b: 17
+            })
+        );
+
+        let new = span(&lines[5], "u16").style();
+        assert_eq!(new.background_color, Some(Color::Rgb { r: 0, g: 96, b: 0 }));
+        let keyword = span(&lines[5], "fn").style();
+        assert_eq!(
+            keyword.foreground_color,
+            Some(Color::Rgb {
+                r: 249,
+                g: 38,
+                b: 114
+            })
+        );
+        assert_eq!(
+            keyword.background_color,
+            Some(Color::Rgb { r: 0, g: 40, b: 0 })
+        );
+        assert_eq!(span(&lines[5], " {").style(), &ContentStyle::new());
+
+        let whitespace = span(&lines[6], "  ").style();
+        assert!(whitespace.attributes.has(Attribute::Reverse));
+        assert_eq!(whitespace.foreground_color, Some(Color::DarkBlue));
+
+        let typo = span(&lines[7], "typo").style();
+        assert!(typo.attributes.has(Attribute::Undercurled));
+        assert_eq!(typo.underline_color, Some(Color::AnsiValue(196)));
+        assert_eq!(span(&lines[7], " ok").style(), &ContentStyle::new());
+    }
+
+    #[test]
+    fn partial_resets() {
+        let lines = parse_spans(
+            b"\x1b[1;2;3;7;9;95;104mA\x1b[22mB\x1b[23;27mC\x1b[29;39;49mD\x1b[21mE\x1b[24mF"
+                .to_vec(),
+        );
+        let styles: Vec<&ContentStyle> =
+            lines[0].content.iter().map(StyledContent::style).collect();
+        let a = styles[0];
+        assert_eq!(a.foreground_color, Some(Color::Magenta));
+        assert_eq!(a.background_color, Some(Color::Blue));
+        for attribute in [
+            Attribute::Bold,
+            Attribute::Dim,
+            Attribute::Italic,
+            Attribute::Reverse,
+            Attribute::CrossedOut,
+        ] {
+            assert!(a.attributes.has(attribute));
+        }
+        assert!(!styles[1].attributes.has(Attribute::Bold));
+        assert!(!styles[1].attributes.has(Attribute::Dim));
+        assert!(styles[1].attributes.has(Attribute::Italic));
+        assert!(!styles[2].attributes.has(Attribute::Italic));
+        assert!(!styles[2].attributes.has(Attribute::Reverse));
+        assert_eq!(styles[3], &ContentStyle::new());
+        assert!(styles[4].attributes.has(Attribute::DoubleUnderlined));
+        assert_eq!(styles[5], &ContentStyle::new());
+    }
+
+    #[test]
+    fn hyperlinks() {
+        let lines = parse_spans(
+            b"\x1b]8;;file:///src/a;b.rs\x1b\\\x1b[34ma;b.rs\x1b[m\x1b]8;;\x1b\\ ok\n\
+              \tsee \x1b]8;id=1;https://example.com\x07exa\nmple\x1b]8;;\x07\n"
+                .to_vec(),
+        );
+        assert_eq!(texts(&lines), vec!["a;b.rs ok", "        see exa", "mple"]);
+        let link = |start: usize, end: usize, url: &str| Hyperlink {
+            columns: start..end,
+            url: url.to_owned(),
+        };
+        assert_eq!(lines[0].links, vec![link(0, 6, "file:///src/a;b.rs")]);
+        assert_eq!(lines[1].links, vec![link(12, 15, "https://example.com")]);
+        assert_eq!(lines[2].links, vec![link(0, 4, "https://example.com")]);
+    }
+
+    #[test]
+    fn tabs() {
+        let lines = parse_spans("+\tx\n日本\ty\n12345678\tz\r\n".as_bytes().to_vec());
+        assert_eq!(
+            texts(&lines),
+            vec!["+       x", "日本    y", "12345678        z"]
+        );
+    }
+}
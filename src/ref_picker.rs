@@ -0,0 +1,300 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fuzzy picker over the branches & tags of the repository, used to switch the shown range.
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{style, Attribute, StyledContent, Stylize};
+use git_wrapper::Repository;
+
+use crate::actors::ahead_behind::AheadBehindThread;
+use crate::refs::RefKind;
+use crate::ui::base::{shorten_line, Drawable, HandleEvent, StyledArea, StyledLine};
+use crate::ui::input::InputLine;
+
+/// Maximal number of shown candidates
+const MAX_SHOWN: usize = 10;
+
+struct Candidate {
+    kind: RefKind,
+    /// Full name of the ref, i.e. `refs/heads/main`
+    name: String,
+    /// Commits ahead & behind of `HEAD`, while they are not calculated `None`
+    ahead_behind: Option<(usize, usize)>,
+}
+
+impl Candidate {
+    fn short_name(&self) -> &str {
+        RefKind::parse(&self.name).1
+    }
+
+    fn render(&self, selected: bool) -> StyledLine<String> {
+        let mut content = vec![
+            style("  ".to_owned()),
+            StyledContent::new(self.kind.style(), self.short_name().to_owned()),
+        ];
+        if let Some((ahead, behind)) = self.ahead_behind {
+            content.push(style(format!("  ↑{} ↓{}", ahead, behind)).dim());
+        }
+        if selected {
+            for part in &mut content {
+                part.style_mut().attributes.set(Attribute::Reverse);
+            }
+        }
+        StyledLine {
+            content,
+            links: vec![],
+        }
+    }
+}
+
+/// Return how well the query matches the text as subsequence, lower is better. The characters
+/// are compared case insensitive & matches at the start of words are preferred.
+fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let mut score: usize = 0;
+    let mut chars = text.char_indices();
+    let mut previous: Option<char> = None;
+    let mut last_match: Option<usize> = None;
+    for q in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            let (i, c) = chars.next()?;
+            let before = previous;
+            previous = Some(c);
+            if !c.to_lowercase().eq(std::iter::once(q)) {
+                continue;
+            }
+            let word_start = before.is_none_or(|b| matches!(b, '/' | '-' | '_' | '.' | ' '));
+            let gap = last_match.map_or(i, |last| i.saturating_sub(last).saturating_sub(1));
+            if !word_start {
+                score = score.saturating_add(gap);
+            }
+            last_match = Some(i);
+            break;
+        }
+    }
+    Some(score)
+}
+
+/// Picker of a branch or tag, shown in the status area
+#[derive(Default)]
+pub struct RefPicker {
+    /// The query, while the picker is open
+    input: Option<InputLine>,
+    candidates: Vec<Candidate>,
+    /// Indices of the candidates matching the query, best match first
+    matches: Vec<usize>,
+    /// Index in `matches`
+    selected: usize,
+    /// Full name of the picked ref, see [`RefPicker::take_chosen`]
+    chosen: Option<String>,
+    ahead_behind: Option<AheadBehindThread>,
+    /// Shown until the next key press, i.e. why the picked ref could not be shown
+    message: Option<String>,
+}
+
+impl RefPicker {
+    /// Open the picker with the local & remote branches & tags of the repository
+    pub fn open(&mut self, repo: &Repository) {
+        let output = repo
+            .git()
+            .args([
+                "for-each-ref",
+                "--format=%(refname)",
+                "refs/heads",
+                "refs/remotes",
+                "refs/tags",
+            ])
+            .output();
+        let text = match output {
+            Ok(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).into_owned()
+            }
+            _ => {
+                log::warn!("Failed to list the refs");
+                String::new()
+            }
+        };
+        self.candidates = text
+            .lines()
+            .filter(|name| !name.ends_with("/HEAD"))
+            .map(|name| Candidate {
+                kind: RefKind::parse(name).0,
+                name: name.to_owned(),
+                ahead_behind: None,
+            })
+            .collect();
+        self.ahead_behind = Some(AheadBehindThread::new(repo.clone()));
+        self.input = Some(InputLine::default());
+        self.update_matches();
+    }
+
+    fn close(&mut self) {
+        self.input = None;
+        self.candidates.clear();
+        self.matches.clear();
+        self.ahead_behind = None;
+    }
+
+    /// Show a message until the next key press
+    pub fn show_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    /// Return the full name of the picked ref, which should be shown now
+    pub fn take_chosen(&mut self) -> Option<String> {
+        self.chosen.take()
+    }
+
+    fn update_matches(&mut self) {
+        let query = self.input.as_ref().map(|i| i.text().as_str()).unwrap_or("");
+        let mut scored: Vec<(usize, usize)> = self
+            .candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| fuzzy_score(query, c.short_name()).map(|score| (score, i)))
+            .collect();
+        scored.sort_by_key(|(score, i)| {
+            let candidate = &self.candidates[*i];
+            (*score, candidate.kind, candidate.name.len())
+        });
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+        // Only the shown candidates are counted, the ones of the previous query are dropped
+        if let Some(thread) = &self.ahead_behind {
+            let names = self
+                .matches
+                .iter()
+                .take(MAX_SHOWN)
+                .map(|i| &self.candidates[*i])
+                .filter(|c| c.ahead_behind.is_none())
+                .map(|c| c.name.clone())
+                .collect();
+            thread.request(names);
+        }
+    }
+
+    pub fn render(&mut self, width: usize) -> StyledArea<String> {
+        let Some(input) = &self.input else {
+            return self
+                .message
+                .iter()
+                .map(|message| {
+                    let line = StyledLine {
+                        content: vec![style(message.clone()).italic()],
+                        links: vec![],
+                    };
+                    shorten_line(line, width)
+                })
+                .collect();
+        };
+        if let Some(thread) = &self.ahead_behind {
+            while let Ok(response) = thread.try_recv() {
+                let candidate = self.candidates.iter_mut().find(|c| c.name == response.name);
+                if let Some(candidate) = candidate {
+                    candidate.ahead_behind = Some((response.ahead, response.behind));
+                }
+            }
+        }
+        let mut result: StyledArea<String> = self
+            .matches
+            .iter()
+            .take(MAX_SHOWN)
+            .enumerate()
+            .rev()
+            .map(|(i, c)| shorten_line(self.candidates[*c].render(i == self.selected), width))
+            .collect();
+        let mut content = vec![style("Show ".to_owned())];
+        content.append(&mut input.render_line());
+        content.push(style(format!(
+            "  ({}/{})  <Enter> show  <Esc> cancel",
+            self.matches.len(),
+            self.candidates.len()
+        )));
+        result.push(shorten_line(
+            StyledLine {
+                content,
+                links: vec![],
+            },
+            width,
+        ));
+        result
+    }
+
+    pub fn on_event(&mut self, event: &Event) -> HandleEvent {
+        let Some(input) = &mut self.input else {
+            // The message does not swallow the key press
+            if matches!(event, Event::Key(_)) {
+                self.message = None;
+            }
+            return HandleEvent::Ignored;
+        };
+        let shown = self.matches.len().min(MAX_SHOWN);
+        match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Esc, ..
+            }) => self.close(),
+            Event::Key(KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            }) => {
+                self.chosen = self
+                    .matches
+                    .get(self.selected)
+                    .map(|i| self.candidates[*i].name.clone());
+                self.close();
+            }
+            // The best match is shown at the bottom, next to the input
+            Event::Key(KeyEvent {
+                code: KeyCode::Up,
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => {
+                if self.selected.saturating_add(1) < shown {
+                    self.selected = self.selected.saturating_add(1);
+                }
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Down,
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => self.selected = self.selected.saturating_sub(1),
+            _ => {
+                if input.on_event(event) == HandleEvent::Handled {
+                    self.update_matches();
+                }
+            }
+        }
+        HandleEvent::Handled
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::fuzzy_score;
+
+    #[test]
+    fn fuzzy_matching() {
+        assert_eq!(fuzzy_score("", "main"), Some(0));
+        assert_eq!(fuzzy_score("main", "main"), Some(0));
+        assert_eq!(fuzzy_score("xyz", "main"), None);
+        assert_eq!(fuzzy_score("MN", "main"), Some(2));
+        assert!(fuzzy_score("rx", "release/x") < fuzzy_score("rx", "refix"));
+        assert!(fuzzy_score("om", "origin/main") < fuzzy_score("om", "some"));
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The refs of the repository by the commit they point to, read via `git for-each-ref`.
+
+use std::collections::HashMap;
+
+use crossterm::style::{Attribute, ContentStyle};
+use git_wrapper::Repository;
+
+use crate::commit::Oid;
+use crate::default_styles::{
+    HEAD_STYLE, LOCAL_BRANCH_STYLE, REF_STYLE, REMOTE_BRANCH_STYLE, TAG_STYLE,
+};
+use crate::history_entry::is_ignored_ref;
+
+/// The kind of a ref, ordered by how they are shown
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum RefKind {
+    Head,
+    LocalBranch,
+    RemoteBranch,
+    Tag,
+    Notes,
+    Stash,
+    Other,
+}
+
+impl RefKind {
+    /// Return the kind & short name of the full ref name
+    pub fn parse(name: &str) -> (Self, &str) {
+        if let Some(short) = name.strip_prefix("refs/heads/") {
+            (Self::LocalBranch, short)
+        } else if let Some(short) = name.strip_prefix("refs/remotes/") {
+            (Self::RemoteBranch, short)
+        } else if let Some(short) = name.strip_prefix("refs/tags/") {
+            (Self::Tag, short)
+        } else if name.starts_with("refs/notes/") {
+            (Self::Notes, name.trim_start_matches("refs/"))
+        } else if name == "refs/stash" {
+            (Self::Stash, "stash")
+        } else {
+            (Self::Other, name)
+        }
+    }
+
+    /// Return `true` for `HEAD` & branches, which are shown before the subject
+    pub const fn is_branch(self) -> bool {
+        matches!(self, Self::Head | Self::LocalBranch | Self::RemoteBranch)
+    }
+
+    pub fn style(self) -> ContentStyle {
+        match self {
+            Self::Head => *HEAD_STYLE,
+            Self::LocalBranch => *LOCAL_BRANCH_STYLE,
+            Self::RemoteBranch => *REMOTE_BRANCH_STYLE,
+            Self::Tag => *TAG_STYLE,
+            Self::Notes | Self::Stash => {
+                let mut style = *REF_STYLE;
+                style.attributes.set(Attribute::Italic);
+                style
+            }
+            Self::Other => *REF_STYLE,
+        }
+    }
+}
+
+/// A ref pointing to a commit
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ref {
+    pub kind: RefKind,
+    /// The name without the `refs/<kind>/` prefix
+    pub name: String,
+}
+
+/// The refs of the repository by the commit they point to
+#[derive(Clone, Debug, Default)]
+pub struct RefMap(HashMap<Oid, Vec<Ref>>);
+
+impl RefMap {
+    /// Read the refs & `HEAD` of the repository
+    pub fn load(repo: &Repository) -> Self {
+        let output = repo
+            .git()
+            .args([
+                "for-each-ref",
+                "--format=%(objectname)%00%(*objectname)%00%(refname)",
+            ])
+            .output();
+        let refs = match output {
+            Ok(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).into_owned()
+            }
+            Ok(output) => {
+                log::warn!(
+                    "git for-each-ref failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                String::new()
+            }
+            Err(err) => {
+                log::warn!("Failed to run git for-each-ref: {}", err);
+                String::new()
+            }
+        };
+        let head = repo
+            .git()
+            .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| Oid(String::from_utf8_lossy(&output.stdout).trim().to_owned()));
+        Self::parse(&refs, head)
+    }
+
+    /// Parse the output of `git for-each-ref`, see [`RefMap::load`]
+    fn parse(text: &str, head: Option<Oid>) -> Self {
+        let mut result: HashMap<Oid, Vec<Ref>> = HashMap::new();
+        if let Some(head) = head {
+            result.entry(head).or_default().push(Ref {
+                kind: RefKind::Head,
+                name: "HEAD".to_owned(),
+            });
+        }
+        for line in text.lines() {
+            let mut fields = line.split('\0');
+            let (Some(oid), Some(peeled), Some(name)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if is_ignored_ref(name) {
+                continue;
+            }
+            // Annotated tags point to the tag object, the peeled one is the commit
+            let oid = if peeled.is_empty() { oid } else { peeled };
+            let (kind, short) = RefKind::parse(name);
+            result.entry(Oid(oid.to_owned())).or_default().push(Ref {
+                kind,
+                name: short.to_owned(),
+            });
+        }
+        for refs in result.values_mut() {
+            refs.sort_by_key(|r| r.kind);
+        }
+        Self(result)
+    }
+
+    /// Return the refs pointing to the commit
+    pub fn get(&self, oid: &Oid) -> Vec<Ref> {
+        self.0.get(oid).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{Ref, RefKind, RefMap};
+    use crate::commit::Oid;
+
+    #[test]
+    fn parse() {
+        let text = "a1\0\0refs/heads/main\n\
+                    a1\0\0refs/remotes/origin/main\n\
+                    b2\0a1\0refs/tags/v1.0\n\
+                    a1\0\0refs/prefetch/remotes/origin/main\n\
+                    c3\0\0refs/stash\n\
+                    d4\0\0refs/notes/commits\n\
+                    e5\0\0refs/pull/12/head\n";
+        let refs = RefMap::parse(text, Some(Oid("a1".to_owned())));
+        let kinds = |oid: &str| -> Vec<(RefKind, String)> {
+            refs.get(&Oid(oid.to_owned()))
+                .into_iter()
+                .map(|Ref { kind, name }| (kind, name))
+                .collect()
+        };
+        assert_eq!(
+            kinds("a1"),
+            vec![
+                (RefKind::Head, "HEAD".to_owned()),
+                (RefKind::LocalBranch, "main".to_owned()),
+                (RefKind::RemoteBranch, "origin/main".to_owned()),
+                (RefKind::Tag, "v1.0".to_owned()),
+            ]
+        );
+        assert_eq!(kinds("b2"), vec![]);
+        assert_eq!(kinds("c3"), vec![(RefKind::Stash, "stash".to_owned())]);
+        assert_eq!(
+            kinds("d4"),
+            vec![(RefKind::Notes, "notes/commits".to_owned())]
+        );
+        assert_eq!(
+            kinds("e5"),
+            vec![(RefKind::Other, "refs/pull/12/head".to_owned())]
+        );
+    }
+}
@@ -19,7 +19,7 @@
 
 use crossterm::style::{Attribute, Color, ContentStyle, StyledContent};
 
-use crate::ui::base::search::Needle;
+use crate::query::Query;
 use crate::ui::base::StyledLine;
 
 struct TextMatch {
@@ -29,24 +29,21 @@ struct TextMatch {
 
 #[must_use]
 #[allow(clippy::ptr_arg)]
-pub fn highlight_search_line(
-    line: &StyledLine<String>,
-    search_state: &Needle,
-) -> StyledLine<String> {
+pub fn highlight_search_line(line: &StyledLine<String>, query: &Query) -> StyledLine<String> {
     let mut result = vec![];
     for sc in &line.content {
-        result.append(&mut highlight_search(sc, search_state));
+        result.append(&mut highlight_search(sc, query));
+    }
+    StyledLine {
+        content: result,
+        links: line.links.clone(),
     }
-    StyledLine { content: result }
 }
 
-fn highlight_search(
-    sc: &StyledContent<String>,
-    search_state: &Needle,
-) -> Vec<StyledContent<String>> {
+fn highlight_search(sc: &StyledContent<String>, query: &Query) -> Vec<StyledContent<String>> {
     let mut cur = 0;
     let mut tmp = vec![];
-    let indices = search_styled_content(sc, search_state);
+    let indices = search_styled_content(sc, query);
     let mut style = ContentStyle {
         background_color: Some(Color::DarkRed),
         foreground_color: Some(Color::DarkGrey),
@@ -78,35 +75,23 @@ fn highlight_search(
     tmp
 }
 
-fn search_styled_content(sc: &StyledContent<String>, state: &Needle) -> Vec<TextMatch> {
-    let (haystack, needle) = if *state.ignore_case() {
-        (sc.content().to_lowercase(), state.text().to_lowercase())
-    } else {
-        (sc.content().to_string(), state.text().clone())
-    };
-    let mut result = Vec::new();
-    let indices = haystack.match_indices(&needle);
-    #[allow(clippy::arithmetic)]
-    for (i, s) in indices {
-        // arithmetic: We know that i + s.len() < i32_MAX, because we iterate over indices!
-        result.push(TextMatch {
-            start: i,
-            end: i + s.len(),
-        });
-    }
-
-    result
+fn search_styled_content(sc: &StyledContent<String>, query: &Query) -> Vec<TextMatch> {
+    query
+        .find_in(sc.content())
+        .into_iter()
+        .map(|(start, end)| TextMatch { start, end })
+        .collect()
 }
 
 #[allow(clippy::ptr_arg)]
 // Used for searching e.g. in details view
-pub fn line_matches(line: &StyledLine<String>, state: &Needle) -> bool {
-    for part in &line.content {
-        if part.content().matches(state.text()).count() > 0 {
-            return true;
-        }
-    }
-    false
+pub fn line_matches(line: &StyledLine<String>, query: &Query) -> bool {
+    let text: String = line
+        .content
+        .iter()
+        .map(|sc| sc.content().as_str())
+        .collect();
+    query.matches_text(&text)
 }
 
 #[cfg(test)]
@@ -125,7 +110,7 @@ mod search_styled_content {
             StyledContent::new(ContentStyle::new(), "Foo bar buz".to_owned());
         let needle = Needle::smart_case("foo", Direction::Forward);
         assert!(needle.ignore_case(), "Case *in*sensitive");
-        let indices = search_styled_content(&sc, &needle);
+        let indices = search_styled_content(&sc, &needle.query());
         assert_eq!(indices.len(), 1, "Found matches ignoring case");
     }
 
@@ -135,7 +120,7 @@ mod search_styled_content {
             StyledContent::new(ContentStyle::new(), "Foo bar buz".to_owned());
         let needle = Needle::smart_case("Foo", Direction::Forward);
         assert!(!needle.ignore_case(), "Case sensitive");
-        let indices = search_styled_content(&sc, &needle);
+        let indices = search_styled_content(&sc, &needle.query());
         assert_eq!(indices.len(), 1, "Found matches ignoring case");
     }
 
@@ -145,7 +130,7 @@ mod search_styled_content {
             StyledContent::new(ContentStyle::new(), "Foo bar buz".to_owned());
         let needle = Needle::smart_case("bar", Direction::Forward);
         assert!(needle.ignore_case(), "Case *in*sensitive");
-        let indices = search_styled_content(&sc, &needle);
+        let indices = search_styled_content(&sc, &needle.query());
         assert_eq!(indices.len(), 1, "Found matches ignoring case");
     }
 }
@@ -0,0 +1,125 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Persist the folding state & selection of a history view between sessions.
+
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+use git_wrapper::Repository;
+
+use crate::cache;
+use crate::commit::Oid;
+use crate::utils::stable_hash;
+
+/// Folding state & selection of a [`crate::history_adapter::HistoryAdapter`]
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Session {
+    /// First-parent history length when the session was stored
+    pub length: usize,
+    /// Number of loaded first-parent entries when the session was stored
+    pub loaded: usize,
+    pub selected: Option<Oid>,
+    pub unfolded: Vec<Oid>,
+}
+
+impl Display for Session {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "length {}", self.length)?;
+        writeln!(f, "loaded {}", self.loaded)?;
+        if let Some(oid) = &self.selected {
+            writeln!(f, "selected {}", oid)?;
+        }
+        for oid in &self.unfolded {
+            writeln!(f, "unfolded {}", oid)?;
+        }
+        Ok(())
+    }
+}
+
+impl Session {
+    /// Parse a session stored via [`Session::to_string`]. Unknown lines are skipped.
+    #[must_use]
+    pub fn parse(text: &str) -> Self {
+        let mut result = Self::default();
+        for line in text.lines() {
+            match line.split_once(' ') {
+                Some(("length", v)) => result.length = v.parse().unwrap_or_default(),
+                Some(("loaded", v)) => result.loaded = v.parse().unwrap_or_default(),
+                Some(("selected", v)) => result.selected = Some(Oid(v.to_owned())),
+                Some(("unfolded", v)) => result.unfolded.push(Oid(v.to_owned())),
+                _ => log::warn!("Unexpected session line «{}»", line),
+            }
+        }
+        result
+    }
+
+    /// Read the session for the repository, range & paths from the cache
+    pub fn load(repo: &Repository, range: &[OsString], paths: &[PathBuf]) -> Option<Self> {
+        match cache::fetch_session(&session_id(repo, range, paths)) {
+            Ok(v) => v.map(|text| Self::parse(&text)),
+            Err(err) => {
+                log::warn!("Failed to load session: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Write the session for the repository, range & paths to the cache
+    pub fn store(&self, repo: &Repository, range: &[OsString], paths: &[PathBuf]) {
+        if let Err(err) = cache::store_session(&session_id(repo, range, paths), &self.to_string()) {
+            log::warn!("Failed to store session: {}", err);
+        }
+    }
+}
+
+fn session_id(repo: &Repository, range: &[OsString], paths: &[PathBuf]) -> String {
+    let work_tree = repo.work_tree().unwrap_or_default();
+    let parts = std::iter::once(work_tree.into_os_string())
+        .chain(range.iter().cloned())
+        .chain(std::iter::once(OsString::from("--")))
+        .chain(paths.iter().map(|p| p.clone().into_os_string()));
+    stable_hash(parts.map(|p| p.to_string_lossy().into_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::Session;
+    use crate::commit::Oid;
+
+    #[test]
+    fn roundtrip() {
+        let session = Session {
+            length: 23,
+            loaded: 5,
+            selected: Some(Oid("a17989470af".to_owned())),
+            unfolded: vec![Oid("6be11cb7f9e".to_owned()), Oid("df622aa0149".to_owned())],
+        };
+        assert_eq!(Session::parse(&session.to_string()), session);
+    }
+
+    #[test]
+    fn skip_invalid_lines() {
+        let session = Session::parse("length 3\nfoo\nunfolded 6be11cb7f9e\n");
+        assert_eq!(session.length, 3);
+        assert_eq!(session.selected, None);
+        assert_eq!(session.unfolded, vec![Oid("6be11cb7f9e".to_owned())]);
+    }
+}
@@ -0,0 +1,448 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Layout of the columns of the history table.
+//!
+//! The columns are configured via `git config glv.columns` as a comma separated list of column
+//! names, each with an optional width, i.e. `id,date:20,author:12,graph:inline,subject:flex,pr`.
+//! A number limits the width of a column, `inline` makes a column only as wide as its content
+//! in each row & the `flex` column takes the space left over by the other columns.
+//!
+//! Column names: `id`, `date`, `author`, `committer`, `icon`, `graph`, `refs`, `tags`,
+//! `modules`, `subject`, `pr` & `signature`.
+
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use crossterm::style::{style, Attribute, StyledContent};
+use git_wrapper::Repository;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::history_entry::Column;
+use crate::ui::base::{
+    content_length, line_length, shorten_line, skip_columns, StyledArea, StyledLine,
+};
+
+/// The columns shown when `glv.columns` is not set
+const DEFAULT_COLUMNS: &str =
+    "id,date:20,author:12,icon,graph:inline,refs:inline,modules:inline,subject:flex,tags:inline";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColumnWidth {
+    /// As wide as the widest cell of the page
+    Auto,
+    /// As wide as the widest cell of the page, but at most the given width
+    Max(usize),
+    /// As wide as the cell in each row, skipped in rows where the cell is empty
+    Inline,
+    /// Takes the space left over by the other columns
+    Flex,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ColumnSpec {
+    pub column: Column,
+    pub width: ColumnWidth,
+}
+
+impl FromStr for ColumnSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, width) = match s.split_once(':') {
+            Some((name, width)) => (name, Some(width)),
+            None => (s, None),
+        };
+        let column = name.trim().parse()?;
+        let width = match width.map(str::trim) {
+            None | Some("auto") => ColumnWidth::Auto,
+            Some("inline") => ColumnWidth::Inline,
+            Some("flex") => ColumnWidth::Flex,
+            Some(number) => match number.parse() {
+                Ok(max) if max > 0 => ColumnWidth::Max(max),
+                _ => return Err(format!("Invalid width “{}” of column “{}”", number, name)),
+            },
+        };
+        Ok(Self { column, width })
+    }
+}
+
+/// The cells of a row, in the order of the [`TableLayout`] columns
+pub struct Row {
+    pub cells: Vec<Vec<StyledContent<String>>>,
+    pub selected: bool,
+}
+
+/// The columns of the history table, their order & widths
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TableLayout(Vec<ColumnSpec>);
+
+impl Default for TableLayout {
+    fn default() -> Self {
+        DEFAULT_COLUMNS.parse().expect("Valid default columns")
+    }
+}
+
+impl FromStr for TableLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let columns = s
+            .split(',')
+            .filter(|spec| !spec.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<ColumnSpec>, _>>()?;
+        if columns.is_empty() {
+            return Err("No columns".to_owned());
+        }
+        let flex = columns
+            .iter()
+            .filter(|spec| spec.width == ColumnWidth::Flex)
+            .count();
+        if flex > 1 {
+            return Err("Only one column can be flex".to_owned());
+        }
+        Ok(Self(columns))
+    }
+}
+
+impl TableLayout {
+    /// Read the layout from `glv.columns` of the git config, or else the default one
+    pub fn from_repo(repo: &Repository) -> Self {
+        let output = repo.git().args(["config", "--get", "glv.columns"]).output();
+        match output {
+            Ok(output) if output.status.success() => {
+                let text = String::from_utf8_lossy(&output.stdout);
+                text.trim().parse().unwrap_or_else(|err| {
+                    log::warn!("Ignoring glv.columns: {}", err);
+                    Self::default()
+                })
+            }
+            _ => Self::default(),
+        }
+    }
+
+    /// Return the shown columns in order
+    pub fn columns(&self) -> impl Iterator<Item = Column> + '_ {
+        self.0.iter().map(|spec| spec.column)
+    }
+
+    pub fn has(&self, column: Column) -> bool {
+        self.columns().any(|c| c == column)
+    }
+
+    /// Return the width of the widest row, when the flex column is as wide as its content
+    pub fn natural_width(&self, rows: &[Row]) -> usize {
+        let widths = self.widths(rows);
+        rows.iter()
+            .map(|row| line_length(&join(self.fit(row, &widths, None))))
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Lay out the rows to lines of the width, scrolled by `offset` columns. While scrolled, the
+    /// id & graph stay on the left, followed by the other columns.
+    pub fn render(&self, rows: &[Row], width: usize, offset: usize) -> StyledArea<String> {
+        let widths = self.widths(rows);
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut line = if offset == 0 {
+                let cells = self.fit(row, &widths, None);
+                let used = line_length(&join(
+                    cells
+                        .iter()
+                        .filter(|(spec, _)| spec.width != ColumnWidth::Flex)
+                        .cloned()
+                        .collect(),
+                ));
+                let flex = width
+                    .saturating_sub(used)
+                    .saturating_sub(usize::from(used > 0));
+                join(self.fit(row, &widths, Some(flex)))
+            } else {
+                let (frozen, scrolled): (Vec<_>, Vec<_>) = self
+                    .fit(row, &widths, None)
+                    .into_iter()
+                    .partition(|(spec, _)| matches!(spec.column, Column::Id | Column::Graph));
+                let mut line = join(frozen);
+                let scrolled = skip_columns(join(scrolled), offset);
+                if !line.content.is_empty() && !scrolled.content.is_empty() {
+                    line.content.push(separator());
+                }
+                line.content.extend(scrolled.content);
+                line
+            };
+            line = shorten_line(line, width);
+            if row.selected {
+                for part in &mut line.content {
+                    part.style_mut().attributes.set(Attribute::Reverse);
+                }
+            }
+            result.push(line);
+        }
+        result
+    }
+
+    /// Return the widths of the columns on the page, `None` for inline & flex columns
+    fn widths(&self, rows: &[Row]) -> Vec<Option<usize>> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| {
+                let widest = || {
+                    rows.iter()
+                        .filter_map(|row| row.cells.get(i))
+                        .map(|cell| cell.iter().map(content_length).sum::<usize>())
+                        .max()
+                        .unwrap_or_default()
+                };
+                match spec.width {
+                    ColumnWidth::Auto => Some(widest()),
+                    ColumnWidth::Max(max) => Some(widest().min(max)),
+                    ColumnWidth::Inline | ColumnWidth::Flex => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Return the shown cells of the row fitted to the column widths. The flex column is fitted
+    /// to `flex` columns or else as wide as its content.
+    fn fit(
+        &self,
+        row: &Row,
+        widths: &[Option<usize>],
+        flex: Option<usize>,
+    ) -> Vec<(ColumnSpec, Vec<StyledContent<String>>)> {
+        let mut result = vec![];
+        for (i, spec) in self.0.iter().enumerate() {
+            let cell = row.cells.get(i).cloned().unwrap_or_default();
+            let width = match spec.width {
+                ColumnWidth::Flex => flex,
+                _ => widths.get(i).copied().flatten(),
+            };
+            let cell = match width {
+                Some(0) => continue,
+                Some(width) => fit_cell(cell, width, spec.width != ColumnWidth::Flex),
+                None if cell.iter().all(|c| c.content().is_empty()) => continue,
+                None => cell,
+            };
+            result.push((*spec, cell));
+        }
+        // Only the flex column in the middle of a row needs trailing spaces
+        if let Some((spec, cell)) = result.last_mut() {
+            if spec.width == ColumnWidth::Flex {
+                while cell.last().is_some_and(|c| c.content().trim().is_empty()) {
+                    cell.pop();
+                }
+            }
+        }
+        result
+    }
+}
+
+fn separator() -> StyledContent<String> {
+    style(" ".to_owned())
+}
+
+/// Join the cells with a separator
+fn join(cells: Vec<(ColumnSpec, Vec<StyledContent<String>>)>) -> StyledLine<String> {
+    let mut result = StyledLine::empty();
+    for (_, cell) in cells {
+        if !result.content.is_empty() {
+            result.content.push(separator());
+        }
+        result.content.extend(cell);
+    }
+    result
+}
+
+/// Shorten or pad the cell to the width. Single texts like names are shortened by words.
+fn fit_cell(
+    cell: Vec<StyledContent<String>>,
+    width: usize,
+    by_words: bool,
+) -> Vec<StyledContent<String>> {
+    if let [single] = cell.as_slice() {
+        if by_words {
+            return vec![StyledContent::new(
+                *single.style(),
+                adjust_string(single.content(), width),
+            )];
+        }
+    }
+    let mut line = StyledLine {
+        content: cell,
+        links: vec![],
+    };
+    if line_length(&line) > width {
+        line = shorten_line(line, width);
+    }
+    let length = line_length(&line);
+    if length < width {
+        line.content
+            .push(style(" ".repeat(width.saturating_sub(length))));
+    }
+    line.content
+}
+
+// I'm not proud of this code. Ohh Omnissiah be merciful on my soul‼
+// arithmetic: This code is not reviewed, but it will be removed soon
+#[allow(clippy::arithmetic_side_effects)]
+fn adjust_string(text: &str, expected: usize) -> String {
+    debug_assert!(expected > 0, "Minimal length should be 1");
+    let length = unicode_width::UnicodeWidthStr::width(text);
+    let mut result = String::from(text);
+    match length.cmp(&expected) {
+        Ordering::Less => {
+            let actual = expected - length;
+            for _ in 0..actual {
+                result.push(' ');
+            }
+        }
+        Ordering::Equal => {}
+        Ordering::Greater => {
+            result = "".to_owned();
+            for w in text.unicode_words().collect::<Vec<&str>>() {
+                let actual = UnicodeWidthStr::width(result.as_str()) + UnicodeWidthStr::width(w);
+                if actual > expected {
+                    break;
+                }
+                result.push_str(w);
+                result.push(' ');
+            }
+
+            if result.is_empty() {
+                let words = text.unicode_words().collect::<Vec<&str>>();
+                result.push_str(words.first().copied().unwrap_or(text));
+            }
+
+            let actual = UnicodeWidthStr::width(result.as_str());
+            if actual > expected {
+                let mut tmp = String::new();
+                let mut i = 0;
+                for g in result.as_str().graphemes(true) {
+                    tmp.push_str(g);
+                    i += 1;
+                    if i == expected - 1 {
+                        break;
+                    }
+                }
+                result = tmp;
+                result.push('…');
+            } else {
+                let end = expected - actual;
+                for _ in 0..end {
+                    result.push(' ');
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::style::style;
+    use pretty_assertions::assert_eq;
+
+    use super::{ColumnSpec, ColumnWidth, Row, TableLayout};
+    use crate::history_entry::Column;
+
+    fn row(cells: &[&str]) -> Row {
+        Row {
+            cells: cells
+                .iter()
+                .map(|c| {
+                    if c.is_empty() {
+                        vec![]
+                    } else {
+                        vec![style((*c).to_owned())]
+                    }
+                })
+                .collect(),
+            selected: false,
+        }
+    }
+
+    fn text(layout: &TableLayout, rows: &[Row], width: usize, offset: usize) -> Vec<String> {
+        layout
+            .render(rows, width, offset)
+            .iter()
+            .map(|line| line.content.iter().map(|c| c.content().as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn parse() {
+        let layout: TableLayout = "id, author:12,subject:flex,pr".parse().expect("Valid");
+        assert_eq!(
+            layout.0,
+            vec![
+                ColumnSpec {
+                    column: Column::Id,
+                    width: ColumnWidth::Auto
+                },
+                ColumnSpec {
+                    column: Column::Author,
+                    width: ColumnWidth::Max(12)
+                },
+                ColumnSpec {
+                    column: Column::Subject,
+                    width: ColumnWidth::Flex
+                },
+                ColumnSpec {
+                    column: Column::PullRequest,
+                    width: ColumnWidth::Auto
+                },
+            ]
+        );
+        assert!("id,foo".parse::<TableLayout>().is_err());
+        assert!("id:0".parse::<TableLayout>().is_err());
+        assert!("subject:flex,refs:flex".parse::<TableLayout>().is_err());
+        assert_eq!(TableLayout::default().columns().count(), 9);
+    }
+
+    #[test]
+    fn layout() {
+        let layout: TableLayout = "id,author:5,refs:inline,subject:flex,pr"
+            .parse()
+            .expect("Valid");
+        let rows = [
+            row(&["a1", "Alice Doe", "«main»", "Fix it", "#12"]),
+            row(&["b2", "Bob", "", "A longer subject", ""]),
+        ];
+        assert_eq!(
+            text(&layout, &rows, 24, 0),
+            vec!["a1 Alic… «main» Fix… #12", "b2 Bob   A longer s…    "]
+        );
+        assert_eq!(
+            layout.natural_width(&rows),
+            "b2 Bob   A longer subject    ".len()
+        );
+    }
+
+    #[test]
+    fn scrolling() {
+        let layout: TableLayout = "id,author,graph:inline,subject:flex"
+            .parse()
+            .expect("Valid");
+        let rows = [row(&["a1", "Alice", "●", "Fix it"])];
+        assert_eq!(text(&layout, &rows, 20, 0), vec!["a1 Alice ● Fix it"]);
+        assert_eq!(text(&layout, &rows, 20, 4), vec!["a1 ● e Fix it"]);
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Several independent history views over different ranges & paths, shown one at a time.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{style, Attribute, Stylize};
+
+use crate::detail::{DiffView, Selection};
+use crate::history_adapter::HistoryAdapter;
+use crate::history_table::TableWidget;
+use crate::table_layout::TableLayout;
+use crate::ui::base::{shorten_line, Area, Drawable, HandleEvent, StyledArea, StyledLine};
+use crate::ui::input::InputLine;
+use crate::ui::layouts::SplitLayout;
+
+pub type View = SplitLayout<TableWidget, DiffView, Selection>;
+
+pub fn build_view(adapter: HistoryAdapter, layout: TableLayout) -> View {
+    let diff = DiffView::new(adapter.repo().clone(), adapter.paths().to_vec());
+    SplitLayout::new(TableWidget::new(adapter, layout), diff)
+}
+
+/// Parse `<revision>… [-- <path>…]`. Without revisions or the `--` the ones of the current view
+/// are used.
+fn parse_view(text: &str, range: &[OsString], paths: &[PathBuf]) -> (Vec<OsString>, Vec<PathBuf>) {
+    let mut words = text.split_whitespace();
+    let new_range: Vec<OsString> = words
+        .by_ref()
+        .take_while(|word| *word != "--")
+        .map(OsString::from)
+        .collect();
+    let new_paths: Vec<PathBuf> = words.map(PathBuf::from).collect();
+    let range = if new_range.is_empty() {
+        range.to_vec()
+    } else {
+        new_range
+    };
+    let paths = if text.split_whitespace().any(|word| word == "--") {
+        new_paths
+    } else {
+        paths.to_vec()
+    };
+    (range, paths)
+}
+
+/// Title of a tab, i.e. `main -- src`
+fn title(adapter: &HistoryAdapter) -> String {
+    let mut words: Vec<String> = adapter
+        .range()
+        .iter()
+        .map(|r| r.to_string_lossy().into_owned())
+        .collect();
+    if !adapter.paths().is_empty() {
+        words.push("--".to_owned());
+        words.extend(adapter.paths().iter().map(|p| p.display().to_string()));
+    }
+    words.join(" ")
+}
+
+/// The history views, a tab bar is shown when there is more than one
+pub struct Tabs {
+    views: Vec<View>,
+    /// Index of the shown view
+    active: usize,
+    layout: TableLayout,
+    /// Restore the folding state & selection of new views from the last session
+    restore: bool,
+    /// Input of the range & paths of a new view, while it is captured
+    input: Option<InputLine>,
+    /// Shown until the next key press, i.e. why a view could not be opened
+    message: Option<String>,
+}
+
+impl Tabs {
+    pub fn new(view: View, layout: TableLayout, restore: bool) -> Self {
+        Self {
+            views: vec![view],
+            active: 0,
+            layout,
+            restore,
+            input: None,
+            message: None,
+        }
+    }
+
+    /// Store the folding state & selection of all views
+    pub fn store_sessions(&self) {
+        for view in &self.views {
+            view.main().store_session();
+        }
+    }
+
+    fn active(&self) -> &View {
+        &self.views[self.active]
+    }
+
+    fn open(&mut self, text: &str) {
+        let adapter = self.active().main().adapter();
+        let (range, paths) = parse_view(text, adapter.range(), adapter.paths());
+        match adapter.with_view(range, paths) {
+            Ok(adapter) => {
+                let mut view = build_view(adapter, self.layout.clone());
+                if self.restore {
+                    view.main_mut().restore_session();
+                }
+                self.active = self.active.saturating_add(1);
+                self.views.insert(self.active, view);
+            }
+            Err(err) => {
+                log::warn!("Failed to open {}: {}", text, err);
+                self.message = Some(format!("Failed to open {}", text.trim()));
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        let view = self.views.remove(self.active);
+        view.main().store_session();
+        self.active = self.active.min(self.views.len().saturating_sub(1));
+    }
+
+    fn input_on_event(&mut self, event: &Event) -> HandleEvent {
+        let input = self.input.as_mut().expect("Capturing new view");
+        if input.on_event(event) == HandleEvent::Handled {
+            return HandleEvent::Handled;
+        }
+        match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Enter,
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => {
+                let text = input.text().clone();
+                self.input = None;
+                self.open(&text);
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Esc,
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => self.input = None,
+            _ => {}
+        }
+        HandleEvent::Handled
+    }
+
+    fn tab_bar(&self, width: usize) -> Option<StyledLine<String>> {
+        let content = if let Some(input) = &self.input {
+            let mut content = vec![style("New tab: ".to_owned())];
+            content.append(&mut input.render_line());
+            content.push(style(
+                "  <revision>… [-- <path>…]  <Enter> open  <Esc> cancel".to_owned(),
+            ));
+            content
+        } else if let Some(message) = &self.message {
+            vec![style(message.clone()).italic()]
+        } else if self.views.len() > 1 {
+            self.views
+                .iter()
+                .enumerate()
+                .map(|(i, view)| {
+                    let text =
+                        format!(" {} {} ", i.saturating_add(1), title(view.main().adapter()));
+                    if i == self.active {
+                        style(text).attribute(Attribute::Reverse)
+                    } else {
+                        style(text)
+                    }
+                })
+                .collect()
+        } else {
+            return None;
+        };
+        Some(shorten_line(
+            StyledLine {
+                content,
+                links: vec![],
+            },
+            width,
+        ))
+    }
+}
+
+impl Drawable for Tabs {
+    fn render(&mut self, area: &Area) -> StyledArea<String> {
+        let width = area.width();
+        match self.tab_bar(width) {
+            Some(bar) => {
+                let view_area = Area::new(width, area.height().saturating_sub(1));
+                let mut result = vec![bar];
+                result.append(&mut self.views[self.active].render(&view_area));
+                result
+            }
+            None => self.views[self.active].render(area),
+        }
+    }
+
+    fn on_event(&mut self, event: &Event) -> HandleEvent {
+        if self.input.is_some() {
+            return self.input_on_event(event);
+        }
+        if matches!(event, Event::Key(_)) {
+            self.message = None;
+        }
+        if self.views[self.active].on_event(event) == HandleEvent::Handled {
+            return HandleEvent::Handled;
+        }
+        match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('t'),
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => {
+                self.input = Some(InputLine::default());
+                HandleEvent::Handled
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Tab,
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => {
+                // arithmetic: there is always at least one view
+                #[allow(clippy::arithmetic_side_effects)]
+                {
+                    self.active = (self.active + 1) % self.views.len();
+                }
+                HandleEvent::Handled
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::BackTab,
+                ..
+            }) => {
+                self.active = self
+                    .active
+                    .checked_sub(1)
+                    .unwrap_or_else(|| self.views.len().saturating_sub(1));
+                HandleEvent::Handled
+            }
+            // The last view quits the application
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('q'),
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) if self.views.len() > 1 => {
+                self.close();
+                HandleEvent::Handled
+            }
+            _ => HandleEvent::Ignored,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::parse_view;
+
+    #[test]
+    fn parse() {
+        let range = vec![OsString::from("HEAD")];
+        let paths = vec![PathBuf::from("src")];
+        assert_eq!(
+            parse_view("", &range, &paths),
+            (range.clone(), paths.clone())
+        );
+        assert_eq!(
+            parse_view(" release/x ", &range, &paths),
+            (vec![OsString::from("release/x")], paths.clone())
+        );
+        assert_eq!(
+            parse_view("main ^v1.0 -- README.md", &range, &paths),
+            (
+                vec![OsString::from("main"), OsString::from("^v1.0")],
+                vec![PathBuf::from("README.md")]
+            )
+        );
+        assert_eq!(parse_view("--", &range, &paths), (range, vec![]));
+    }
+}
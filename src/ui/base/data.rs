@@ -17,6 +17,7 @@
 
 use crossterm::style::{style, Attribute};
 
+use crate::events;
 use crate::search::line_matches;
 use crate::ui::base::search::{Direction, Needle, SearchResult};
 use crate::ui::base::{Pos, StyledArea, StyledLine};
@@ -31,6 +32,7 @@ pub trait DataAdapter<T> {
     fn get_line(&mut self, i: Pos, selected: bool) -> StyledLine<String>;
     fn get_data(&mut self, i: Pos) -> &T;
 
+    #[allow(dead_code)]
     fn is_empty(&self) -> bool;
     fn len(&self) -> NonZeroUsize;
     fn search(&mut self, needle: Needle, start: usize) -> Receiver<SearchProgress>;
@@ -45,6 +47,7 @@ impl DataAdapter<String> for VecAdapter {
         }
         StyledLine {
             content: vec![content],
+            links: vec![],
         }
     }
 
@@ -84,31 +87,6 @@ impl VecAdapter {
     }
 }
 
-#[cfg(test)]
-mod test_vec_adapter {
-    use crossterm::style::Attribute;
-    use pretty_assertions::assert_eq;
-
-    use crate::ui::base::data::DataAdapter;
-    use crate::ui::base::data::VecAdapter;
-    use crate::ui::base::test_helpers::lore_ipsum_lines;
-
-    #[test]
-    fn foo() {
-        let adapter = &mut VecAdapter::new(lore_ipsum_lines(30));
-        assert_eq!(adapter.len().get(), 30);
-        assert!(!adapter.is_empty());
-        let line = adapter.get_line(0, false);
-        for sc in line.content {
-            assert!(!sc.style().attributes.has(Attribute::Reverse));
-        }
-        let selected_line = adapter.get_line(0, true);
-        for sc in selected_line.content {
-            assert!(sc.style().attributes.has(Attribute::Reverse));
-        }
-    }
-}
-
 pub struct StyledAreaAdapter {
     pub content: StyledArea<String>,
     pub thread: Option<JoinHandle<()>>,
@@ -148,9 +126,10 @@ impl DataAdapter<String> for StyledAreaAdapter {
             if *needle.direction() == Direction::Backward {
                 range = range.into_iter().rev().collect::<Vec<_>>();
             }
+            let query = needle.query();
             for i in range {
                 let line = &cloned[i];
-                let t = if line_matches(line, &needle) {
+                let t = if line_matches(line, &query) {
                     SearchProgress::Found(SearchResult(vec![i]))
                 } else {
                     SearchProgress::Searched(1)
@@ -159,13 +138,40 @@ impl DataAdapter<String> for StyledAreaAdapter {
                     log::error!("Failed to send search progress\n{}", err);
                     return;
                 }
+                events::wake();
             }
 
             if let Err(err) = rx.send(SearchProgress::Finished) {
                 log::error!("Failed to send SearchProgress::Finished\n{}", err);
             }
+            events::wake();
         });
         self.thread = Some(thread);
         tx
     }
 }
+
+#[cfg(test)]
+mod test_vec_adapter {
+    use crossterm::style::Attribute;
+    use pretty_assertions::assert_eq;
+
+    use crate::ui::base::data::DataAdapter;
+    use crate::ui::base::data::VecAdapter;
+    use crate::ui::base::test_helpers::lore_ipsum_lines;
+
+    #[test]
+    fn foo() {
+        let adapter = &mut VecAdapter::new(lore_ipsum_lines(30));
+        assert_eq!(adapter.len().get(), 30);
+        assert!(!adapter.is_empty());
+        let line = adapter.get_line(0, false);
+        for sc in line.content {
+            assert!(!sc.style().attributes.has(Attribute::Reverse));
+        }
+        let selected_line = adapter.get_line(0, true);
+        for sc in selected_line.content {
+            assert!(sc.style().attributes.has(Attribute::Reverse));
+        }
+    }
+}
@@ -0,0 +1,349 @@
+// Copyright (C) 2022  Bahtiar `kalkin-` Gadimov <bahtiar@gadimov.de>
+//
+// This file is part of git-log-viewer
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Frame buffer which only redraws the changed cells of the terminal.
+//!
+//! Each frame is laid out into a grid of cells & compared with the previous frame. Only the runs
+//! of changed cells are printed, wrapped in a synchronized update, so the terminal does not show
+//! half drawn frames.
+
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crossterm::cursor::MoveTo;
+use crossterm::queue;
+use crossterm::style::{ContentStyle, Print, PrintStyledContent, StyledContent};
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::Result;
+use unicode_width::UnicodeWidthChar;
+
+use super::{line_length, Area, StyledArea};
+use crate::hyperlink;
+
+/// Begin & end of a synchronized update, the terminal shows the frame only after the end
+const BEGIN_SYNCHRONIZED_UPDATE: &str = "\x1b[?2026h";
+const END_SYNCHRONIZED_UPDATE: &str = "\x1b[?2026l";
+
+/// Set when the content of the terminal was destroyed, i.e. by another program
+static INVALIDATED: AtomicBool = AtomicBool::new(false);
+
+/// Redraw the whole screen on the next render
+pub fn invalidate() {
+    INVALIDATED.store(true, Ordering::SeqCst);
+}
+
+/// Return the OSC 8 sequence starting a hyperlink to the url, or ending it for an empty url
+fn osc8(url: &str) -> Print<String> {
+    Print(format!("\x1b]8;;{}\x1b\\", url))
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Cell {
+    /// The character & following zero width characters. Empty for the second column of a wide
+    /// character.
+    symbol: String,
+    style: ContentStyle,
+    link: Option<Rc<str>>,
+}
+
+impl Cell {
+    fn blank() -> Self {
+        Self {
+            symbol: " ".to_owned(),
+            style: ContentStyle::new(),
+            link: None,
+        }
+    }
+
+    fn is_continuation(&self) -> bool {
+        self.symbol.is_empty()
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct Frame {
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Frame {
+    fn blank(area: &Area) -> Self {
+        Self {
+            rows: vec![vec![Cell::blank(); area.width()]; area.height()],
+        }
+    }
+
+    fn new(lines: &StyledArea<String>, area: &Area) -> Self {
+        let mut frame = Self::blank(area);
+        for (row, line) in frame.rows.iter_mut().zip(lines) {
+            let links: Vec<_> = hyperlink::links(line)
+                .into_iter()
+                .map(|link| (link.columns, Rc::<str>::from(link.url)))
+                .collect();
+            let mut column: usize = 0;
+            let mut last: Option<usize> = None;
+            'line: for styled_content in &line.content {
+                let style = *styled_content.style();
+                for c in styled_content.content().chars() {
+                    let width = c.width().unwrap_or_default();
+                    if width == 0 {
+                        if let Some(cell) = last.and_then(|i| row.get_mut(i)) {
+                            cell.symbol.push(c);
+                        }
+                        continue;
+                    }
+                    let end = column.saturating_add(width);
+                    if end > row.len() {
+                        break 'line;
+                    }
+                    let link = links
+                        .iter()
+                        .find(|(columns, _)| columns.contains(&column))
+                        .map(|(_, url)| Rc::clone(url));
+                    row[column] = Cell {
+                        symbol: c.to_string(),
+                        style,
+                        link: link.clone(),
+                    };
+                    for cell in &mut row[column.saturating_add(1)..end] {
+                        *cell = Cell {
+                            symbol: String::new(),
+                            style,
+                            link: link.clone(),
+                        };
+                    }
+                    last = Some(column);
+                    column = end;
+                }
+            }
+        }
+        frame
+    }
+
+    fn has_size_of(&self, other: &Self) -> bool {
+        self.rows.len() == other.rows.len()
+            && self.rows.first().map(Vec::len) == other.rows.first().map(Vec::len)
+    }
+}
+
+/// Print the cells starting at the column, grouped by style & hyperlink
+fn draw_run(out: &mut impl Write, row: usize, column: usize, cells: &[Cell]) -> Result<()> {
+    let to_u16 = |n: usize| u16::try_from(n).unwrap_or(u16::MAX);
+    queue!(out, MoveTo(to_u16(column), to_u16(row)))?;
+    let mut link: Option<&Rc<str>> = None;
+    let mut i = 0;
+    while let Some(first) = cells.get(i) {
+        if link != first.link.as_ref() {
+            queue!(out, osc8(first.link.as_deref().unwrap_or_default()))?;
+            link = first.link.as_ref();
+        }
+        let mut text = String::new();
+        while let Some(cell) = cells.get(i) {
+            if cell.style != first.style || cell.link != first.link {
+                break;
+            }
+            text.push_str(&cell.symbol);
+            i = i.saturating_add(1);
+        }
+        queue!(
+            out,
+            PrintStyledContent(StyledContent::new(first.style, text))
+        )?;
+    }
+    if link.is_some() {
+        queue!(out, osc8(""))?;
+    }
+    Ok(())
+}
+
+/// Renders frames to the terminal, printing only what changed since the last frame
+#[derive(Default)]
+pub struct Renderer {
+    previous: Option<Frame>,
+}
+
+impl Renderer {
+    /// Return `false` if the next render redraws the whole screen
+    pub fn is_valid(&self) -> bool {
+        self.previous.is_some() && !INVALIDATED.load(Ordering::SeqCst)
+    }
+
+    /// Renders the lines to `stdout`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when there are more lines than `area.height` or a line is wider than
+    /// `area.width` or writing to the terminal fails
+    pub fn render(&mut self, lines: &StyledArea<String>, area: &Area) -> Result<()> {
+        if area.height() < lines.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Height does not match area.\nExpected: {} got: {}",
+                    area.height(),
+                    lines.len()
+                ),
+            ));
+        }
+        for line in lines {
+            let width = line_length(line);
+            if area.width() < width {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Width does not match expected: {} got: {}",
+                        area.width(),
+                        width
+                    ),
+                ));
+            }
+        }
+
+        let mut stdout = io::stdout();
+        self.draw(&mut stdout, lines, area)
+    }
+
+    fn draw(
+        &mut self,
+        out: &mut impl Write,
+        lines: &StyledArea<String>,
+        area: &Area,
+    ) -> Result<()> {
+        let frame = Frame::new(lines, area);
+        queue!(out, Print(BEGIN_SYNCHRONIZED_UPDATE))?;
+        let invalidated = INVALIDATED.swap(false, Ordering::SeqCst);
+        let previous = match self.previous.take() {
+            Some(previous) if !invalidated && previous.has_size_of(&frame) => previous,
+            _ => {
+                queue!(out, Clear(ClearType::All))?;
+                Frame::blank(area)
+            }
+        };
+
+        for (y, (new, old)) in frame.rows.iter().zip(&previous.rows).enumerate() {
+            let mut x = 0;
+            while x < new.len() {
+                if new[x] == old[x] {
+                    x = x.saturating_add(1);
+                    continue;
+                }
+                // A run starts at the first column of a wide character & includes its second one
+                let mut start = x;
+                while start > 0 && new[start].is_continuation() {
+                    start = start.saturating_sub(1);
+                }
+                let mut end = x;
+                while end < new.len() && (new[end] != old[end] || new[end].is_continuation()) {
+                    end = end.saturating_add(1);
+                }
+                draw_run(out, y, start, &new[start..end])?;
+                x = end;
+            }
+        }
+
+        queue!(out, Print(END_SYNCHRONIZED_UPDATE))?;
+        out.flush()?;
+        self.previous = Some(frame);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::style::{style, Stylize};
+    use pretty_assertions::assert_eq;
+
+    use super::Renderer;
+    use crate::ui::base::{Area, Hyperlink, StyledArea, StyledLine};
+
+    fn lines(texts: &[&str]) -> StyledArea<String> {
+        texts
+            .iter()
+            .map(|text| StyledLine {
+                content: vec![style((*text).to_owned())],
+                links: vec![],
+            })
+            .collect()
+    }
+
+    fn draw(renderer: &mut Renderer, lines: &StyledArea<String>, area: &Area) -> String {
+        let mut out: Vec<u8> = vec![];
+        renderer.draw(&mut out, lines, area).expect("Drawn frame");
+        String::from_utf8(out)
+            .expect("UTF-8 output")
+            .replace("\x1b[?2026h", "")
+            .replace("\x1b[?2026l", "")
+    }
+
+    #[test]
+    fn changed_runs() {
+        let area = Area::new(6, 3);
+        let mut renderer = Renderer::default();
+        let first = draw(&mut renderer, &lines(&["foo", "bar"]), &area);
+        assert_eq!(first, "\x1b[2J\x1b[1;1Hfoo\x1b[2;1Hbar");
+
+        assert_eq!(draw(&mut renderer, &lines(&["foo", "bar"]), &area), "");
+        assert_eq!(
+            draw(&mut renderer, &lines(&["fox", "", "baz"]), &area),
+            "\x1b[1;3Hx\x1b[2;1H   \x1b[3;1Hbaz"
+        );
+
+        let resized = Area::new(5, 3);
+        assert!(draw(&mut renderer, &lines(&["fox"]), &resized).starts_with("\x1b[2J"));
+    }
+
+    #[test]
+    fn wide_characters() {
+        let area = Area::new(6, 1);
+        let mut renderer = Renderer::default();
+        draw(&mut renderer, &lines(&["a日本"]), &area);
+        assert_eq!(draw(&mut renderer, &lines(&["a日x"]), &area), "\x1b[1;4Hx ");
+        assert_eq!(
+            draw(&mut renderer, &lines(&["a日本"]), &area),
+            "\x1b[1;4H本"
+        );
+        // Replacing the first half of a wide character clears its second half
+        assert_eq!(
+            draw(&mut renderer, &lines(&["ab本"]), &area),
+            "\x1b[1;2Hb本 "
+        );
+    }
+
+    #[test]
+    fn hyperlinks() {
+        let area = Area::new(30, 1);
+        let line = StyledLine {
+            content: vec![
+                style("ab".to_owned()),
+                style("cd".to_owned()).bold(),
+                style("e see https://example.com".to_owned()),
+            ],
+            links: vec![Hyperlink {
+                columns: 1..3,
+                url: "file:///tmp".to_owned(),
+            }],
+        };
+        let mut renderer = Renderer::default();
+        let text = draw(&mut renderer, &vec![line], &area);
+        let plain = text.replace("\x1b[1m", "").replace("\x1b[0m", "");
+        assert_eq!(
+            plain,
+            "\x1b[2J\x1b[1;1Ha\x1b]8;;file:///tmp\x1b\\bc\x1b]8;;\x1b\\de\x1b[1;7Hsee\x1b[1;11H\
+             \x1b]8;;https://example.com/\x1b\\https://example.com\x1b]8;;\x1b\\"
+        );
+    }
+}
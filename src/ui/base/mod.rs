@@ -16,29 +16,33 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::cmp::Ordering;
-use std::io;
 use std::io::Write;
 use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
 
-use crossterm::cursor::{Hide, MoveDown, MoveTo, MoveToColumn, Show};
+use crossterm::cursor::{Hide, Show};
 use crossterm::event::Event;
-use crossterm::style::{PrintStyledContent, StyledContent};
+use crossterm::execute;
+use crossterm::style::StyledContent;
 use crossterm::terminal::{
-    disable_raw_mode, enable_raw_mode, size, Clear, ClearType::FromCursorDown,
-    EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
+    disable_raw_mode, enable_raw_mode, size, EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
 };
 use crossterm::Result;
-use crossterm::{execute, queue};
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
+#[allow(unused_imports)]
 pub use data::DataAdapter;
+#[allow(unused_imports)]
 pub use data::VecAdapter;
 
 pub use crate::ui::base::area::Area;
+pub use crate::ui::base::frame::Renderer;
 pub use crate::ui::list::ListWidget;
 
 mod area;
 pub mod data;
+pub mod frame;
 pub mod paging;
 pub mod search;
 #[cfg(test)]
@@ -57,11 +61,35 @@ pub enum HandleEvent {
 #[derive(Clone, Eq, Debug, PartialEq)]
 pub struct StyledLine<D: std::fmt::Display> {
     pub content: Vec<StyledContent<D>>,
+    /// Hyperlinks of the line, emitted as OSC 8 sequences by [`render`]
+    pub links: Vec<Hyperlink>,
 }
 
 impl<D: std::fmt::Display> StyledLine<D> {
     pub const fn empty() -> Self {
-        Self { content: vec![] }
+        Self {
+            content: vec![],
+            links: vec![],
+        }
+    }
+}
+
+/// A hyperlink target for the text in the display columns of a [`StyledLine`]
+#[derive(Clone, Eq, Debug, PartialEq)]
+pub struct Hyperlink {
+    pub columns: Range<usize>,
+    pub url: String,
+}
+
+impl Hyperlink {
+    /// Return the link moved by `offset` columns to the right
+    #[must_use]
+    pub fn shifted(&self, offset: usize) -> Self {
+        Self {
+            columns: self.columns.start.saturating_add(offset)
+                ..self.columns.end.saturating_add(offset),
+            url: self.url.clone(),
+        }
     }
 }
 pub type StyledArea<D> = Vec<StyledLine<D>>;
@@ -76,63 +104,6 @@ pub trait Selectable<T> {
     fn selected_item(&mut self) -> &T;
 }
 
-#[allow(clippy::ptr_arg)]
-/// Renders ui to `stdout`
-///
-/// # Panics
-///
-/// When vector len does not match the `area.height` or any [`StyledContent`] is wider then `area.width`.
-///
-/// # Errors
-///
-/// Returns an error when something goes wrong
-pub fn render(lines: &StyledArea<String>, area: &Area) -> Result<()> {
-    let mut stdout = std::io::stdout();
-
-    // Validate data {
-    if area.height() < lines.len() {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!(
-                "Height does not match area.\nExpected: {} got: {}",
-                area.height(),
-                lines.len()
-            ),
-        ));
-    }
-
-    for rows in lines {
-        let width = rows
-            .content
-            .iter()
-            .map(|x| UnicodeWidthStr::width(x.content().as_str()))
-            .sum::<usize>();
-        if area.width() < width {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "Width does not match expected: {} got: {}",
-                    area.width(),
-                    width
-                ),
-            ));
-        }
-    }
-    // End validate data }
-
-    queue!(stdout, MoveTo(0, 0), Clear(FromCursorDown))?;
-
-    for line in lines {
-        for x in line.content.iter().cloned().map(PrintStyledContent) {
-            queue!(stdout, x)?;
-        }
-        queue!(stdout, MoveDown(1), MoveToColumn(0))?;
-    }
-
-    stdout.flush()?;
-    Ok(())
-}
-
 /// Run this before starting rendering
 ///
 /// # Errors
@@ -163,6 +134,51 @@ pub fn shutdown_screen() -> Result<()> {
     Ok(())
 }
 
+/// Set from reading an input event until the UI thread handled it. The input thread does not
+/// read the terminal meanwhile, so programs started by [`suspend`] get all the input.
+static INPUT_PENDING: Mutex<bool> = Mutex::new(false);
+static INPUT_HANDLED: Condvar = Condvar::new();
+
+fn lock_input_pending() -> MutexGuard<'static, bool> {
+    INPUT_PENDING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Read the next input event, once the previous one was handled, see [`input_handled`]
+///
+/// # Errors
+///
+/// Returns an error when reading from the terminal failed
+pub fn read_input() -> Result<Event> {
+    drop(
+        INPUT_HANDLED
+            .wait_while(lock_input_pending(), |pending| *pending)
+            .unwrap_or_else(PoisonError::into_inner),
+    );
+    let event = crossterm::event::read()?;
+    *lock_input_pending() = true;
+    Ok(event)
+}
+
+/// Let the input thread read the next input event
+pub fn input_handled() {
+    *lock_input_pending() = false;
+    INPUT_HANDLED.notify_all();
+}
+
+/// Hand the terminal over to another program, i.e. an editor, while running `f`. Must only be
+/// called while handling an input event, so the input thread does not read the terminal.
+///
+/// # Errors
+///
+/// Returns an error when the screen could not be shut down or set up again
+pub fn suspend<T>(f: impl FnOnce() -> T) -> Result<T> {
+    shutdown_screen()?;
+    let result = f();
+    setup_screen("glv")?;
+    frame::invalidate();
+    Ok(result)
+}
+
 #[must_use]
 pub fn new_area() -> Area {
     Area::from(size().expect("An area"))
@@ -181,7 +197,14 @@ pub fn content_length(styled_content: &StyledContent<String>) -> usize {
 
 #[must_use]
 pub fn shorten_line(line: StyledLine<String>, width: usize) -> StyledLine<String> {
-    let mut result: StyledLine<String> = StyledLine { content: vec![] };
+    let mut result: StyledLine<String> = StyledLine {
+        content: vec![],
+        links: line
+            .links
+            .into_iter()
+            .filter(|link| link.columns.start < width)
+            .collect(),
+    };
     let mut i: usize = 0;
     for styled_content in line.content {
         let length = i.saturating_add(content_length(&styled_content));
@@ -211,3 +234,159 @@ pub fn shorten_line(line: StyledLine<String>, width: usize) -> StyledLine<String
 
     result
 }
+
+/// Display columns scrolled by one horizontal scroll step
+pub const SCROLL_STEP: usize = 8;
+
+/// Split the line before the display column. A wide character crossing the column goes to the
+/// second part, so the first one may be one column shorter.
+#[must_use]
+pub fn split_line(
+    line: StyledLine<String>,
+    column: usize,
+) -> (StyledLine<String>, StyledLine<String>) {
+    let mut left: StyledLine<String> = StyledLine::empty();
+    let mut right: StyledLine<String> = StyledLine::empty();
+    let mut i: usize = 0;
+    for styled_content in line.content {
+        let length = content_length(&styled_content);
+        if i.saturating_add(length) <= column {
+            left.content.push(styled_content);
+        } else if i >= column {
+            right.content.push(styled_content);
+        } else {
+            let style = *styled_content.style();
+            let mut head = String::new();
+            let mut tail = String::new();
+            let mut width = i;
+            for c in styled_content.content().chars() {
+                let char_width = UnicodeWidthChar::width(c).unwrap_or_default();
+                if tail.is_empty() && width.saturating_add(char_width) <= column {
+                    head.push(c);
+                    width = width.saturating_add(char_width);
+                } else {
+                    tail.push(c);
+                }
+            }
+            if !head.is_empty() {
+                left.content.push(StyledContent::new(style, head));
+            }
+            right.content.push(StyledContent::new(style, tail));
+        }
+        i = i.saturating_add(length);
+    }
+    let split = line_length(&left);
+    for link in line.links {
+        if link.columns.start < split {
+            left.links.push(Hyperlink {
+                columns: link.columns.start..link.columns.end.min(split),
+                url: link.url.clone(),
+            });
+        }
+        if link.columns.end > split {
+            right.links.push(Hyperlink {
+                columns: link.columns.start.saturating_sub(split)
+                    ..link.columns.end.saturating_sub(split),
+                url: link.url,
+            });
+        }
+    }
+    (left, right)
+}
+
+/// Drop the first display columns of the line, i.e. when it is scrolled horizontally
+#[must_use]
+pub fn skip_columns(line: StyledLine<String>, columns: usize) -> StyledLine<String> {
+    if columns == 0 {
+        return line;
+    }
+    let (left, mut right) = split_line(line, columns);
+    if line_length(&left) < columns {
+        // The second half of a wide character is left, replace it with a space
+        let first = right.content.iter_mut().find(|c| !c.content().is_empty());
+        if let Some(first) = first {
+            let rest: String = first.content().chars().skip(1).collect();
+            *first = StyledContent::new(*first.style(), format!(" {}", rest));
+            right.links = right
+                .links
+                .into_iter()
+                .filter(|link| link.columns.end > 1)
+                .map(|link| Hyperlink {
+                    columns: link.columns.start.saturating_sub(1)
+                        ..link.columns.end.saturating_sub(1),
+                    url: link.url,
+                })
+                .collect();
+        }
+    }
+    right
+}
+
+/// Wrap the line into rows not wider than `width`
+#[must_use]
+pub fn wrap_line(line: StyledLine<String>, width: usize) -> StyledArea<String> {
+    let mut result = vec![];
+    let mut rest = line;
+    while line_length(&rest) > width {
+        let (row, tail) = split_line(rest, width);
+        if row.content.is_empty() {
+            // Not even one character fits
+            rest = tail;
+            break;
+        }
+        result.push(row);
+        rest = tail;
+    }
+    result.push(rest);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::style::{style, Stylize};
+    use pretty_assertions::assert_eq;
+
+    use super::{skip_columns, split_line, wrap_line, Hyperlink, StyledLine};
+
+    fn line(texts: &[&str]) -> StyledLine<String> {
+        StyledLine {
+            content: texts.iter().map(|t| style((*t).to_owned())).collect(),
+            links: vec![],
+        }
+    }
+
+    fn texts(line: &StyledLine<String>) -> Vec<&str> {
+        line.content.iter().map(|c| c.content().as_str()).collect()
+    }
+
+    #[test]
+    fn split() {
+        let mut input = line(&["ab", "cd", "e"]);
+        input.content[1] = style("cd".to_owned()).bold();
+        input.links.push(Hyperlink {
+            columns: 1..4,
+            url: "https://example.com".to_owned(),
+        });
+        let (left, right) = split_line(input, 3);
+        assert_eq!(texts(&left), vec!["ab", "c"]);
+        assert_eq!(texts(&right), vec!["d", "e"]);
+        assert_eq!(right.content[0], style("d".to_owned()).bold());
+        assert_eq!(left.links[0].columns, 1..3);
+        assert_eq!(right.links[0].columns, 0..1);
+    }
+
+    #[test]
+    fn skip_wide_characters() {
+        assert_eq!(texts(&skip_columns(line(&["a日本"]), 1)), vec!["日本"]);
+        assert_eq!(texts(&skip_columns(line(&["a日本"]), 2)), vec![" 本"]);
+        assert_eq!(texts(&skip_columns(line(&["abc"]), 5)), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn wrap() {
+        let rows = wrap_line(line(&["abc", "de日f"]), 3);
+        let rows: Vec<Vec<&str>> = rows.iter().map(texts).collect();
+        assert_eq!(rows, vec![vec!["abc"], vec!["de"], vec!["日f"]]);
+        assert_eq!(wrap_line(line(&[]), 3), vec![line(&[])]);
+    }
+}
@@ -43,7 +43,7 @@ impl Default for Paging {
 impl Paging {
     #[cfg(test)]
     #[cfg(not(tarpaulin_include))]
-    #[allow(clippy::arithmetic)]
+    #[allow(clippy::arithmetic_side_effects)]
     /// arithmetic: this is only used during testing
     pub const fn new(page_height: Height, total_length: NonZeroUsize) -> Self {
         let bottom = page_height.get() - 1;
@@ -74,12 +74,12 @@ impl Paging {
                 self.top = top;
                 self.bottom = self.top.saturating_add(self.page_height.get());
                 self.selected = self.selected.saturating_add(self.page_height.get());
-                #[allow(clippy::arithmetic)]
+                #[allow(clippy::arithmetic_side_effects)]
                 // arithmetic: total_length is always >= 1, because it's a NonZeroUsize
                 if self.bottom >= self.total_length.get() {
                     self.bottom = self.total_length.get() - 1;
                 }
-                #[allow(clippy::arithmetic)]
+                #[allow(clippy::arithmetic_side_effects)]
                 // arithmetic: total_length is always >= 1, because it's a NonZeroUsize
                 if self.selected >= self.total_length.get() {
                     self.selected = self.total_length.get() - 1;
@@ -106,7 +106,7 @@ impl Paging {
         self.bottom = self
             .top
             .saturating_add(self.page_height.get().saturating_sub(1));
-        #[allow(clippy::arithmetic)]
+        #[allow(clippy::arithmetic_side_effects)]
         // arithmetic: total_length is always >= 1, because it's a NonZeroUsize
         if self.bottom >= self.total_length.get() {
             self.bottom = self.total_length.get() - 1;
@@ -147,7 +147,7 @@ impl Paging {
     fn select_next(&mut self) {
         self.selected = self.selected.saturating_add(1);
 
-        #[allow(clippy::arithmetic)]
+        #[allow(clippy::arithmetic_side_effects)]
         // arithmetic: total_length is always >= 1, because it's a NonZeroUsize
         if self.selected >= self.total_length.get() {
             self.selected = self.total_length.get() - 1;
@@ -20,6 +20,7 @@ use std::fmt::Debug;
 use getset::Getters;
 
 use super::data::SearchProgress;
+use crate::query::Query;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Direction {
@@ -52,9 +53,15 @@ impl Needle {
         Self {
             text: text.to_owned(),
             direction: dir,
-            ignore_case: text.chars().all(char::is_lowercase),
+            ignore_case: Query::parse(text).ignores_case(),
         }
     }
+
+    /// Parse the needle text as [`Query`], with smart case for each term
+    #[must_use]
+    pub fn query(&self) -> Query {
+        Query::parse(&self.text)
+    }
 }
 
 #[allow(clippy::module_name_repetitions)]
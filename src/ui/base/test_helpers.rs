@@ -22,3 +22,66 @@ pub fn lore_ipsum_lines(n: usize) -> Vec<String> {
     let words: Vec<&str> = sentence.split_ascii_whitespace().collect();
     words.iter().map(|&x| x.into()).collect()
 }
+
+/// A git repository in a temporary directory, which is removed when dropped
+pub struct TestRepo {
+    repo: git_wrapper::Repository,
+    path: std::path::PathBuf,
+}
+
+impl TestRepo {
+    /// Create an empty repository with `main` as the current branch
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("glv-{}-{}", name, std::process::id()));
+        if path.exists() {
+            std::fs::remove_dir_all(&path).expect("Removed old test repository");
+        }
+        std::fs::create_dir_all(&path).expect("Created test repository directory");
+        let repo = git_wrapper::Repository::create(&path).expect("Created test repository");
+        let result = Self { repo, path };
+        result.git(&["symbolic-ref", "HEAD", "refs/heads/main"]);
+        result
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Run git(1) in the repository & panic if it fails
+    pub fn git(&self, args: &[&str]) {
+        let out = self
+            .repo
+            .git()
+            .args([
+                "-c",
+                "user.name=Tester",
+                "-c",
+                "user.email=test@example.com",
+            ])
+            .args(args)
+            .output()
+            .expect("Executed git(1)");
+        assert!(out.status.success(), "git {:?} failed", args);
+    }
+
+    /// Create an empty commit
+    pub fn commit(&self, subject: &str) {
+        self.git(&["commit", "-q", "--allow-empty", "-m", subject]);
+    }
+}
+
+impl std::ops::Deref for TestRepo {
+    type Target = git_wrapper::Repository;
+
+    fn deref(&self) -> &Self::Target {
+        &self.repo
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_dir_all(&self.path) {
+            log::warn!("Failed to remove test repository: {}", err);
+        }
+    }
+}
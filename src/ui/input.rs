@@ -17,59 +17,182 @@
 
 use crate::ui::base::{Area, Drawable, HandleEvent, StyledArea, StyledLine};
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
-use crossterm::style::style;
-use unicode_truncate::UnicodeTruncateStr;
-use unicode_width::UnicodeWidthStr;
+use crossterm::style::{style, Attribute, StyledContent, Stylize};
+use unicode_segmentation::UnicodeSegmentation;
 
+/// Maximal number of remembered history entries
+const MAX_HISTORY: usize = 100;
+
+/// Single line editor with a cursor & history
 #[allow(clippy::module_name_repetitions)]
-pub struct InputLine(String);
+#[derive(Default)]
+pub struct InputLine {
+    text: String,
+    /// Byte offset of the cursor in `text`
+    cursor: usize,
+    history: Vec<String>,
+    /// Position in `history`, while browsing it
+    history_index: Option<usize>,
+    /// Text typed before browsing the history
+    draft: String,
+}
 
 impl InputLine {
+    #[must_use]
+    pub fn with_history(history: Vec<String>) -> Self {
+        Self {
+            history,
+            ..Self::default()
+        }
+    }
+
     pub const fn text(&self) -> &String {
-        &self.0
+        &self.text
+    }
+
+    pub const fn history(&self) -> &Vec<String> {
+        &self.history
+    }
+
+    /// Clear the text, but keep the history
+    pub fn clear(&mut self) {
+        self.set_text(String::new());
+        self.history_index = None;
+    }
+
+    /// Remember the current text as newest history entry
+    pub fn add_to_history(&mut self) {
+        if self.text.is_empty() {
+            return;
+        }
+        self.history.retain(|entry| *entry != self.text);
+        self.history.push(self.text.clone());
+        let overflow = self.history.len().saturating_sub(MAX_HISTORY);
+        self.history.drain(..overflow);
+        self.history_index = None;
+    }
+
+    /// Render the text with the cursor
+    #[must_use]
+    pub fn render_line(&self) -> Vec<StyledContent<String>> {
+        let (before, after) = self.text.split_at(self.cursor);
+        let mut graphemes = after.graphemes(true);
+        let under_cursor = graphemes.next().unwrap_or(" ");
+        vec![
+            style(before.to_owned()),
+            style(under_cursor.to_owned()).attribute(Attribute::Reverse),
+            style(graphemes.as_str().to_owned()),
+        ]
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.text = text;
+        self.cursor = self.text.len();
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |g| self.cursor.saturating_add(g.len()))
+    }
+
+    /// Return the start of the word before the cursor
+    fn word_start(&self) -> usize {
+        let before = self.text[..self.cursor].trim_end();
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i.saturating_add(c.len_utf8()))
+    }
+
+    fn history_prev(&mut self) {
+        let index = match self.history_index {
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.text.clone();
+                self.history.len().saturating_sub(1)
+            }
+            Some(i) => i.saturating_sub(1),
+        };
+        self.history_index = Some(index);
+        self.set_text(self.history[index].clone());
+    }
+
+    fn history_next(&mut self) {
+        if let Some(i) = self.history_index {
+            let index = i.saturating_add(1);
+            if index < self.history.len() {
+                self.history_index = Some(index);
+                self.set_text(self.history[index].clone());
+            } else {
+                self.history_index = None;
+                let draft = std::mem::take(&mut self.draft);
+                self.set_text(draft);
+            }
+        }
     }
 }
 
 impl Drawable for InputLine {
     fn render(&mut self, _area: &Area) -> StyledArea<String> {
         vec![StyledLine {
-            content: vec![style(self.0.clone())],
+            content: vec![style(self.text.clone())],
+            links: vec![],
         }]
     }
 
     fn on_event(&mut self, event: &Event) -> HandleEvent {
-        match event {
-            Event::Key(KeyEvent {
-                code: KeyCode::Char(c),
-                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
-                ..
-            }) => {
-                self.0.push(*c);
-                HandleEvent::Handled
-            }
-            Event::Key(KeyEvent {
-                code: KeyCode::Backspace,
-                modifiers: KeyModifiers::NONE,
-                ..
-            }) => {
-                let cur = UnicodeWidthStr::width(self.0.as_str());
-                #[allow(clippy::arithmetic)]
-                // arithmetic: if condition guards it
-                if cur > 0 {
-                    let string = self.0.clone();
-                    let (tmp, _) = string.unicode_truncate(cur - 1);
-                    self.0 = tmp.to_owned();
+        if let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = event
+        {
+            match (code, *modifiers) {
+                (KeyCode::Char('w'), KeyModifiers::CONTROL) => {
+                    let start = self.word_start();
+                    self.text.drain(start..self.cursor);
+                    self.cursor = start;
+                }
+                (KeyCode::Char('u'), KeyModifiers::CONTROL) => {
+                    self.text.drain(..self.cursor);
+                    self.cursor = 0;
+                }
+                (KeyCode::Char('a'), KeyModifiers::CONTROL) | (KeyCode::Home, _) => {
+                    self.cursor = 0;
+                }
+                (KeyCode::Char('e'), KeyModifiers::CONTROL) | (KeyCode::End, _) => {
+                    self.cursor = self.text.len();
+                }
+                (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    self.text.insert(self.cursor, *c);
+                    self.cursor = self.cursor.saturating_add(c.len_utf8());
+                }
+                (KeyCode::Backspace, KeyModifiers::NONE) => {
+                    let start = self.prev_boundary();
+                    self.text.drain(start..self.cursor);
+                    self.cursor = start;
                 }
-                HandleEvent::Handled
+                (KeyCode::Delete, KeyModifiers::NONE) => {
+                    let end = self.next_boundary();
+                    self.text.drain(self.cursor..end);
+                }
+                (KeyCode::Left, KeyModifiers::NONE) => self.cursor = self.prev_boundary(),
+                (KeyCode::Right, KeyModifiers::NONE) => self.cursor = self.next_boundary(),
+                (KeyCode::Up, KeyModifiers::NONE) => self.history_prev(),
+                (KeyCode::Down, KeyModifiers::NONE) => self.history_next(),
+                _ => return HandleEvent::Ignored,
             }
-            _ => HandleEvent::Ignored,
+            return HandleEvent::Handled;
         }
-    }
-}
-
-impl Default for InputLine {
-    fn default() -> Self {
-        Self("".to_owned())
+        HandleEvent::Ignored
     }
 }
 
@@ -153,6 +276,70 @@ mod test_input_widget {
         assert_eq!(input.text(), "c");
     }
 
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        })
+    }
+
+    fn type_text(input: &mut InputLine, text: &str) {
+        for c in text.chars() {
+            handle_event(input, &key(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn cursor_movement() {
+        let input = &mut InputLine::default();
+        type_text(input, "fär");
+        handle_event(input, &key(KeyCode::Left, KeyModifiers::NONE));
+        handle_event(input, &key(KeyCode::Left, KeyModifiers::NONE));
+        type_text(input, "o");
+        assert_eq!(input.text(), "foär");
+        handle_event(input, &key(KeyCode::Delete, KeyModifiers::NONE));
+        assert_eq!(input.text(), "for");
+        handle_event(input, &key(KeyCode::Home, KeyModifiers::NONE));
+        type_text(input, "<");
+        handle_event(input, &key(KeyCode::End, KeyModifiers::NONE));
+        type_text(input, ">");
+        assert_eq!(input.text(), "<for>");
+    }
+
+    #[test]
+    fn delete_word_and_line() {
+        let input = &mut InputLine::default();
+        type_text(input, "author:foo  bar ");
+        handle_event(input, &key(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        assert_eq!(input.text(), "author:foo  ");
+        handle_event(input, &key(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        assert_eq!(input.text(), "");
+        type_text(input, "foo bar");
+        handle_event(input, &key(KeyCode::Left, KeyModifiers::NONE));
+        handle_event(input, &key(KeyCode::Char('u'), KeyModifiers::CONTROL));
+        assert_eq!(input.text(), "r");
+    }
+
+    #[test]
+    fn history() {
+        let input = &mut InputLine::with_history(vec!["foo".to_owned(), "bar".to_owned()]);
+        type_text(input, "ba");
+        handle_event(input, &key(KeyCode::Up, KeyModifiers::NONE));
+        assert_eq!(input.text(), "bar");
+        handle_event(input, &key(KeyCode::Up, KeyModifiers::NONE));
+        handle_event(input, &key(KeyCode::Up, KeyModifiers::NONE));
+        assert_eq!(input.text(), "foo");
+        handle_event(input, &key(KeyCode::Down, KeyModifiers::NONE));
+        handle_event(input, &key(KeyCode::Down, KeyModifiers::NONE));
+        assert_eq!(input.text(), "ba", "Restored typed text");
+        input.clear();
+        type_text(input, "foo");
+        input.add_to_history();
+        assert_eq!(input.history(), &vec!["bar".to_owned(), "foo".to_owned()]);
+    }
+
     fn handle_event(input: &mut InputLine, event: &Event) {
         assert_eq!(input.on_event(event), HandleEvent::Handled);
     }
@@ -50,6 +50,14 @@ where
             _selected: PhantomData,
         }
     }
+
+    pub const fn main(&self) -> &Main {
+        &self.main
+    }
+
+    pub fn main_mut(&mut self) -> &mut Main {
+        &mut self.main
+    }
 }
 
 impl<Main, Aside, T> Drawable for SplitLayout<Main, Aside, T>
@@ -63,7 +71,7 @@ where
             let main_size;
 
             let horizontal_split = area.width() < 160;
-            #[allow(clippy::arithmetic)]
+            #[allow(clippy::arithmetic_side_effects)]
             // arithmetic: division by 2 is safe
             if horizontal_split {
                 aside_size = Area::new(area.width(), area.height() / 2);
@@ -89,6 +97,10 @@ where
                             .content
                             .push(StyledContent::new(ContentStyle::default(), content));
                     }
+                    let offset = line_length(right_row);
+                    right_row
+                        .links
+                        .extend(row.links.iter().map(|link| link.shifted(offset)));
                     right_row.content.append(&mut row.content);
                 }
                 result
@@ -15,12 +15,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use crossterm::event::Event;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 
 use crate::search::highlight_search_line;
 use crate::ui::base::data::{DataAdapter, SearchProgress};
 use crate::ui::base::paging::Paging;
-use crate::ui::base::{shorten_line, Area, Drawable, HandleEvent, Selectable, StyledArea};
+use crate::ui::base::{
+    line_length, shorten_line, skip_columns, wrap_line, Area, Drawable, HandleEvent, Selectable,
+    StyledArea, SCROLL_STEP,
+};
 use crate::ui::search::SearchWidget;
 use std::num::NonZeroUsize;
 use std::sync::mpsc::Receiver;
@@ -33,6 +36,10 @@ pub struct ListWidget<T> {
     paging: Paging,
     search_input: SearchWidget,
     search_progress_tx: Option<Receiver<SearchProgress>>,
+    /// Display columns scrolled to the right
+    offset: usize,
+    /// Wrap long lines instead of shortening them
+    wrap: bool,
 }
 
 impl<T> ListWidget<T> {
@@ -44,14 +51,59 @@ impl<T> ListWidget<T> {
             paging: Paging::default(),
             search_input,
             search_progress_tx: None,
+            offset: 0,
+            wrap: false,
         }
     }
 
+    /// Wrap long lines, otherwise shorten them
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    /// Return the display columns scrolled to the right
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Scroll to the right, the offset is limited by the longest line when rendering
+    pub fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+
+    /// Return the index of the selected line
+    pub const fn selected(&self) -> usize {
+        self.paging.selected()
+    }
+
+    /// Select the line & scroll to it. Only works after the list was rendered once.
+    pub fn select(&mut self, i: usize) {
+        self.paging.set_selected(i);
+    }
+
+    /// Return the wrapped lines of the page. When the selected line does not fit, the first
+    /// rows are dropped.
+    fn wrapped_page(&mut self, page_height: usize, width: usize) -> StyledArea<String> {
+        let mut rows = vec![];
+        let mut selected_end = 0;
+        for i in self.paging.top()..=self.paging.bottom() {
+            let line = self.adapter.get_line(i, i == self.paging.selected());
+            rows.extend(wrap_line(line, width));
+            if i == self.paging.selected() {
+                selected_end = rows.len();
+            }
+        }
+        rows.into_iter()
+            .skip(selected_end.saturating_sub(page_height))
+            .take(page_height)
+            .collect()
+    }
+
     fn highlight_search(&self, input: &mut StyledArea<String>) -> StyledArea<String> {
         let mut new_result = Vec::with_capacity(input.len());
-        let search_state = self.search_input.needle();
+        let query = self.search_input.needle().query();
         for row in input {
-            new_result.push(highlight_search_line(row, &search_state));
+            new_result.push(highlight_search_line(row, &query));
         }
         new_result
     }
@@ -66,7 +118,7 @@ impl<T> Selectable<T> for ListWidget<T> {
 impl<T> Drawable for ListWidget<T> {
     fn render(&mut self, area: &Area) -> StyledArea<String> {
         let mut result: StyledArea<String> = vec![];
-        #[allow(clippy::arithmetic)]
+        #[allow(clippy::arithmetic_side_effects)]
         // arithmetic: we assume that `height >= 4`.
         let page_height: NonZeroUsize = if self.search_input.is_visible() {
             NonZeroUsize::new(area.height() - 1).expect("area hight >= 1")
@@ -87,9 +139,18 @@ impl<T> Drawable for ListWidget<T> {
             self.paging.set_selected(selected.0[0]);
         }
 
-        for i in self.paging.top()..=self.paging.bottom() {
-            let line = self.adapter.get_line(i, i == self.paging.selected());
-            result.push(shorten_line(line, area.width()));
+        if self.wrap {
+            result = self.wrapped_page(page_height.get(), area.width());
+        } else {
+            let lines: StyledArea<String> = (self.paging.top()..=self.paging.bottom())
+                .map(|i| self.adapter.get_line(i, i == self.paging.selected()))
+                .collect();
+            // Do not scroll further than the end of the longest line
+            let longest = lines.iter().map(line_length).max().unwrap_or_default();
+            self.offset = self.offset.min(longest.saturating_sub(area.width()));
+            for line in lines {
+                result.push(shorten_line(skip_columns(line, self.offset), area.width()));
+            }
         }
 
         if result.len() < page_height.into() {
@@ -106,19 +167,58 @@ impl<T> Drawable for ListWidget<T> {
     }
 
     fn on_event(&mut self, event: &Event) -> HandleEvent {
-        match self.search_input.on_event(event) {
-            HandleEvent::Handled => HandleEvent::Handled,
-            HandleEvent::Ignored => self.paging.on_event(event),
+        if self.search_input.on_event(event) == HandleEvent::Handled
+            || self.paging.on_event(event) == HandleEvent::Handled
+        {
+            return HandleEvent::Handled;
+        }
+        match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Left,
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) if !self.wrap => {
+                self.offset = self.offset.saturating_sub(SCROLL_STEP);
+                HandleEvent::Handled
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Right,
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) if !self.wrap => {
+                self.offset = self.offset.saturating_add(SCROLL_STEP);
+                HandleEvent::Handled
+            }
+            _ => HandleEvent::Ignored,
         }
     }
 }
 
+#[cfg(test)]
+fn example_content() -> ListWidget<String> {
+    use crate::ui::base::test_helpers::lore_ipsum_lines;
+    let adapter = crate::ui::base::VecAdapter::new(lore_ipsum_lines(30));
+    let search_input = SearchWidget::default();
+    let paging = Paging::new(
+        NonZeroUsize::new(25).unwrap(),
+        NonZeroUsize::new(30).unwrap(),
+    );
+    ListWidget {
+        adapter: Box::new(adapter),
+        paging,
+        search_input,
+        search_progress_tx: None,
+        offset: 0,
+        wrap: false,
+    }
+}
+
 #[cfg(test)]
 mod test_list_widget {
     use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
     use pretty_assertions::assert_eq;
 
-    use crate::ui::base::{Area, Drawable, HandleEvent, ListWidget};
+    use crate::ui::base::{Area, Drawable, HandleEvent, ListWidget, VecAdapter};
     use crate::ui::list::example_content;
 
     #[test]
@@ -174,6 +274,32 @@ mod test_list_widget {
         }
     }
 
+    #[test]
+    fn horizontal_scrolling() {
+        let lines = vec!["abcdefghijklmnop".to_owned(), "short".to_owned()];
+        let list = &mut ListWidget::new(Box::new(VecAdapter::new(lines)));
+        let area = Area::new(6, 4);
+        let rows = |list: &mut ListWidget<String>| -> Vec<String> {
+            list.render(&area)
+                .iter()
+                .map(|line| line.content.iter().map(|c| c.content().as_str()).collect())
+                .collect()
+        };
+        assert_eq!(rows(list), vec!["abcde…", "short", "", ""]);
+
+        handle_event(list, KeyCode::Right);
+        assert_eq!(rows(list), vec!["ijklm…", "", "", ""]);
+        // Stops at the end of the longest line
+        handle_event(list, KeyCode::Right);
+        assert_eq!(rows(list), vec!["klmnop", "", "", ""]);
+
+        handle_event(list, KeyCode::Left);
+        assert_eq!(rows(list), vec!["cdefg…", "ort", "", ""]);
+
+        list.set_wrap(true);
+        assert_eq!(rows(list), vec!["abcdef", "ghijkl", "mnop", "short"]);
+    }
+
     fn handle_event(pager: &mut ListWidget<String>, code: KeyCode) {
         let event = Event::Key(KeyEvent {
             code,
@@ -185,19 +311,3 @@ mod test_list_widget {
         assert_eq!(pager.on_event(&event), HandleEvent::Handled);
     }
 }
-#[cfg(test)]
-fn example_content() -> ListWidget<String> {
-    use crate::ui::base::test_helpers::lore_ipsum_lines;
-    let adapter = crate::ui::base::VecAdapter::new(lore_ipsum_lines(30));
-    let search_input = SearchWidget::default();
-    let paging = Paging::new(
-        NonZeroUsize::new(25).unwrap(),
-        NonZeroUsize::new(30).unwrap(),
-    );
-    ListWidget {
-        adapter: Box::new(adapter),
-        paging,
-        search_input,
-        search_progress_tx: None,
-    }
-}
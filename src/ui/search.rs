@@ -15,9 +15,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::sync::{Mutex, PoisonError};
+
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::style::style;
 
+use crate::cache;
 use crate::ui::base::data::SearchProgress;
 use crate::ui::base::search::{Direction, Needle, SearchResult, State};
 use crate::ui::base::{search, shorten_line, Drawable, HandleEvent, StyledLine};
@@ -52,6 +55,7 @@ impl SearchWidget {
     pub fn render(&mut self, width: usize) -> StyledLine<String> {
         let mut line = StyledLine {
             content: Vec::with_capacity(3),
+            links: vec![],
         };
         match self.direction {
             Direction::Forward => {
@@ -61,7 +65,11 @@ impl SearchWidget {
                 line.content.push(style("?".to_owned()));
             }
         }
-        line.content.push(style(self.input.text().to_string()));
+        if let State::CaptureNeedle(_) = self.capture.state() {
+            line.content.append(&mut self.input.render_line());
+        } else {
+            line.content.push(style(self.input.text().to_string()));
+        }
         line.content.push(style(format!(
             "\tFound({}) / Seen({})",
             self.results.results().len(),
@@ -96,15 +104,17 @@ impl SearchWidget {
     }
 
     pub fn on_event(&mut self, event: &Event) -> HandleEvent {
+        let before = self.input.text().clone();
         match self.capture.state() {
             State::Hidden => self.hiden_state_on_event(event),
             State::CaptureNeedle(dir) => match self.input.on_event(event) {
                 HandleEvent::Handled => {
                     let text = self.input.text().clone();
-                    if !text.is_empty() {
+                    // Cursor movement does not restart the search
+                    if text != before && !text.is_empty() {
                         self.needle = Some(Needle::smart_case(&text, *dir));
+                        self.results = ResultManager::default();
                     }
-                    self.results = ResultManager::default();
                     HandleEvent::Handled
                 }
                 HandleEvent::Ignored => match event {
@@ -114,6 +124,8 @@ impl SearchWidget {
                         ..
                     }) => {
                         let text = self.input.text().clone();
+                        self.input.add_to_history();
+                        store_search_history(self.input.history());
                         self.needle = Some(Needle::smart_case(&text, *dir));
                         self.results = ResultManager::default();
                         self.capture.on_event(search::Event::Text(text));
@@ -197,6 +209,7 @@ impl SearchWidget {
                 modifiers: KeyModifiers::NONE,
                 ..
             }) => {
+                self.input = InputLine::with_history(search_history());
                 self.capture
                     .on_event(search::Event::Activate(Direction::Forward));
                 self.direction = Direction::Forward;
@@ -207,6 +220,7 @@ impl SearchWidget {
                 modifiers: KeyModifiers::NONE,
                 ..
             }) => {
+                self.input = InputLine::with_history(search_history());
                 self.direction = Direction::Backward;
                 self.capture
                     .on_event(search::Event::Activate(Direction::Backward));
@@ -234,10 +248,39 @@ impl SearchWidget {
     fn reset(&mut self) {
         self.needle = None;
         self.results = ResultManager::default();
-        self.input = InputLine::default();
+        self.input.clear();
     }
 
     pub fn is_visible(&self) -> bool {
         *self.capture.state() != search::State::Hidden
     }
 }
+
+/// The search history, read from the cache on first use
+static HISTORY: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// Return the persisted search history
+#[must_use]
+pub fn search_history() -> Vec<String> {
+    let mut history = HISTORY.lock().unwrap_or_else(PoisonError::into_inner);
+    history
+        .get_or_insert_with(|| match cache::fetch_search_history() {
+            Ok(text) => text
+                .unwrap_or_default()
+                .lines()
+                .map(ToOwned::to_owned)
+                .collect(),
+            Err(err) => {
+                log::warn!("Failed to read search history: {}", err);
+                vec![]
+            }
+        })
+        .clone()
+}
+
+pub fn store_search_history(history: &[String]) {
+    *HISTORY.lock().unwrap_or_else(PoisonError::into_inner) = Some(history.to_vec());
+    if let Err(err) = cache::store_search_history(history) {
+        log::warn!("Failed to store search history: {}", err);
+    }
+}
@@ -2,6 +2,7 @@ use crate::{commit::parse_remote_url, credentials};
 use curl::easy::Easy;
 use git_wrapper::Remote;
 use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 use url::Url;
 
 pub fn transfer(mut easy: Easy, domain: &str) -> Option<(u32, HashMap<String, String>, String)> {
@@ -86,3 +87,71 @@ pub fn find_forge_url(hash_map: &HashMap<String, Remote>) -> Option<Url> {
     }
     None
 }
+
+/// Return `path` relative to the root of the work tree. Relative paths are resolved against the
+/// work tree, `.` & `..` are removed. Returns `None` for paths outside the work tree.
+pub fn repo_relative(work_tree: &Path, path: &Path) -> Option<PathBuf> {
+    let mut result = PathBuf::new();
+    for component in work_tree.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !result.pop() {
+                    return None;
+                }
+            }
+            other => result.push(other),
+        }
+    }
+    result.strip_prefix(work_tree).ok().map(Path::to_path_buf)
+}
+
+/// Return the 64-bit FNV-1a hash of the parts as hex. Unlike `DefaultHasher` it is stable across
+/// Rust releases, so it can be used to name files in the cache.
+pub fn stable_hash<I, P>(parts: I) -> String
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<[u8]>,
+{
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    let mut hash = OFFSET_BASIS;
+    for part in parts {
+        // A byte which is not valid UTF-8 separates the parts, so ["ab", "c"] ≠ ["a", "bc"]
+        for byte in part.as_ref().iter().chain(&[0xff]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use std::path::{Path, PathBuf};
+
+    use super::{repo_relative, stable_hash};
+
+    #[test]
+    fn stable() {
+        // The cache files are named by these, they must not change between releases
+        assert_eq!(stable_hash(["glv"]), "a4818a72129ff345");
+        assert_eq!(stable_hash(["gl", "v"]), "97955ad310348780");
+    }
+
+    #[test]
+    fn relative_to_work_tree() {
+        let work_tree = Path::new("/src/glv");
+        assert_eq!(
+            repo_relative(work_tree, Path::new("./src/../README.md")),
+            Some(PathBuf::from("README.md"))
+        );
+        assert_eq!(
+            repo_relative(work_tree, Path::new("/src/glv/src/main.rs")),
+            Some(PathBuf::from("src/main.rs"))
+        );
+        assert_eq!(repo_relative(work_tree, Path::new("../other")), None);
+    }
+}